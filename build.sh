@@ -1,6 +1,7 @@
 #!/bin/bash
 set -e
 
-RUSTFLAGS='-C link-arg=-s' cargo build --target wasm32-unknown-unknown --release
+# Diagnostic logs are left out of release builds. Run `./build.sh --features debug-logs` to include them.
+RUSTFLAGS='-C link-arg=-s' cargo build --target wasm32-unknown-unknown --release "$@"
 mkdir -p ./out
 cp target/wasm32-unknown-unknown/release/*.wasm ./out/main.wasm
@@ -0,0 +1,243 @@
+use crate::*;
+
+const GAS_PER_CCC: Gas = Gas(5_000_000_000_000); // 5 TGas
+const RECEIPT_GAS_COST: Gas = Gas(2_500_000_000_000); // 2.5 TGas
+// Bytes the runtime charges for every storage record on top of its key and value
+const STORAGE_BYTES_PER_RECORD: u64 = 40;
+
+/*
+    Pure maths for what funders are charged and refunded. Nothing in here reads from `env` or
+    touches storage so the cost model can be tested on its own. Anything that comes from the
+    runtime (storage byte cost, GAS used, measured storage) is passed in by the contract methods.
+*/
+
+/// Costs paid up front for every claim of a key on top of the key's allowance
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PerClaimCosts {
+    // $NEAR sent to the claiming account
+    pub balance: Balance,
+    // Storage for the access key
+    pub access_key_storage: Balance,
+    // Storage for the longest token ID summed across every NFT asset
+    pub nft_storage: Balance,
+    // Storage deposit to register the claiming account summed across every FT asset
+    pub ft_storage: Balance,
+    // Storage for recording the claiming account if claims per account are limited
+    pub claimed_account_storage: Balance,
+}
+
+impl PerClaimCosts {
+    pub fn total(&self) -> Balance {
+        self.balance + self.access_key_storage + self.nft_storage + self.ft_storage + self.claimed_account_storage
+    }
+}
+
+/// Everything needed to work out the deposit for adding keys to a drop
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeyDepositInputs {
+    pub num_keys: u128,
+    pub claims_per_key: u64,
+    // Only charged when the drop is created
+    pub drop_fee: Balance,
+    // Summed across every key being added. Volume tiers can charge keys differently.
+    pub key_fees: Balance,
+    // Allowance attached to each key to cover all its claims
+    pub allowance_per_key: Balance,
+    // Function call deposits across every use of a key
+    pub fc_deposits_per_key: Balance,
+    // Storage measured for the drop and the keys being added
+    pub storage: Balance,
+    pub per_claim: PerClaimCosts,
+}
+
+/// Breakdown of the deposit required to add keys to a drop
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RequiredDeposit {
+    // Kept by the contract
+    pub fees: Balance,
+    // Burned as GAS when the keys are used. Whatever is left is refunded.
+    pub allowance: Balance,
+    pub storage: Balance,
+    pub fc_deposits: Balance,
+    // Per claim costs across every claim of every key
+    pub claim_costs: Balance,
+}
+
+impl RequiredDeposit {
+    pub fn total(&self) -> Balance {
+        self.fees + self.allowance + self.storage + self.fc_deposits + self.claim_costs
+    }
+}
+
+/// Deposit required to add `num_keys` keys to a drop
+pub fn required_deposit(inputs: &KeyDepositInputs) -> RequiredDeposit {
+    RequiredDeposit {
+        fees: inputs.drop_fee + inputs.key_fees,
+        allowance: inputs.allowance_per_key * inputs.num_keys,
+        storage: inputs.storage,
+        fc_deposits: inputs.fc_deposits_per_key * inputs.num_keys,
+        claim_costs: inputs.per_claim.total() * inputs.claims_per_key as u128 * inputs.num_keys,
+    }
+}
+
+/// Basis points a fee multiplier is expressed in. A multiplier of `FEE_MULTIPLIER_BASE` leaves the fee unchanged.
+pub const FEE_MULTIPLIER_BASE: u32 = 10_000;
+
+/*
+    Key fees for adding `num_keys` keys when the funder has already added `keys_before` keys. Each key is charged the
+    fee of the last tier it has reached (tiers are sorted by `min_keys`) or the base key fee if it hasn't reached one.
+*/
+pub fn tiered_key_fees(key_fee: Balance, tiers: &[VolumeTier], keys_before: u64, num_keys: u64) -> Balance {
+    let end = keys_before + num_keys;
+    let mut from = keys_before;
+    let mut fee = key_fee;
+    let mut total = 0;
+
+    for tier in tiers {
+        if tier.min_keys >= end {
+            break;
+        }
+        if tier.min_keys > from {
+            total += fee * (tier.min_keys - from) as u128;
+            from = tier.min_keys;
+        }
+        fee = tier.key_fee.0;
+    }
+
+    total + fee * (end - from) as u128
+}
+
+/// Scale a fee by a multiplier in basis points
+pub fn apply_fee_multiplier(fee: Balance, multiplier: u32) -> Balance {
+    fee * multiplier as u128 / FEE_MULTIPLIER_BASE as u128
+}
+
+/// Share of the fees credited to a referrer given the split in basis points
+pub fn referral_share(fees: Balance, split: u32) -> Balance {
+    fees * split as u128 / FEE_MULTIPLIER_BASE as u128
+}
+
+/// Extra deposit to register every claim of every key on the FT contracts. This is charged once the storage has been queried.
+pub fn ft_storage_deposit(ft_storage_per_claim: Balance, claims_per_key: u64, num_keys: u128) -> Balance {
+    ft_storage_per_claim * claims_per_key as u128 * num_keys
+}
+
+/// Cost of storing `bytes` on the contract
+pub fn storage_cost(bytes: u64, storage_byte_cost: Balance) -> Balance {
+    Balance::from(bytes) * storage_byte_cost
+}
+
+/// Cost of the storage released between two measurements. Storage that was prepaid for (but not measured) is released as well.
+pub fn storage_freed(initial_storage: u64, prepaid_storage: u64, final_storage: u64, storage_byte_cost: Balance) -> Balance {
+    storage_cost((initial_storage + prepaid_storage).saturating_sub(final_storage), storage_byte_cost)
+}
+
+/// Estimated bytes for a single storage record
+pub fn record_storage(key_len: usize, value_len: usize) -> u64 {
+    (key_len + value_len) as u64 + STORAGE_BYTES_PER_RECORD
+}
+
+/// Estimated bytes for inserting into an `UnorderedMap`. The index lookup, the key and the value are each stored in their own record.
+pub fn unordered_map_entry_storage(prefix_len: usize, key_len: usize, value_len: usize) -> u64 {
+    // prefix + 'i' + key -> index, prefix + 'k' + index -> key and prefix + 'v' + index -> value
+    record_storage(prefix_len + 1 + key_len, 8) + record_storage(prefix_len + 1 + 8, key_len) + record_storage(prefix_len + 1 + 8, value_len)
+}
+
+/// Estimated bytes for inserting into an `UnorderedSet`. The index lookup and the element are each stored in their own record.
+pub fn unordered_set_entry_storage(prefix_len: usize, element_len: usize) -> u64 {
+    // prefix + 'i' + element -> index and prefix + 'e' + index -> element
+    record_storage(prefix_len + 1 + element_len, 8) + record_storage(prefix_len + 1 + 8, element_len)
+}
+
+/// Cost of burning a given amount of GAS
+pub fn gas_cost(gas: Gas, yocto_per_gas: u128) -> Balance {
+    gas.0 as u128 * yocto_per_gas
+}
+
+/// Pessimistic allowance needed for a single claim with the given GAS attached
+pub fn base_allowance(attached_gas: Gas, yocto_per_gas: u128) -> Balance {
+    // Get the number of CCCs you can make with the attached GAS
+    let calls_with_gas = (attached_gas.0 / GAS_PER_CCC.0) as f32;
+    // Get the constant used to pessimistically calculate the required allowance
+    let pow_outcome = 1.03_f32.powf(calls_with_gas);
+
+    // Get the required GAS based on the calculated constant
+    ((attached_gas.0 + RECEIPT_GAS_COST.0) as f32 * pow_outcome + RECEIPT_GAS_COST.0 as f32) as u128 * yocto_per_gas
+}
+
+/// Allowance attached to a key so it can be claimed `claims_per_key` times
+pub fn key_allowance(attached_gas: Gas, claims_per_key: u64, yocto_per_gas: u128) -> Balance {
+    base_allowance(attached_gas, yocto_per_gas) * claims_per_key as u128
+}
+
+/// Allowance taken from a key when a claim is rejected. The GAS used so far plus an offset for the rest of the call.
+pub fn failed_claim_charge(used_gas: Gas, yocto_per_gas: u128) -> Balance {
+    gas_cost(Gas(used_gas.0 + GAS_FOR_PANIC_OFFSET.0), yocto_per_gas)
+}
+
+/// Allowance set aside for the claim that's being made. For the last use of a key, the rest is refunded.
+pub fn claim_charge(required_gas: Gas, yocto_per_gas: u128) -> Balance {
+    gas_cost(required_gas, yocto_per_gas)
+}
+
+/// Everything needed to work out the refund once a claim's callback resolves
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClaimRefundInputs {
+    // Did the transfer or account creation succeed
+    pub claim_succeeded: bool,
+    // $NEAR that was sent to the claiming account
+    pub balance: Balance,
+    // Storage released when the key was claimed
+    pub storage_freed: Balance,
+    // Storage prepaid for the asset that's no longer needed (longest token ID for NFTs)
+    pub asset_storage_released: Balance,
+    // Function call deposits for this use of the key
+    pub fc_deposit: Balance,
+    // Were the claimer's function call args accepted
+    pub fc_args_accepted: bool,
+    // Should the refund be attached to the first function call instead of going to the funder
+    pub refund_to_deposit: bool,
+}
+
+/// Where the refund for a claim goes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClaimRefund {
+    pub to_funder: Balance,
+    // Attached to the deposit of the first function call
+    pub to_deposit: Balance,
+}
+
+/// Refund for a claim once its callback resolves. If the claim failed, the balance and deposits were never sent so they're refunded.
+pub fn claim_refund(inputs: &ClaimRefundInputs) -> ClaimRefund {
+    let mut amount = inputs.storage_freed + inputs.asset_storage_released;
+    if !inputs.claim_succeeded {
+        amount += inputs.balance + inputs.fc_deposit;
+    } else if !inputs.fc_args_accepted {
+        amount += inputs.fc_deposit;
+    }
+
+    if inputs.claim_succeeded && inputs.refund_to_deposit {
+        ClaimRefund { to_funder: 0, to_deposit: amount }
+    } else {
+        ClaimRefund { to_funder: amount, to_deposit: 0 }
+    }
+}
+
+/// Everything needed to work out the refund for deleting keys from a drop
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeyDeletionInputs {
+    // Storage released by deleting the keys
+    pub storage_freed: Balance,
+    // Allowance left across every key
+    pub allowance_left: Balance,
+    // Function call deposits for the uses left across every key
+    pub fc_deposits_left: Balance,
+    // Claims left across every key
+    pub uses_left: u64,
+    pub per_claim: PerClaimCosts,
+}
+
+/// Refund for deleting keys. Everything paid for claims that will never happen is returned.
+pub fn key_deletion_refund(inputs: &KeyDeletionInputs) -> Balance {
+    inputs.storage_freed + inputs.allowance_left + inputs.fc_deposits_left + inputs.per_claim.total() * inputs.uses_left as u128
+}
@@ -0,0 +1,294 @@
+use std::fmt;
+
+use near_sdk::json_types::Base58CryptoHash;
+
+use crate::*;
+
+/// Name of the standard that the events follow
+pub const EVENT_STANDARD: &str = "linkdrop";
+/// Version of the event schema. Bump this whenever the data of any event changes.
+pub const EVENT_VERSION: &str = "1.0.0";
+
+/// Enum that represents the data type of the EventLog.
+/// The enum can either be a drop creation, key addition, etc.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+#[serde(crate = "near_sdk::serde")]
+#[non_exhaustive]
+pub enum EventLogVariant {
+    DropCreation(DropCreationLog),
+    KeyAddition(KeyAdditionLog),
+    Claim(ClaimLog),
+    ClaimFailure(ClaimFailureLog),
+    KeyDeletion(KeyDeletionLog),
+    AssetRegistration(AssetRegistrationLog),
+    AssetRefund(AssetRefundLog),
+    FunderRefund(FunderRefundLog),
+    BalanceDeposit(BalanceLog),
+    BalanceWithdrawal(BalanceLog),
+    FeeWithdrawal(FeeWithdrawalLog),
+    CodeStaged(CodeStagedLog),
+    CodeDeployment(CodeDeploymentLog),
+    RoleGrant(RoleLog),
+    RoleRevoke(RoleLog),
+    OwnershipTransfer(OwnershipTransferLog),
+    Pause(PauseLog),
+    DropPause(DropPauseLog),
+    ReferralFee(ReferralFeeLog),
+    ReferralFeeWithdrawal(FeeWithdrawalLog),
+    TokenFeePayment(TokenFeePaymentLog),
+    TokenFeeWithdrawal(TokenFeeWithdrawalLog),
+    TokenBalanceDeposit(TokenBalanceLog),
+    TokenBalanceWithdrawal(TokenBalanceWithdrawalLog),
+}
+
+/// Interface to capture data about an event
+///
+/// Arguments:
+/// * `standard`: name of standard e.g. linkdrop
+/// * `version`: e.g. 1.0.0
+/// * `event`: associate event data
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EventLog {
+    pub standard: String,
+    pub version: String,
+
+    // `flatten` to not have "event": {<EventLogVariant>} in the JSON, just have the contents of {<EventLogVariant>}.
+    #[serde(flatten)]
+    pub event: EventLogVariant,
+}
+
+impl fmt::Display for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "EVENT_JSON:{}",
+            &near_sdk::serde_json::to_string(self).map_err(|_| fmt::Error)?
+        ))
+    }
+}
+
+/// Log an event following the NEP-297 standard
+pub(crate) fn emit_event(event: EventLogVariant) {
+    let log = EventLog {
+        standard: EVENT_STANDARD.to_string(),
+        version: EVENT_VERSION.to_string(),
+        event,
+    };
+    env::log_str(&log.to_string());
+}
+
+/// A drop was created
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DropCreationLog {
+    pub funder_id: AccountId,
+    pub drop_id: U128,
+    pub num_keys: u64,
+    pub balance: U128,
+    // Type of each asset in the drop in order ("ft", "nft" or "fc")
+    pub asset_types: Vec<String>,
+    pub required_deposit: U128,
+}
+
+/// Keys were added to an existing drop
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct KeyAdditionLog {
+    pub funder_id: AccountId,
+    pub drop_id: U128,
+    pub public_keys: Vec<PublicKey>,
+    pub required_deposit: U128,
+}
+
+/// Details of an asset sent as part of a claim
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+#[serde(crate = "near_sdk::serde")]
+pub enum ClaimedAssetLog {
+    FT { ft_contract: AccountId, amount: U128 },
+    NFT { nft_contract: AccountId, token_id: String },
+    FC { methods: Vec<String> },
+}
+
+/// A key was successfully used to claim a drop. Logged once the assets have been sent.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimLog {
+    pub drop_id: U128,
+    pub public_key: PublicKey,
+    pub account_id: AccountId,
+    // Was a new account created as part of the claim
+    pub new_account: bool,
+    pub use_index: u64,
+    pub balance: U128,
+    pub assets: Vec<ClaimedAssetLog>,
+}
+
+/// A claim was rejected and the key's allowance was decremented, or the claim's transfer failed and nothing was decremented
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimFailureLog {
+    pub drop_id: U128,
+    pub public_key: PublicKey,
+    pub account_id: AccountId,
+    pub reason: String,
+    pub allowance_decremented: U128,
+}
+
+/// Keys were deleted from a drop and the funder was refunded
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct KeyDeletionLog {
+    pub funder_id: AccountId,
+    pub drop_id: U128,
+    pub public_keys: Vec<PublicKey>,
+    pub refund: U128,
+}
+
+/// FTs or NFTs were sent to the contract and registered claims for a drop
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetRegistrationLog {
+    pub drop_id: U128,
+    pub asset_index: u64,
+    pub contract_id: AccountId,
+    pub sender_id: AccountId,
+    pub num_claims_registered: u64,
+    // Amount of FTs or the token ID of the NFT that was sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+}
+
+/// FTs or NFTs registered for a drop were sent back to their sender
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetRefundLog {
+    pub drop_id: U128,
+    pub asset_index: u64,
+    pub num_refunded: u64,
+    pub success: bool,
+}
+
+/// A funder's balance was refunded as part of resolving a claim
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FunderRefundLog {
+    pub funder_id: AccountId,
+    pub amount: U128,
+}
+
+/// An account deposited into or withdrew from their balance
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceLog {
+    pub account_id: AccountId,
+    pub amount: U128,
+}
+
+/// The owner withdrew the fees collected by the contract
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeWithdrawalLog {
+    pub withdraw_to: AccountId,
+    pub amount: U128,
+    pub success: bool,
+}
+
+/// The owner staged new code for the contract
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CodeStagedLog {
+    pub code_hash: Base58CryptoHash,
+    pub deployable_at: u64,
+}
+
+/// The owner deployed the staged code
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CodeDeploymentLog {
+    pub code_hash: Base58CryptoHash,
+}
+
+/// The owner granted or revoked a role
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoleLog {
+    pub role: Role,
+    pub account_id: AccountId,
+}
+
+/// The proposed owner accepted ownership of the contract
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnershipTransferLog {
+    pub old_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+}
+
+/// A pauser paused or unpaused a category of methods
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseLog {
+    pub category: PauseCategory,
+    pub paused: bool,
+}
+
+/// The funder paused or unpaused claims on their drop
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DropPauseLog {
+    pub drop_id: U128,
+    pub paused: bool,
+}
+
+/// A referrer was credited their share of the fees for a drop
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferralFeeLog {
+    pub referrer_id: AccountId,
+    pub drop_id: U128,
+    pub amount: U128,
+}
+
+/// A funder paid for fees with a fungible token and was credited the $NEAR value
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenFeePaymentLog {
+    pub token_id: AccountId,
+    pub account_id: AccountId,
+    pub amount: U128,
+    pub credit: U128,
+}
+
+/// A fee manager withdrew the fees collected in a fungible token
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenFeeWithdrawalLog {
+    pub token_id: AccountId,
+    pub withdraw_to: AccountId,
+    pub amount: U128,
+    pub success: bool,
+}
+
+/// Fungible tokens were added to an account's token balance
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenBalanceLog {
+    pub account_id: AccountId,
+    pub token_id: AccountId,
+    pub amount: U128,
+}
+
+/// An account withdrew fungible tokens from their token balance
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenBalanceWithdrawalLog {
+    pub account_id: AccountId,
+    pub token_id: AccountId,
+    pub amount: U128,
+    pub success: bool,
+}
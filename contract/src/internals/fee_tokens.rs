@@ -0,0 +1,217 @@
+use crate::*;
+
+/// How a fungible token that can pay for fees is converted to $NEAR. Exactly one of the two must be set.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeToken {
+    // Fixed rate in yoctoNEAR per smallest unit of the token
+    pub rate: Option<U128>,
+    // Contract that quotes the rate (in yoctoNEAR per smallest unit) each time the token is sent
+    pub oracle_id: Option<AccountId>,
+}
+
+/// Fee token with the contract it's sent from
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonFeeToken {
+    pub token_id: AccountId,
+    pub rate: Option<U128>,
+    pub oracle_id: Option<AccountId>,
+}
+
+#[near_bindgen]
+impl DropZone {
+    /// Allow a fungible token to pay for fees or stop it from being accepted (by passing in None)
+    pub fn set_fee_token(&mut self, token_id: AccountId, fee_token: Option<FeeToken>) {
+        self.assert_role(Role::Operations);
+        match fee_token {
+            Some(fee_token) => {
+                require!(fee_token.rate.is_some() != fee_token.oracle_id.is_some(), "fee token must have either a rate or an oracle");
+                require!(fee_token.rate.map(|rate| rate.0 > 0).unwrap_or(true), "fee token rate must be greater than 0");
+                self.fee_tokens.insert(&token_id, &fee_token)
+            },
+            None => self.fee_tokens.remove(&token_id),
+        };
+    }
+
+    /*
+        Withdraw every fee collected in a fungible token to the passed in Account Id. The account must be registered
+        on the token contract. If the transfer fails, the fees are added back to the ledger.
+    */
+    pub fn withdraw_token_fees(&mut self, token_id: AccountId, withdraw_to: AccountId) -> Promise {
+        self.assert_role(Role::FeeManager);
+        let amount = self.token_fees_collected.remove(&token_id).expect("no fees collected for token");
+
+        ext_ft_contract::ext(token_id.clone())
+            .with_attached_deposit(1)
+            .with_static_gas(MIN_GAS_FOR_FT_TRANSFER)
+            .ft_transfer(withdraw_to.clone(), U128(amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_GAS_FOR_ON_WITHDRAW_TOKEN_FEES)
+                    .on_withdraw_token_fees(token_id, U128(amount), withdraw_to)
+            )
+    }
+
+    /// Callback for withdrawing the fees collected in a fungible token
+    #[private]
+    pub fn on_withdraw_token_fees(&mut self, token_id: AccountId, amount: U128, withdraw_to: AccountId) -> bool {
+        let result = promise_result_as_success();
+
+        emit_event(EventLogVariant::TokenFeeWithdrawal(TokenFeeWithdrawalLog {
+            token_id: token_id.clone(),
+            withdraw_to,
+            amount,
+            success: result.is_some(),
+        }));
+
+        // If something went wrong, add the fees back to the ledger
+        if result.is_none() {
+            let collected = self.token_fees_collected.get(&token_id).unwrap_or(0);
+            self.token_fees_collected.insert(&token_id, &(collected + amount.0));
+            return false
+        }
+
+        true
+    }
+
+    /*
+        Self callback once the oracle has quoted the rate for a fee payment. Returns the amount of tokens the
+        FT contract should refund. If the oracle call failed or the tokens can't be credited, every token is refunded.
+    */
+    #[private]
+    pub fn resolve_fee_payment(&mut self, token_id: AccountId, account_id: AccountId, amount: U128) -> U128 {
+        let rate = match promise_result_as_success().and_then(|bytes| near_sdk::serde_json::from_slice::<U128>(&bytes).ok()) {
+            Some(rate) if rate.0 > 0 => rate.0,
+            _ => {
+                debug_log!("Oracle didn't return a rate for {}. Refunding {} tokens", token_id, amount.0);
+                return amount
+            }
+        };
+
+        U128(self.internal_credit_fees(token_id, account_id, amount.0, rate))
+    }
+
+    /// Returns every token that can pay for fees
+    pub fn get_fee_tokens(&self) -> Vec<JsonFeeToken> {
+        self.fee_tokens.iter()
+            .map(|(token_id, fee_token)| JsonFeeToken {
+                token_id,
+                rate: fee_token.rate,
+                oracle_id: fee_token.oracle_id,
+            })
+            .collect()
+    }
+
+    /// Returns the fees (in yoctoNEAR) an account has prepaid with fungible tokens that haven't been used
+    pub fn get_fee_credit(&self, account_id: AccountId) -> U128 {
+        U128(self.fee_credits.get(&account_id).unwrap_or(0))
+    }
+
+    /// Returns the fees collected in a fungible token that haven't been withdrawn
+    pub fn get_token_fees_collected(&self, token_id: AccountId) -> U128 {
+        U128(self.token_fees_collected.get(&token_id).unwrap_or(0))
+    }
+}
+
+impl DropZone {
+    /*
+        Fungible tokens sent to pay for fees are kept as fees and their $NEAR value is credited to the account.
+        Tokens with an oracle are only credited once the oracle has quoted the rate. Tokens that can't pay for
+        fees are refunded.
+    */
+    pub(crate) fn internal_pay_fees_with_token(&mut self, account_id: AccountId, amount: U128) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        let fee_token = match self.fee_tokens.get(&token_id) {
+            Some(fee_token) => fee_token,
+            None => {
+                debug_log!("{} can't be used to pay for fees. Refunding the tokens", token_id);
+                return PromiseOrValue::Value(amount)
+            }
+        };
+
+        match (fee_token.rate, fee_token.oracle_id) {
+            (Some(rate), _) => PromiseOrValue::Value(U128(self.internal_credit_fees(token_id, account_id, amount.0, rate.0))),
+            (None, Some(oracle_id)) => ext_fee_oracle::ext(oracle_id)
+                // Query the rate with exactly this amount of GAS. No unspent GAS will be added on top.
+                .with_static_gas(GAS_FOR_FEE_ORACLE)
+                .with_unused_gas_weight(0)
+                .get_rate(token_id.clone())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(MIN_GAS_FOR_RESOLVE_FEE_PAYMENT)
+                        .resolve_fee_payment(token_id, account_id, amount)
+                ).into(),
+            (None, None) => PromiseOrValue::Value(amount),
+        }
+    }
+
+    /*
+        Add the tokens to the fees collected for the token and credit their $NEAR value to the account. The first
+        time an account is credited, the storage for their record is taken out of the credit. Returns the amount
+        to refund, which is everything if the credit can't cover the storage.
+    */
+    pub(crate) fn internal_credit_fees(&mut self, token_id: AccountId, account_id: AccountId, amount: Balance, rate: Balance) -> Balance {
+        let mut credit = match amount.checked_mul(rate) {
+            Some(credit) => credit,
+            None => {
+                debug_log!("Fee credit overflowed. Refunding the tokens");
+                return amount
+            }
+        };
+
+        let initial_storage = env::storage_usage();
+        let credited = self.fee_credits.get(&account_id).unwrap_or(0);
+        self.fee_credits.insert(&account_id, &(credited + credit));
+        let record_cost = storage_cost(env::storage_usage() - initial_storage, env::storage_byte_cost());
+        if credit <= record_cost {
+            debug_log!("Fee credit {} can't cover the storage for {}. Refunding the tokens", yocto_to_near(credit), account_id);
+            if record_cost > 0 {
+                self.fee_credits.remove(&account_id);
+            } else {
+                self.fee_credits.insert(&account_id, &credited);
+            }
+            return amount
+        }
+        credit -= record_cost;
+        self.fee_credits.insert(&account_id, &(credited + credit));
+
+        let collected = self.token_fees_collected.get(&token_id).unwrap_or(0);
+        self.token_fees_collected.insert(&token_id, &(collected + amount));
+
+        emit_event(EventLogVariant::TokenFeePayment(TokenFeePaymentLog {
+            token_id,
+            account_id,
+            amount: U128(amount),
+            credit: U128(credit),
+        }));
+        0
+    }
+
+    /// Take as much of the fees as possible out of the funder's credit and return how much was used
+    pub(crate) fn internal_use_fee_credit(&mut self, funder_id: &AccountId, fees: Balance) -> Balance {
+        let credit = match self.fee_credits.get(funder_id) {
+            Some(credit) => credit,
+            None => return 0,
+        };
+        let used = credit.min(fees);
+
+        if used == credit {
+            self.fee_credits.remove(funder_id);
+        } else {
+            self.fee_credits.insert(funder_id, &(credit - used));
+        }
+        debug_log!("Fee credit used {}", yocto_to_near(used));
+        used
+    }
+
+    /// Give back fee credit that was used for a drop that couldn't be created
+    pub(crate) fn internal_restore_fee_credit(&mut self, funder_id: &AccountId, credit: Balance) {
+        if credit == 0 {
+            return
+        }
+        let current = self.fee_credits.get(funder_id).unwrap_or(0);
+        self.fee_credits.insert(funder_id, &(current + credit));
+        debug_log!("Fee credit restored {}", yocto_to_near(credit));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::*;
+
+/// Key fee charged once a funder has added `min_keys` keys across all their drops
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VolumeTier {
+    pub min_keys: u64,
+    pub key_fee: U128,
+}
+
+/// Fees for a specific funder. These replace the global fees and volume tiers.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FunderFees {
+    // If None, the global fee is used
+    pub drop_fee: Option<U128>,
+    // If None, the global key fee and volume tiers are used
+    pub key_fee: Option<U128>,
+    // Exempt funders aren't charged any fees
+    pub exempt: bool,
+}
+
+/// Kinds of drops that can be charged different fees
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum DropType {
+    #[serde(rename = "simple")]
+    Simple,
+    #[serde(rename = "ft")]
+    FT,
+    #[serde(rename = "nft")]
+    NFT,
+    #[serde(rename = "fc")]
+    FC,
+}
+
+/// Fee multipliers for each kind of drop in basis points (10,000 leaves the fees unchanged)
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeMultipliers {
+    pub simple: u32,
+    pub ft: u32,
+    pub nft: u32,
+    pub fc: u32,
+}
+
+impl Default for FeeMultipliers {
+    fn default() -> Self {
+        Self {
+            simple: FEE_MULTIPLIER_BASE,
+            ft: FEE_MULTIPLIER_BASE,
+            nft: FEE_MULTIPLIER_BASE,
+            fc: FEE_MULTIPLIER_BASE,
+        }
+    }
+}
+
+impl FeeMultipliers {
+    pub fn for_type(&self, drop_type: DropType) -> u32 {
+        match drop_type {
+            DropType::Simple => self.simple,
+            DropType::FT => self.ft,
+            DropType::NFT => self.nft,
+            DropType::FC => self.fc,
+        }
+    }
+
+    /// Drops with several kinds of assets use the highest multiplier across them
+    pub fn for_assets(&self, assets: &[DropAsset]) -> u32 {
+        assets.iter()
+            .map(|asset| self.for_type(match asset {
+                DropAsset::FT(_) => DropType::FT,
+                DropAsset::NFT(_) => DropType::NFT,
+                DropAsset::FC(_) => DropType::FC,
+            }))
+            .max()
+            .unwrap_or(self.simple)
+    }
+}
+
+/// Fees that apply to a funder for a kind of drop
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonFunderFees {
+    pub drop_fee: U128,
+    // Fee for the next key the funder adds
+    pub key_fee: U128,
+    pub exempt: bool,
+    // Keys the funder has added across all their drops. Used for the volume tiers.
+    pub keys_added: u64,
+}
+
+/// Global fees charged to funders without an override
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonFeeSchedule {
+    pub drop_fee: U128,
+    pub key_fee: U128,
+    pub volume_tiers: Vec<VolumeTier>,
+    pub multipliers: FeeMultipliers,
+}
+
+#[near_bindgen]
+impl DropZone {
+    /// Set or remove (by passing in None) the fees for a specific funder
+    pub fn set_funder_fees(&mut self, account_id: AccountId, funder_fees: Option<FunderFees>) {
+        self.assert_role(Role::Operations);
+        match funder_fees {
+            Some(funder_fees) => self.funder_fees.insert(&account_id, &funder_fees),
+            None => self.funder_fees.remove(&account_id),
+        };
+    }
+
+    /*
+        Set the key fee for funders that have added at least `min_keys` keys across all their drops. Tiers must be sorted
+        by `min_keys` and the fees can't go up as the volume increases. Passing in an empty list removes the tiers.
+    */
+    pub fn set_volume_tiers(&mut self, volume_tiers: Vec<VolumeTier>) {
+        self.assert_role(Role::Operations);
+        for tiers in volume_tiers.windows(2) {
+            require!(tiers[0].min_keys < tiers[1].min_keys, "volume tiers must be sorted by min_keys");
+            require!(tiers[0].key_fee.0 >= tiers[1].key_fee.0, "volume tier fees can't increase");
+        }
+        self.volume_tiers = volume_tiers;
+    }
+
+    /// Set the fee multipliers for each kind of drop in basis points
+    pub fn set_fee_multipliers(&mut self, multipliers: FeeMultipliers) {
+        self.assert_role(Role::Operations);
+        self.fee_multipliers = multipliers;
+    }
+
+    /// Returns the global fees, volume tiers and multipliers
+    pub fn get_fee_schedule(&self) -> JsonFeeSchedule {
+        JsonFeeSchedule {
+            drop_fee: U128(self.drop_fee),
+            key_fee: U128(self.key_fee),
+            volume_tiers: self.volume_tiers.clone(),
+            multipliers: self.fee_multipliers.clone(),
+        }
+    }
+
+    /// Returns the fees set for a specific funder
+    pub fn get_funder_fees(&self, account_id: AccountId) -> Option<FunderFees> {
+        self.funder_fees.get(&account_id)
+    }
+
+    /// Returns the fees that would apply to a funder creating a drop of the given type (defaults to a simple drop)
+    pub fn get_fees_for_funder(&self, account_id: AccountId, drop_type: Option<DropType>) -> JsonFunderFees {
+        let multiplier = self.fee_multipliers.for_type(drop_type.unwrap_or(DropType::Simple));
+        let keys_added = self.keys_for_funder.get(&account_id).unwrap_or(0);
+        let (drop_fee, key_fee) = self.internal_fees_for(&account_id, multiplier, keys_added, 1);
+
+        JsonFunderFees {
+            drop_fee: U128(drop_fee),
+            key_fee: U128(key_fee),
+            exempt: self.funder_fees.get(&account_id).map(|fees| fees.exempt).unwrap_or(false),
+            keys_added,
+        }
+    }
+}
+
+impl DropZone {
+    /// Drop fee and key fees (summed across `num_keys` keys) for a funder that has already added `keys_before` keys
+    pub(crate) fn internal_fees_for(&self, funder_id: &AccountId, multiplier: u32, keys_before: u64, num_keys: u64) -> (Balance, Balance) {
+        let (drop_fee, key_fees) = match self.funder_fees.get(funder_id) {
+            Some(FunderFees { exempt: true, .. }) => return (0, 0),
+            Some(FunderFees { drop_fee, key_fee, .. }) => (
+                drop_fee.map(|fee| fee.0).unwrap_or(self.drop_fee),
+                key_fee.map(|fee| fee.0 * num_keys as u128)
+                    .unwrap_or_else(|| tiered_key_fees(self.key_fee, &self.volume_tiers, keys_before, num_keys))
+            ),
+            None => (self.drop_fee, tiered_key_fees(self.key_fee, &self.volume_tiers, keys_before, num_keys)),
+        };
+
+        (apply_fee_multiplier(drop_fee, multiplier), apply_fee_multiplier(key_fees, multiplier))
+    }
+
+    /// Add to the number of keys a funder has added and return how many they had added before
+    pub(crate) fn internal_record_keys_for_funder(&mut self, funder_id: &AccountId, num_keys: u64) -> u64 {
+        let keys_before = self.keys_for_funder.get(funder_id).unwrap_or(0);
+        self.keys_for_funder.insert(funder_id, &(keys_before + num_keys));
+        keys_before
+    }
+
+    /// Take back keys counted for a drop that couldn't be created. The record is removed once no keys are left.
+    pub(crate) fn internal_unrecord_keys_for_funder(&mut self, funder_id: &AccountId, num_keys: u64) {
+        let keys_left = self.keys_for_funder.get(funder_id).unwrap_or(0).saturating_sub(num_keys);
+        if keys_left == 0 {
+            self.keys_for_funder.remove(funder_id);
+        } else {
+            self.keys_for_funder.insert(funder_id, &keys_left);
+        }
+    }
+}
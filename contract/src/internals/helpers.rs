@@ -1,24 +1,75 @@
 use crate::*;
 
-const GAS_PER_CCC: Gas = Gas(5_000_000_000_000); // 5 TGas
-const RECEIPT_GAS_COST: Gas = Gas(2_500_000_000_000); // 2.5 TGas
-
 /// Used to generate a unique prefix in our storage collections (this is to avoid data collisions)
 pub(crate) fn hash_account_id(account_id: &String) -> CryptoHash {
     env::sha256_array(account_id.as_bytes())
 }
 
+/// Password hashes for a key and for each of its uses
+pub(crate) type KeyPasswords = (Option<Vec<u8>>, Option<HashMap<u64, Vec<u8>>>);
+
+/// Get the password hashes the funder passed in for the key at a given index. Per-use passwords are keyed by their use number (starting at 1).
+pub(crate) fn key_passwords(
+    passwords_per_key: &Option<Vec<Option<Base64VecU8>>>,
+    passwords_per_use: &Option<Vec<Option<Vec<JsonPasswordForUse>>>>,
+    key_index: usize,
+    max_claims_per_key: u64
+) -> KeyPasswords {
+    let pw_per_key = passwords_per_key.as_ref()
+        .and_then(|pws| pws[key_index].clone())
+        .map(|pw| pw.0);
+
+    let pw_per_use = passwords_per_use.as_ref()
+        .and_then(|pws| pws[key_index].clone())
+        .map(|pws| pws.into_iter().map(|pw| {
+            require!(pw.key_use > 0 && pw.key_use <= max_claims_per_key, "password key_use must be between 1 and max_claims_per_key");
+            (pw.key_use, pw.pw.0)
+        }).collect::<HashMap<u64, Vec<u8>>>());
+
+    (pw_per_key, pw_per_use)
+}
+
+/// Make sure the passwords passed in line up with the public keys being added
+pub(crate) fn assert_passwords_match_keys(
+    passwords_per_key: &Option<Vec<Option<Base64VecU8>>>,
+    passwords_per_use: &Option<Vec<Option<Vec<JsonPasswordForUse>>>>,
+    num_keys: usize
+) {
+    if let Some(pws) = passwords_per_key {
+        require!(pws.len() == num_keys, "passwords_per_key must have one entry per public key");
+    }
+    if let Some(pws) = passwords_per_use {
+        require!(pws.len() == num_keys, "passwords_per_use must have one entry per public key");
+    }
+}
+
 impl DropZone {
+    /// Panic if the predecessor isn't the owner
+    pub(crate) fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "predecessor != owner"
+        );
+    }
+
+    /// Panic if the predecessor can't act as the given role
+    pub(crate) fn assert_role(&self, role: Role) {
+        require!(
+            self.has_role(role, env::predecessor_account_id()),
+            &format!("predecessor doesn't have the {} role", role.as_str())
+        );
+    }
+
+    /// Panic if the methods in the given category are paused
+    pub(crate) fn assert_not_paused(&self, category: PauseCategory) {
+        require!(!self.paused.is_paused(category), &format!("contract is paused for {}", category.as_str()));
+    }
+
     /// Used to calculate the base allowance needed given attached GAS
     pub(crate) fn calculate_base_allowance(&self, attached_gas: Gas) -> u128 {    
-        // Get the number of CCCs you can make with the attached GAS
-        let calls_with_gas = (attached_gas.0 / GAS_PER_CCC.0) as f32;
-        // Get the constant used to pessimistically calculate the required allowance
-        let pow_outcome = 1.03_f32.powf(calls_with_gas);
-        
-        // Get the required GAS based on the calculated constant
-        let required_allowance = ((attached_gas.0 + RECEIPT_GAS_COST.0) as f32 * pow_outcome + RECEIPT_GAS_COST.0 as f32) as u128 * self.yocto_per_gas;
-        env::log_str(&format!("{} calls with {} attached GAS. Pow outcome: {}. Required Allowance: {}", calls_with_gas, attached_gas.0, pow_outcome, required_allowance));
+        let required_allowance = base_allowance(attached_gas, self.yocto_per_gas);
+        debug_log!("{} attached GAS. Required Allowance: {}", attached_gas.0, required_allowance);
 
         required_allowance
     }
@@ -72,165 +123,401 @@ impl DropZone {
         }
     }
 
-    /// Internal function for executing the callback code either straight up or using `.then` for a passed in promise
+    /// Increment the funder's balance by the amount to refund
+    pub(crate) fn internal_refund_funder(&mut self, funder_id: &AccountId, amount_to_refund: Balance) {
+        if amount_to_refund == 0 {
+            return;
+        }
+
+        let mut cur_funder_balance = self.user_balances.get(funder_id).expect("No funder balance found");
+        cur_funder_balance += amount_to_refund;
+        self.user_balances.insert(funder_id, &cur_funder_balance);
+
+        emit_event(EventLogVariant::FunderRefund(FunderRefundLog {
+            funder_id: funder_id.clone(),
+            amount: U128(amount_to_refund),
+        }));
+    }
+
+    /// Internal function for executing the callback code either straight up or using `.then` for a passed in promise.
+    /// Every asset in the drop gets its own callback. Only one callback per claim refunds the funder for the balance and storage.
+    /// A joint promise can't be used as a callback so the callbacks are chained one after another. Each callback returns whether
+    /// the claim succeeded, which the next callback reads in place of the result of the promise.
     pub(crate) fn internal_execute(
         &mut self,
         drop_data: Drop, 
+        drop_id: DropId,
         account_id: AccountId, 
         storage_freed: u128,
-        token_id: Option<String>,
-        storage_for_longest: Option<u128>,
-        promise: Option<Promise>
+        token_ids: Vec<Option<String>>,
+        use_index: u64,
+        fc_args: Option<String>,
+        claim: ClaimLog,
+        mut promise: Option<Promise>
     ) {        
-        // Determine what callback we should use depending on the drop type
-        match drop_data.drop_type {
-            DropType::FC(data) => {
-                // If we're dealing with a promise, execute the callback
-                if let Some(promise) = promise {
-                    promise.then(
-                        // Call on_claim_fc with all unspent GAS + min gas for on claim. No attached deposit.
-                        Self::ext(env::current_account_id())
+        // The access key storage and the storage freed are refunded once per claim
+        let storage_used = ACCESS_KEY_STORAGE + storage_freed;
+        // The claim is resolved once, by the callback that refunds the funder
+        let mut claim = Some(claim);
+
+        // Simple drops have no assets so the only thing to do is refund the funder
+        if drop_data.assets.is_empty() {
+            // If we're dealing with a promise, execute the callback
+            if let Some(promise) = promise {
+                promise.then(
+                    Self::ext(env::current_account_id())
                         .with_static_gas(MIN_GAS_FOR_ON_CLAIM)
-                        .on_claim_fc(
+                        .on_claim_simple(
+                            // Account ID that funded the linkdrop
+                            drop_data.funder_id,
+                            // Balance associated with the linkdrop
+                            drop_data.balance,
+                            // How much storage was freed when the key was claimed
+                            U128(storage_used),
+                            // Claim being resolved
+                            claim,
+                            // Executing the function and treating it like a callback.
+                            false
+                        )
+                );
+            } else {
+                // We're not dealing with a promise so we simply execute the function.
+                self.on_claim_simple(
+                    // Account ID that funded the linkdrop
+                    drop_data.funder_id, 
+                    // Balance associated with the linkdrop
+                    drop_data.balance, 
+                    // How much storage was freed when the key was claimed
+                    U128(storage_used),
+                    // Claim being resolved
+                    claim,
+                    // Executing the function and treating it NOT like a callback. 
+                    true
+                );
+            }
+            return;
+        }
+
+        // The first asset refunds the funder unless a function call wants the refund attached to its deposit
+        let refund_index = drop_data.assets.iter().position(|asset| matches!(
+            asset, 
+            DropAsset::FC(data) if data.refund_to_deposit.unwrap_or(false)
+        )).unwrap_or(0);
+
+        for (index, (asset, token_id)) in drop_data.assets.into_iter().zip(token_ids).enumerate() {
+            // Only the refund callback gets the balance and storage. Every other callback refunds only its own asset.
+            let (balance, storage_used) = if index == refund_index {(drop_data.balance, storage_used)} else {(U128(0), 0)};
+            let claim = if index == refund_index {claim.take()} else {None};
+            let funder_id = drop_data.funder_id.clone();
+
+            // Determine what callback we should use depending on the asset type
+            match asset {
+                DropAsset::FC(data) => {
+                    // Only pass in the methods scheduled for the current use of the key
+                    let data = data.for_use(use_index);
+
+                    // If we're dealing with a promise, chain the callback after it
+                    if let Some(previous) = promise.take() {
+                        promise = Some(previous.then(
+                            Self::ext(env::current_account_id())
+                                .with_static_gas(MIN_GAS_FOR_ON_CLAIM)
+                                .on_claim_fc(
+                                    // Account ID that claimed the linkdrop
+                                    account_id.clone(),
+                                    // Account ID that funded the linkdrop
+                                    funder_id,
+                                    // Balance associated with the linkdrop
+                                    balance,
+                                    // How much storage was freed when the key was claimed
+                                    U128(storage_used),
+                                    // FC Data
+                                    data,
+                                    // Drop ID that the claimed key belongs to
+                                    U128(drop_id),
+                                    // Which use of the key was claimed
+                                    use_index,
+                                    // Fields passed in by the claimer
+                                    fc_args.clone(),
+                                    // Claim being resolved. Only passed into the callback that refunds the funder.
+                                    claim,
+                                    // Executing the function and treating it like a callback.
+                                    false
+                                )
+                        ));
+                    } else {
+                        // We're not dealing with a promise so we simply execute the function.
+                        self.on_claim_fc(
                             // Account ID that claimed the linkdrop
-                            account_id, 
+                            account_id.clone(), 
                             // Account ID that funded the linkdrop
-                            drop_data.funder_id, 
+                            funder_id, 
                             // Balance associated with the linkdrop
-                            drop_data.balance, 
+                            balance, 
                             // How much storage was freed when the key was claimed
-                            storage_freed,
+                            U128(storage_used),
                             // FC Data
                             data,
-                            // Executing the function and treating it like a callback. 
-                            false
-                        )
-                    );
-                } else {
-                    // We're not dealing with a promise so we simply execute the function.
-                    self.on_claim_fc(
-                        // Account ID that claimed the linkdrop
-                        account_id, 
-                        // Account ID that funded the linkdrop
-                        drop_data.funder_id, 
-                        // Balance associated with the linkdrop
-                        drop_data.balance, 
-                        // How much storage was freed when the key was claimed
-                        storage_freed,
-                        // FC Data
-                        data,
-                        // Executing the function and treating it NOT like a callback. 
-                        true
-                    );
-                }
-            },
-            DropType::NFT(data) => {
-                // If we're dealing with a promise, execute the callback
-                if let Some(promise) = promise {
-                    promise.then(
-                        // Call on_claim_nft with all unspent GAS + min gas for on claim. No attached deposit.
-                        Self::ext(env::current_account_id())
-                        .with_static_gas(MIN_GAS_FOR_ON_CLAIM)
-                        .on_claim_nft(
+                            // Drop ID that the claimed key belongs to
+                            U128(drop_id),
+                            // Which use of the key was claimed
+                            use_index,
+                            // Fields passed in by the claimer
+                            fc_args.clone(),
+                            // Claim being resolved. Only passed into the callback that refunds the funder.
+                            claim,
+                            // Executing the function and treating it NOT like a callback. 
+                            true
+                        );
+                    }
+                },
+                DropAsset::NFT(data) => {
+                    // If we're dealing with a promise, chain the callback after it
+                    if let Some(previous) = promise.take() {
+                        promise = Some(previous.then(
+                            Self::ext(env::current_account_id())
+                                .with_static_gas(MIN_GAS_FOR_ON_CLAIM)
+                                .on_claim_nft(
+                                    // Account ID that claimed the linkdrop
+                                    account_id.clone(),
+                                    // Account ID that funded the linkdrop
+                                    funder_id,
+                                    // Balance associated with the linkdrop
+                                    balance,
+                                    // How much storage was freed when the key was claimed
+                                    U128(storage_used),
+                                    // How much storage was prepaid to cover the longest token ID being inserted.
+                                    U128(data.storage_for_longest),
+                                    // Sender of the NFT
+                                    data.nft_sender,
+                                    // Contract where the NFT is stored
+                                    data.nft_contract,
+                                    // Token ID for the NFT
+                                    token_id.expect("no token ID found"),
+                                    // Claim being resolved. Only passed into the callback that refunds the funder.
+                                    claim,
+                                    // Executing the function and treating it like a callback.
+                                    false
+                                )
+                        ));
+                    } else {
+                        // We're not dealing with a promise so we simply execute the function.
+                        self.on_claim_nft(
                             // Account ID that claimed the linkdrop
-                            account_id, 
+                            account_id.clone(), 
                             // Account ID that funded the linkdrop
-                            drop_data.funder_id, 
+                            funder_id, 
                             // Balance associated with the linkdrop
-                            drop_data.balance, 
+                            balance, 
                             // How much storage was freed when the key was claimed
-                            storage_freed,
+                            U128(storage_used),
                             // How much storage was prepaid to cover the longest token ID being inserted.
-                            storage_for_longest.expect("no storage for longest token Id found"),
+                            U128(data.storage_for_longest),
                             // Sender of the NFT
                             data.nft_sender,
                             // Contract where the NFT is stored
                             data.nft_contract,
                             // Token ID for the NFT
                             token_id.expect("no token ID found"),
-                            // Executing the function and treating it like a callback. 
-                            false
-                        )
-                    );
-                } else {
-                    // We're not dealing with a promise so we simply execute the function.
-                    self.on_claim_nft(
-                        // Account ID that claimed the linkdrop
-                        account_id, 
-                        // Account ID that funded the linkdrop
-                        drop_data.funder_id, 
-                        // Balance associated with the linkdrop
-                        drop_data.balance, 
-                        // How much storage was freed when the key was claimed
-                        storage_freed,
-                        // How much storage was prepaid to cover the longest token ID being inserted.
-                        storage_for_longest.expect("no storage for longest token Id found"),
-                        // Sender of the NFT
-                        data.nft_sender,
-                        // Contract where the NFT is stored
-                        data.nft_contract,
-                        // Token ID for the NFT
-                        token_id.expect("no token ID found"),
-                        // Executing the function and treating it NOT like a callback. 
-                        true
-                    );
-                }
-            },
-            DropType::FT(data) => {
-                // If we're dealing with a promise, execute the callback
-                if let Some(promise) = promise {
-                    promise.then(
-                        // Call on_claim_ft with all unspent GAS + min gas for on claim. No attached deposit.
-                        Self::ext(env::current_account_id())
-                        .with_static_gas(MIN_GAS_FOR_ON_CLAIM)
-                        .on_claim_ft(
+                            // Claim being resolved. Only passed into the callback that refunds the funder.
+                            claim,
+                            // Executing the function and treating it NOT like a callback. 
+                            true
+                        );
+                    }
+                },
+                DropAsset::FT(data) => {
+                    // If we're dealing with a promise, chain the callback after it
+                    if let Some(previous) = promise.take() {
+                        promise = Some(previous.then(
+                            Self::ext(env::current_account_id())
+                                .with_static_gas(MIN_GAS_FOR_ON_CLAIM)
+                                .on_claim_ft(
+                                    // Account ID that claimed the linkdrop
+                                    account_id.clone(),
+                                    // Account ID that funded the linkdrop
+                                    funder_id,
+                                    // Balance associated with the linkdrop
+                                    balance,
+                                    // How much storage was freed when the key was claimed
+                                    U128(storage_used),
+                                    // FT Data to be used
+                                    data,
+                                    // Claim being resolved. Only passed into the callback that refunds the funder.
+                                    claim,
+                                    // Executing the function and treating it like a callback.
+                                    false
+                                )
+                        ));
+                    } else {
+                        // We're not dealing with a promise so we simply execute the function.
+                        self.on_claim_ft(
                             // Account ID that claimed the linkdrop
-                            account_id, 
+                            account_id.clone(), 
                             // Account ID that funded the linkdrop
-                            drop_data.funder_id, 
+                            funder_id, 
                             // Balance associated with the linkdrop
-                            drop_data.balance, 
+                            balance, 
                             // How much storage was freed when the key was claimed
-                            storage_freed,
+                            U128(storage_used),
                             // FT Data to be used
                             data,
-                            // Executing the function and treating it like a callback. 
-                            false
-                        )
-                    );
-                } else {
-                    // We're not dealing with a promise so we simply execute the function.
-                    self.on_claim_ft(
-                        // Account ID that claimed the linkdrop
-                        account_id, 
-                        // Account ID that funded the linkdrop
-                        drop_data.funder_id, 
-                        // Balance associated with the linkdrop
-                        drop_data.balance, 
-                        // How much storage was freed when the key was claimed
-                        storage_freed,
-                        // FT Data to be used
-                        data,
-                        // Executing the function and treating it NOT like a callback. 
-                        true
-                    );
+                            // Claim being resolved. Only passed into the callback that refunds the funder.
+                            claim,
+                            // Executing the function and treating it NOT like a callback. 
+                            true
+                        );
+                    }
                 }
+            };
+        }
+    }
+
+    /*
+        Random number for a draw made while claiming. The block's random seed is mixed with the drop, the key, the use
+        of the key, the asset and a nonce that's incremented on every draw so no two draws get the same number. The seed
+        is produced by the block's validators, who can still influence the result, so draws shouldn't decide amounts
+        worth manipulating.
+    */
+    pub(crate) fn internal_random_u64(&mut self, drop_id: DropId, public_key: &PublicKey, use_index: u64, asset_index: u64) -> u64 {
+        let mut seed = env::random_seed();
+        seed.extend_from_slice(&drop_id.to_le_bytes());
+        seed.extend_from_slice(public_key.as_bytes());
+        seed.extend_from_slice(&use_index.to_le_bytes());
+        seed.extend_from_slice(&asset_index.to_le_bytes());
+        seed.extend_from_slice(&self.random_nonce.to_le_bytes());
+        self.random_nonce += 1;
+
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&env::sha256_array(&seed)[..8]);
+        u64::from_le_bytes(bytes)
+    }
+
+    /*
+        Resolve a claim once the $NEAR transfer or account creation it depends on has finished and log the outcome.
+        If it failed, the claim no longer counts towards the drop's total claims or the claiming account's claims.
+        The key's use isn't given back. The storage prepaid for recording the claiming account was already released by the claim.
+    */
+    pub(crate) fn internal_resolve_claim(&mut self, claim: Option<ClaimLog>, claim_succeeded: bool) {
+        let claim = match claim {
+            Some(claim) if claim_succeeded => {
+                emit_event(EventLogVariant::Claim(claim));
+                return
             },
-            DropType::Simple => {
-                promise.unwrap().then(
-                    // Call on_claim_simple with all unspent GAS + min gas for on claim. No attached deposit.
-                    Self::ext(env::current_account_id())
-                    .with_static_gas(MIN_GAS_FOR_ON_CLAIM)
-                    .on_claim_simple(
-                        // Account ID that funded the linkdrop
-                        drop_data.funder_id, 
-                        // Balance associated with the linkdrop
-                        drop_data.balance, 
-                        // How much storage was freed when the key was claimed
-                        storage_freed,
-                    )
-                );
-            }
+            Some(claim) => claim,
+            None => return
+        };
+        emit_claim_failure(claim.drop_id.0, &claim.public_key, &claim.account_id, "transfer failed", 0);
+
+        // The drop is gone if this was its last key so there are no limits left to roll back
+        let mut drop = match self.drop_for_id.get(&claim.drop_id.0) {
+            Some(drop) => drop,
+            None => return
         };
+        drop.total_claims = drop.total_claims.saturating_sub(1);
+        // The record is kept so its storage stays covered by what the claim released of the prepaid storage
+        if drop.drop_config.max_claims_per_account.is_some() {
+            let claims = drop.claims_per_account.get(&claim.account_id).unwrap_or(0);
+            drop.claims_per_account.insert(&claim.account_id, &claims.saturating_sub(1));
+        }
+        self.drop_for_id.insert(&claim.drop_id.0, &drop);
+        debug_log!("Claim by {} failed. Drop {} has {} total claims", claim.account_id, claim.drop_id.0, drop.total_claims);
+    }
+}
+
+/// Whether the transfer or account creation a claim callback was chained after went through. Callbacks chained after
+/// another `on_claim_*` callback get the `bool` it returned. A transfer has no return value so any successful result without one counts.
+pub(crate) fn promise_claim_succeeded() -> bool {
+    match env::promise_result(0) {
+        PromiseResult::Successful(value) => near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(true),
+        _ => false
     }
 }
+
+/// Costs paid for every claim of a key in an existing drop. Function call deposits can differ for each use of a key so they're calculated with `fc_deposits_for_uses_left`.
+pub(crate) fn per_claim_costs(assets: &Vec<DropAsset>, drop_config: &DropConfig, balance: Balance) -> PerClaimCosts {
+    let mut costs = PerClaimCosts {
+        balance,
+        access_key_storage: ACCESS_KEY_STORAGE,
+        claimed_account_storage: claimed_account_storage_per_claim(drop_config),
+        ..Default::default()
+    };
+
+    for asset in assets {
+        match asset {
+            DropAsset::NFT(data) => costs.nft_storage += storage_cost(data.storage_for_longest as u64, env::storage_byte_cost()),
+            DropAsset::FT(data) => costs.ft_storage += data.ft_storage.0,
+            DropAsset::FC(_) => {}
+        }
+    }
+    costs
+}
+
+/// Details of a claim with every asset being sent. This is logged by the callbacks once the claim has succeeded.
+pub(crate) fn claim_log(drop: &Drop, drop_id: DropId, account_id: &AccountId, new_account: bool, token_ids: &Vec<Option<String>>, use_index: u64) -> ClaimLog {
+    let assets = drop.assets.iter().zip(token_ids).map(|(asset, token_id)| match asset {
+        DropAsset::FT(data) => ClaimedAssetLog::FT {
+            ft_contract: data.ft_contract.clone(),
+            amount: data.ft_balance,
+        },
+        DropAsset::NFT(data) => ClaimedAssetLog::NFT {
+            nft_contract: data.nft_contract.clone(),
+            token_id: token_id.clone().unwrap_or_default(),
+        },
+        DropAsset::FC(data) => ClaimedAssetLog::FC {
+            methods: data.methods_for_use(use_index).unwrap_or_default().into_iter()
+                .map(|method_data| format!("{}:{}", method_data.receiver, method_data.method))
+                .collect(),
+        },
+    }).collect();
+
+    ClaimLog {
+        drop_id: U128(drop_id),
+        public_key: env::signer_account_pk(),
+        account_id: account_id.clone(),
+        new_account,
+        use_index,
+        balance: drop.balance,
+        assets,
+    }
+}
+
+/// Emit an event for a claim that was rejected and decremented the key's allowance or whose transfer failed
+pub(crate) fn emit_claim_failure(drop_id: DropId, public_key: &PublicKey, account_id: &AccountId, reason: &str, allowance_decremented: Balance) {
+    emit_event(EventLogVariant::ClaimFailure(ClaimFailureLog {
+        drop_id: U128(drop_id),
+        public_key: public_key.clone(),
+        account_id: account_id.clone(),
+        reason: reason.to_string(),
+        allowance_decremented: U128(allowance_decremented),
+    }));
+}
+
+/// Storage cost prepaid for each claim to record the claiming account. Only charged if the drop limits claims per account.
+pub(crate) fn claimed_account_storage_per_claim(drop_config: &DropConfig) -> Balance {
+    if drop_config.max_claims_per_account.is_some() {
+        storage_cost(CLAIMED_ACCOUNT_STORAGE, env::storage_byte_cost())
+    } else {
+        0
+    }
+}
+
+/// GAS the keys in a drop must attach. Every asset after the first is resolved in its own callback which needs its own minimum GAS
+/// as well as the GAS burnt scheduling it.
+pub(crate) fn gas_for_assets(num_assets: usize, fc_data: &[FCData]) -> Gas {
+    // Straight executes can only be specified if the function call is the only asset
+    if let Some(gas) = fc_data.iter().find_map(|data| data.gas_if_straight_execute) {
+        return gas + GAS_OFFSET_IF_FC_EXECUTE;
+    }
+
+    let gas = Gas(ATTACHED_GAS_FROM_WALLET.0 + (MIN_GAS_FOR_ON_CLAIM.0 + GAS_FOR_CHAINING_ON_CLAIM.0) * (num_assets.max(1) as u64 - 1));
+    require!(gas <= MAX_GAS_ATTACHABLE, "too many assets in a single drop");
+    gas
+}
+
+/// Sum the deposits of every function call asset in a drop for the last `num_uses` uses of a key that has `max_uses` claims in total
+pub(crate) fn fc_deposits_for_uses_left(assets: &[DropAsset], max_uses: u64, num_uses: u64) -> Balance {
+    assets.iter().map(|asset| match asset {
+        DropAsset::FC(data) => data.deposit_for_uses_left(max_uses, num_uses),
+        _ => 0
+    }).sum()
+}
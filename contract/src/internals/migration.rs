@@ -0,0 +1,444 @@
+use near_sdk::IntoStorageKey;
+
+use crate::*;
+
+/// Version of the contract state written by this code. Bump this and add a variant to `VersionedDropZone` whenever `DropZone` changes.
+pub const STATE_VERSION: u32 = 2;
+
+/// Tag written in front of every drop stored by this code. This is the index of `VersionedDrop::V2`.
+const CURRENT_DROP_TAG: u8 = 1;
+
+// Key the contract state is stored under by near_bindgen
+const STATE_KEY: &[u8] = b"STATE";
+// Key the version of the contract state is stored under. The baseline contract never wrote it.
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+/*
+    Layouts of the contract state, drops and key usages written by the baseline contract (v1). These are frozen
+    copies of the deployed structs and must never change, even as the live structs they were copied from do.
+*/
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DropZoneV1 {
+    pub owner_id: AccountId,
+    pub linkdrop_contract: AccountId,
+    pub drop_id_for_pk: UnorderedMap<PublicKey, DropId>,
+    pub drop_for_id: LookupMap<DropId, DropV1>,
+    pub drop_ids_for_funder: LookupMap<AccountId, UnorderedSet<DropId>>,
+    pub drop_fee: u128,
+    pub key_fee: u128,
+    pub fees_collected: u128,
+    pub user_balances: LookupMap<AccountId, Balance>,
+    pub nonce: DropId,
+    pub yocto_per_gas: u128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DropV1 {
+    pub funder_id: AccountId,
+    pub pks: UnorderedMap<PublicKey, KeyUsageV1>,
+    pub balance: U128,
+    pub drop_type: DropTypeV1,
+    pub drop_config: DropConfigV1,
+    // Simple and FC drops count down the claims left. FT and NFT drops count the claims with assets registered.
+    pub num_claims_registered: u64,
+    pub required_gas_attached: Gas,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum DropTypeV1 {
+    Simple,
+    NFT(NFTDataV1),
+    FT(FTDataV1),
+    FC(FCDataV1),
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct KeyUsageV1 {
+    pub num_uses: u64,
+    pub last_used: u64,
+    pub allowance: u128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DropConfigV1 {
+    pub max_claims_per_key: u64,
+    pub start_timestamp: Option<u64>,
+    pub usage_interval: Option<u64>,
+    pub refund_if_claim: Option<bool>,
+    pub only_call_claim: Option<bool>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct FTDataV1 {
+    pub ft_contract: AccountId,
+    pub ft_sender: AccountId,
+    pub ft_balance: U128,
+    pub ft_storage: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct NFTDataV1 {
+    pub nft_sender: AccountId,
+    pub nft_contract: AccountId,
+    pub longest_token_id: String,
+    pub storage_for_longest: Balance,
+    pub token_ids: UnorderedSet<String>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct FCDataV1 {
+    pub receiver: AccountId,
+    pub method: String,
+    pub args: String,
+    pub deposit: U128,
+    pub refund_to_deposit: Option<bool>,
+    pub claimed_account_field: Option<String>,
+    pub gas_if_straight_execute: Option<Gas>,
+}
+
+impl From<KeyUsageV1> for KeyUsage {
+    fn from(key_usage: KeyUsageV1) -> Self {
+        KeyUsage {
+            num_uses: key_usage.num_uses,
+            last_used: key_usage.last_used,
+            last_used_block: 0,
+            last_used_epoch: 0,
+            claims_in_epoch: 0,
+            allowance: key_usage.allowance,
+            pw_per_key: None,
+            pw_per_use: None,
+        }
+    }
+}
+
+impl From<DropConfigV1> for DropConfig {
+    fn from(config: DropConfigV1) -> Self {
+        DropConfig {
+            max_claims_per_key: config.max_claims_per_key,
+            start_timestamp: config.start_timestamp,
+            end_timestamp: None,
+            start_block: None,
+            end_block: None,
+            usage_interval: config.usage_interval,
+            usage_interval_blocks: None,
+            max_claims_per_epoch: None,
+            max_total_claims: None,
+            max_claims_per_account: None,
+            refund_if_claim: config.refund_if_claim,
+            only_call_claim: config.only_call_claim,
+        }
+    }
+}
+
+impl From<FCDataV1> for FCData {
+    fn from(data: FCDataV1) -> Self {
+        FCData {
+            methods: vec![MethodData {
+                receiver: data.receiver,
+                method: data.method,
+                args: data.args,
+                deposit: data.deposit,
+                // The baseline attached the straight execute GAS (or nothing) on top of an even share of the unspent GAS
+                min_gas: data.gas_if_straight_execute,
+                gas_weight: None,
+            }],
+            methods_per_use: None,
+            chain_methods: None,
+            refund_to_deposit: data.refund_to_deposit,
+            claimed_account_field: data.claimed_account_field,
+            drop_id_field: None,
+            key_field: None,
+            funder_id_field: None,
+            key_use_field: None,
+            timestamp_field: None,
+            user_args: None,
+            gas_if_straight_execute: data.gas_if_straight_execute,
+        }
+    }
+}
+
+impl DropV1 {
+    /// Upgrade the drop to the current layout. The new collections are prefixed the same way `create_drop` prefixes them.
+    pub fn into_current(self, drop_id: DropId) -> Drop {
+        let num_keys = self.pks.len();
+        let max_claims_per_key = self.drop_config.max_claims_per_key;
+
+        let (assets, num_claims_registered) = match self.drop_type {
+            DropTypeV1::Simple => (vec![], self.num_claims_registered),
+            DropTypeV1::FC(data) => (vec![DropAsset::FC(data.into())], self.num_claims_registered),
+            // The registered claims move to the asset. The drop keeps the most claims its keys could have left.
+            DropTypeV1::FT(data) => (vec![DropAsset::FT(FTData {
+                ft_contract: data.ft_contract,
+                ft_sender: data.ft_sender,
+                ft_balance: data.ft_balance,
+                ft_storage: data.ft_storage,
+                num_claims_registered: self.num_claims_registered,
+                amount_tiers: None,
+            })], num_keys * max_claims_per_key),
+            DropTypeV1::NFT(data) => (vec![DropAsset::NFT(NFTData {
+                nft_sender: data.nft_sender,
+                nft_contract: data.nft_contract,
+                longest_token_id: data.longest_token_id,
+                storage_for_longest: data.storage_for_longest,
+                token_ids: data.token_ids,
+                num_claims_registered: self.num_claims_registered,
+            })], num_keys * max_claims_per_key),
+        };
+
+        Drop {
+            // The keys are read through `StoredKeyUsage` so the baseline values don't need rewriting here
+            pks: UnorderedMap::try_from_slice(&self.pks.try_to_vec().unwrap()).unwrap(),
+            balance: self.balance,
+            assets,
+            drop_config: self.drop_config.into(),
+            num_claims_registered,
+            required_gas_attached: self.required_gas_attached,
+            total_claims: 0,
+            claims_per_account: LookupMap::new(StorageKey::ClaimsPerAccountForDrop {
+                account_id_hash: hash_account_id(&format!("claims-{}{}", drop_id, self.funder_id)),
+            }),
+            allowlist: LookupSet::new(StorageKey::AllowlistForDrop {
+                account_id_hash: hash_account_id(&format!("allow-{}{}", drop_id, self.funder_id)),
+            }),
+            allowlist_len: 0,
+            denylist: LookupSet::new(StorageKey::DenylistForDrop {
+                account_id_hash: hash_account_id(&format!("deny-{}{}", drop_id, self.funder_id)),
+            }),
+            new_account_suffix: None,
+            paused: false,
+            pending_refunds: 0,
+            funder_id: self.funder_id,
+        }
+    }
+}
+
+/*
+    Key usage as it's stored in a drop's key map. Keys created by the baseline contract are still stored in its
+    layout until they're written again. That layout is always exactly 32 bytes long and the current one never is,
+    so the two are told apart by the length of the stored value. Only ever decode this from a whole stored value.
+*/
+pub struct StoredKeyUsage(pub KeyUsage);
+
+impl BorshDeserialize for StoredKeyUsage {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        if buf.len() == 32 {
+            return Ok(StoredKeyUsage(<KeyUsageV1 as BorshDeserialize>::deserialize(buf)?.into()));
+        }
+        Ok(StoredKeyUsage(<KeyUsage as BorshDeserialize>::deserialize(buf)?))
+    }
+}
+
+impl BorshSerialize for StoredKeyUsage {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        BorshSerialize::serialize(&self.0, writer)
+    }
+}
+
+impl Drop {
+    /// Get the usage of a key in the drop in the current layout
+    pub fn get_key_usage(&self, pk: &PublicKey) -> Option<KeyUsage> {
+        // The map's layout doesn't depend on its value type so it can be read back with the stored values
+        let stored: UnorderedMap<PublicKey, StoredKeyUsage> = UnorderedMap::try_from_slice(&self.pks.try_to_vec().unwrap()).unwrap();
+        stored.get(pk).map(|key_usage| key_usage.0)
+    }
+
+    /// Remove a key from the drop and return its usage in the current layout
+    pub fn remove_key_usage(&mut self, pk: &PublicKey) -> Option<KeyUsage> {
+        self.pks.remove_raw(&pk.try_to_vec().unwrap())
+            .map(|bytes| StoredKeyUsage::try_from_slice(&bytes).expect("unable to deserialize key usage").0)
+    }
+}
+
+/// Every layout a drop has been stored with. The variant index is written in front of the drop.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedDrop {
+    // Never stored with a tag. These only live in the legacy map.
+    V1(DropV1),
+    V2(Drop),
+}
+
+/*
+    Map of drop IDs to drops. Drops are stored with a version tag so new layouts can be added without making old
+    records unreadable. Drops stored by the baseline contract live in the legacy map and are moved across (upgraded)
+    the first time they're written. Reads (and views) upgrade them in memory only.
+*/
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DropMap {
+    prefix: Vec<u8>,
+    legacy: LookupMap<DropId, DropV1>,
+}
+
+impl DropMap {
+    pub fn new<S: IntoStorageKey, L: IntoStorageKey>(prefix: S, legacy_prefix: L) -> Self {
+        Self {
+            prefix: prefix.into_storage_key(),
+            legacy: LookupMap::new(legacy_prefix),
+        }
+    }
+
+    fn raw_key(&self, drop_id: &DropId) -> Vec<u8> {
+        let mut key = self.prefix.clone();
+        key.extend_from_slice(&drop_id.to_le_bytes());
+        key
+    }
+
+    fn decode(drop_id: &DropId, bytes: &[u8]) -> Drop {
+        match VersionedDrop::try_from_slice(bytes).expect("unable to deserialize drop") {
+            VersionedDrop::V1(drop) => drop.into_current(*drop_id),
+            VersionedDrop::V2(drop) => drop,
+        }
+    }
+
+    /// Get the drop in its current layout
+    pub fn get(&self, drop_id: &DropId) -> Option<Drop> {
+        match env::storage_read(&self.raw_key(drop_id)) {
+            Some(bytes) => Some(Self::decode(drop_id, &bytes)),
+            None => self.legacy.get(drop_id).map(|drop| drop.into_current(*drop_id)),
+        }
+    }
+
+    /// Store the drop with the current version tag. If it was stored by the baseline contract, the old record is removed.
+    pub fn insert(&mut self, drop_id: &DropId, drop: &Drop) {
+        let mut bytes = vec![CURRENT_DROP_TAG];
+        drop.serialize(&mut bytes).expect("unable to serialize drop");
+
+        if !env::storage_write(&self.raw_key(drop_id), &bytes) {
+            self.legacy.remove(drop_id);
+        }
+    }
+
+    /// Remove the drop and return it in its current layout
+    pub fn remove(&mut self, drop_id: &DropId) -> Option<Drop> {
+        if env::storage_remove(&self.raw_key(drop_id)) {
+            let bytes = env::storage_get_evicted().expect("unable to read evicted drop");
+            return Some(Self::decode(drop_id, &bytes));
+        }
+
+        self.legacy.remove(drop_id).map(|drop| drop.into_current(*drop_id))
+    }
+}
+
+/// Every layout the contract state has been stored with
+pub enum VersionedDropZone {
+    V1(DropZoneV1),
+    V2(DropZone),
+}
+
+impl VersionedDropZone {
+    /// Read the contract state in the layout given by the stored version
+    pub fn read() -> Self {
+        let bytes = env::storage_read(STATE_KEY).expect("contract is not initialized");
+
+        match read_state_version() {
+            1 => VersionedDropZone::V1(DropZoneV1::try_from_slice(&bytes).expect("unable to deserialize v1 state")),
+            2 => VersionedDropZone::V2(DropZone::try_from_slice(&bytes).expect("unable to deserialize v2 state")),
+            version => env::panic_str(&format!("unknown state version {}", version)),
+        }
+    }
+
+    pub fn owner_id(&self) -> &AccountId {
+        match self {
+            VersionedDropZone::V1(contract) => &contract.owner_id,
+            VersionedDropZone::V2(contract) => &contract.owner_id,
+        }
+    }
+
+    /// Upgrade the contract state to the current layout. Drops and keys are upgraded lazily as they're written.
+    pub fn into_current(self) -> DropZone {
+        match self {
+            VersionedDropZone::V1(contract) => contract.into(),
+            VersionedDropZone::V2(contract) => contract,
+        }
+    }
+}
+
+/// Version of the stored contract state. State without a stored version was written by the baseline contract.
+pub(crate) fn read_state_version() -> u32 {
+    env::storage_read(STATE_VERSION_KEY)
+        .map(|bytes| u32::try_from_slice(&bytes).expect("unable to deserialize state version"))
+        .unwrap_or(1)
+}
+
+/// Record that the contract state is stored in the current layout
+pub(crate) fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.try_to_vec().unwrap());
+}
+
+impl From<DropZoneV1> for DropZone {
+    fn from(contract: DropZoneV1) -> Self {
+        let DropZoneV1 {
+            owner_id,
+            linkdrop_contract,
+            drop_id_for_pk,
+            drop_for_id: _,
+            drop_ids_for_funder,
+            drop_fee,
+            key_fee,
+            fees_collected,
+            user_balances,
+            nonce,
+            yocto_per_gas,
+        } = contract;
+
+        DropZone {
+            owner_id,
+            linkdrop_contract,
+            drop_id_for_pk,
+            // The baseline drops are kept where they are and read through the legacy map
+            drop_for_id: DropMap::new(StorageKey::VersionedDropsForId, StorageKey::DropsForId),
+            drop_ids_for_funder,
+            drop_fee,
+            key_fee,
+            fees_collected,
+            user_balances,
+            nonce,
+            yocto_per_gas,
+            upgrade_delay: 0,
+            staged_code: None,
+            code_hash: None,
+            // No roles have been granted. The owner can still act as every role.
+            roles: LookupMap::new(StorageKey::Roles),
+            pending_owner_id: None,
+            paused: PauseFlags::default(),
+            funder_fees: LookupMap::new(StorageKey::FunderFees),
+            volume_tiers: vec![],
+            fee_multipliers: FeeMultipliers::default(),
+            // Keys added before the upgrade don't count towards the volume tiers
+            keys_for_funder: LookupMap::new(StorageKey::KeysForFunder),
+            referral_split: 0,
+            referral_fees: LookupMap::new(StorageKey::ReferralFees),
+            fee_tokens: UnorderedMap::new(StorageKey::FeeTokens),
+            fee_credits: LookupMap::new(StorageKey::FeeCredits),
+            token_fees_collected: LookupMap::new(StorageKey::TokenFeesCollected),
+            token_balances: LookupMap::new(StorageKey::TokenBalances),
+            random_nonce: 0,
+        }
+    }
+}
+
+#[near_bindgen]
+impl DropZone {
+    /*
+        Upgrade the contract state after new code has been deployed. Can be called by the owner or by the
+        contract itself (i.e in the same batch as the deploy). If the state is already current, nothing changes.
+    */
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = VersionedDropZone::read();
+        let predecessor = env::predecessor_account_id();
+        require!(
+            &predecessor == state.owner_id() || predecessor == env::current_account_id(),
+            "predecessor != owner"
+        );
+
+        let contract = state.into_current();
+        write_state_version();
+        debug_log!("Migrated contract state to version {}", STATE_VERSION);
+        contract
+    }
+
+    /// Returns the version of the contract state
+    pub fn get_state_version(&self) -> u32 {
+        read_state_version()
+    }
+}
@@ -1,9 +1,22 @@
 pub mod ext_traits;
+pub mod fee_tokens;
+pub mod fees;
 pub mod helpers;
+pub mod migration;
 pub mod owner;
+pub mod pause;
+pub mod referrals;
+pub mod roles;
 pub mod storage;
+pub mod token_balances;
+pub mod upgrade;
 
 pub use ext_traits::*;
-pub use owner::*;
-pub use storage::*;
-pub(crate) use helpers::*;
\ No newline at end of file
+pub use fee_tokens::*;
+pub use fees::*;
+pub use migration::*;
+pub use pause::*;
+pub use roles::*;
+pub use upgrade::*;
+pub(crate) use helpers::*;
+pub(crate) use referrals::*;
\ No newline at end of file
@@ -5,45 +5,50 @@ use crate::*;
 impl DropZone {
     /// Set the desired linkdrop contract to interact with
     pub fn set_contract(&mut self, linkdrop_contract: AccountId) {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner_id,
-            "predecessor != owner"
-        );
+        self.assert_owner();
         self.linkdrop_contract = linkdrop_contract;
     }
 
-    /// Set the desired linkdrop contract to interact with
+    /// Set the price per unit of GAS used to charge for claims
     pub fn set_gas_price(&mut self, yocto_per_gas: u128) {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner_id,
-            "predecessor != owner"
-        );
+        self.assert_role(Role::Operations);
         self.yocto_per_gas = yocto_per_gas;
     }
 
+    /// Set the fees charged per drop and per key. Only the fees passed in are changed.
+    pub fn set_fees(&mut self, drop_fee: Option<U128>, key_fee: Option<U128>) {
+        self.assert_role(Role::Operations);
+        if let Some(drop_fee) = drop_fee {
+            self.drop_fee = drop_fee.0;
+        }
+        if let Some(key_fee) = key_fee {
+            self.key_fee = key_fee.0;
+        }
+    }
+
     /// Withdraw the fees collected to the passed in Account Id
     pub fn withdraw_fees(&mut self, withdraw_to: AccountId) -> Promise {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner_id,
-            "predecessor != owner"
-        );
+        self.assert_role(Role::FeeManager);
         let amount = self.fees_collected;
         self.fees_collected = 0;
 
-        Promise::new(withdraw_to).transfer(amount).then(
+        Promise::new(withdraw_to.clone()).transfer(amount).then(
             Self::ext(env::current_account_id())
-                .on_withdraw_fees(amount)
+                .on_withdraw_fees(amount, withdraw_to)
         )
     }
 
     /// Callback for withdrawing fees on the contract
     #[private]
-    pub fn on_withdraw_fees(&mut self, fees_collected: u128) -> bool {
+    pub fn on_withdraw_fees(&mut self, fees_collected: u128, withdraw_to: AccountId) -> bool {
         let result = promise_result_as_success();
 
+        emit_event(EventLogVariant::FeeWithdrawal(FeeWithdrawalLog {
+            withdraw_to,
+            amount: U128(fees_collected),
+            success: result.is_some(),
+        }));
+
         // If something went wrong, set the fees collected again
         if result.is_none() {
             self.fees_collected += fees_collected;
@@ -0,0 +1,113 @@
+use crate::*;
+
+/// Groups of methods that can be paused together during an incident
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum PauseCategory {
+    // `create_drop` and `add_to_drop`
+    Creation,
+    // `claim` and `create_account_and_claim`
+    Claims,
+    // `ft_on_transfer` and `nft_on_transfer`
+    AssetIntake,
+    // `withdraw_from_balance`, `withdraw_referral_fees` and `withdraw_token_balance`
+    Withdrawals,
+}
+
+impl PauseCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PauseCategory::Creation => "creation",
+            PauseCategory::Claims => "claims",
+            PauseCategory::AssetIntake => "asset_intake",
+            PauseCategory::Withdrawals => "withdrawals",
+        }
+    }
+}
+
+/// Which categories are currently paused
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseFlags {
+    pub creation: bool,
+    pub claims: bool,
+    pub asset_intake: bool,
+    pub withdrawals: bool,
+}
+
+impl PauseFlags {
+    fn flag(&mut self, category: PauseCategory) -> &mut bool {
+        match category {
+            PauseCategory::Creation => &mut self.creation,
+            PauseCategory::Claims => &mut self.claims,
+            PauseCategory::AssetIntake => &mut self.asset_intake,
+            PauseCategory::Withdrawals => &mut self.withdrawals,
+        }
+    }
+
+    pub fn is_paused(&self, category: PauseCategory) -> bool {
+        match category {
+            PauseCategory::Creation => self.creation,
+            PauseCategory::Claims => self.claims,
+            PauseCategory::AssetIntake => self.asset_intake,
+            PauseCategory::Withdrawals => self.withdrawals,
+        }
+    }
+}
+
+#[near_bindgen]
+impl DropZone {
+    /// Stop every method in a category from being called until it's unpaused
+    pub fn pause(&mut self, category: PauseCategory) {
+        self.internal_set_paused(category, true);
+    }
+
+    /// Allow the methods in a category to be called again
+    pub fn unpause(&mut self, category: PauseCategory) {
+        self.internal_set_paused(category, false);
+    }
+
+    /*
+        Funder can pause their own drop. While paused, every claim on the drop fails without charging the key's
+        allowance. Keys can still be added, deleted and have assets registered to them.
+    */
+    pub fn pause_drop(&mut self, drop_id: DropId) {
+        self.internal_set_drop_paused(drop_id, true);
+    }
+
+    /// Allow the keys in a paused drop to be claimed again
+    pub fn unpause_drop(&mut self, drop_id: DropId) {
+        self.internal_set_drop_paused(drop_id, false);
+    }
+
+    /// Returns which categories are currently paused
+    pub fn get_paused(&self) -> PauseFlags {
+        self.paused.clone()
+    }
+
+    fn internal_set_paused(&mut self, category: PauseCategory, paused: bool) {
+        self.assert_role(Role::Pauser);
+        let flag = self.paused.flag(category);
+        require!(*flag != paused, if paused {"category is already paused"} else {"category isn't paused"});
+        *flag = paused;
+
+        emit_event(EventLogVariant::Pause(PauseLog {
+            category,
+            paused,
+        }));
+    }
+
+    fn internal_set_drop_paused(&mut self, drop_id: DropId, paused: bool) {
+        let mut drop = self.drop_for_id.get(&drop_id).expect("no drop found for ID");
+        require!(drop.funder_id == env::predecessor_account_id(), "only funder can pause a drop");
+        require!(drop.paused != paused, if paused {"drop is already paused"} else {"drop isn't paused"});
+        drop.paused = paused;
+        self.drop_for_id.insert(&drop_id, &drop);
+
+        emit_event(EventLogVariant::DropPause(DropPauseLog {
+            drop_id: U128(drop_id),
+            paused,
+        }));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::*;
+
+#[near_bindgen]
+impl DropZone {
+    /// Set the share of the fees (in basis points) that referrers passed into `create_drop` and `add_to_drop` are credited
+    pub fn set_referral_split(&mut self, referral_split: u32) {
+        self.assert_role(Role::Operations);
+        require!(referral_split <= FEE_MULTIPLIER_BASE, "referral split can't be more than 10000 basis points");
+        self.referral_split = referral_split;
+    }
+
+    /// Withdraw the referral fees credited to the predecessor
+    pub fn withdraw_referral_fees(&mut self) -> Promise {
+        self.assert_not_paused(PauseCategory::Withdrawals);
+        let referrer_id = env::predecessor_account_id();
+        let amount = self.referral_fees.remove(&referrer_id).expect("no referral fees to withdraw");
+
+        Promise::new(referrer_id.clone()).transfer(amount).then(
+            Self::ext(env::current_account_id())
+                .on_withdraw_referral_fees(U128(amount), referrer_id)
+        )
+    }
+
+    /// Callback for withdrawing referral fees
+    #[private]
+    pub fn on_withdraw_referral_fees(&mut self, amount: U128, referrer_id: AccountId) -> bool {
+        let result = promise_result_as_success();
+
+        emit_event(EventLogVariant::ReferralFeeWithdrawal(FeeWithdrawalLog {
+            withdraw_to: referrer_id.clone(),
+            amount,
+            success: result.is_some(),
+        }));
+
+        // If something went wrong, credit the referrer again
+        if result.is_none() {
+            let fees = self.referral_fees.get(&referrer_id).unwrap_or(0);
+            self.referral_fees.insert(&referrer_id, &(fees + amount.0));
+            return false
+        }
+
+        true
+    }
+
+    /// Returns the referral fees credited to an account that haven't been withdrawn
+    pub fn get_referral_fees(&self, account_id: AccountId) -> U128 {
+        U128(self.referral_fees.get(&account_id).unwrap_or(0))
+    }
+
+    /// Returns the share of the fees (in basis points) credited to referrers
+    pub fn get_referral_split(&self) -> u32 {
+        self.referral_split
+    }
+}
+
+impl DropZone {
+    /*
+        Add the fees for a drop to the fees collected. If there's a referrer, their share is credited to them instead.
+        The first time a referrer is credited, the storage for their record is taken out of their share. If their
+        share can't cover it, the contract keeps the fees.
+    */
+    pub(crate) fn internal_collect_fees(&mut self, funder_id: &AccountId, fees: Balance, referrer: Option<AccountId>, drop_id: DropId) {
+        assert_valid_referrer(funder_id, &referrer);
+        let mut share = 0;
+        if let Some(referrer_id) = referrer {
+            share = referral_share(fees, self.referral_split);
+
+            let initial_storage = env::storage_usage();
+            let credited = self.referral_fees.get(&referrer_id).unwrap_or(0);
+            self.referral_fees.insert(&referrer_id, &(credited + share));
+            let record_cost = storage_cost(env::storage_usage() - initial_storage, env::storage_byte_cost());
+
+            if share > record_cost {
+                share -= record_cost;
+                self.referral_fees.insert(&referrer_id, &(credited + share));
+                emit_event(EventLogVariant::ReferralFee(ReferralFeeLog {
+                    referrer_id,
+                    drop_id: U128(drop_id),
+                    amount: U128(share),
+                }));
+            } else {
+                debug_log!("Referral share {} can't cover the storage for {}", yocto_to_near(share), referrer_id);
+                share = 0;
+                if record_cost > 0 {
+                    self.referral_fees.remove(&referrer_id);
+                } else {
+                    self.referral_fees.insert(&referrer_id, &credited);
+                }
+            }
+        }
+
+        self.fees_collected += fees - share;
+        debug_log!("Fees collected {}. Referral share {}", yocto_to_near(fees - share), yocto_to_near(share));
+    }
+}
+
+/// Funders can't be credited a share of their own fees
+pub(crate) fn assert_valid_referrer(funder_id: &AccountId, referrer: &Option<AccountId>) {
+    require!(referrer.as_ref() != Some(funder_id), "funder can't refer themselves");
+}
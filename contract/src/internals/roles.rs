@@ -0,0 +1,122 @@
+use crate::*;
+
+/// Privileged roles the owner can grant. The owner can always act as every role.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    // Can withdraw the fees collected
+    FeeManager,
+    // Can change the GAS price, fees and linkdrop contract
+    Operations,
+    // Can stage and deploy new code
+    Upgrader,
+    // Can pause the contract
+    Pauser,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::FeeManager => "fee_manager",
+            Role::Operations => "operations",
+            Role::Upgrader => "upgrader",
+            Role::Pauser => "pauser",
+        }
+    }
+}
+
+#[near_bindgen]
+impl DropZone {
+    /// Give an account a role. Only the owner can grant roles.
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_owner();
+
+        let mut members = self.roles.get(&role).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::RoleMembers { role })
+        });
+        require!(members.insert(&account_id), "account already has the role");
+        self.roles.insert(&role, &members);
+
+        emit_event(EventLogVariant::RoleGrant(RoleLog {
+            role,
+            account_id,
+        }));
+    }
+
+    /// Take a role away from an account. Only the owner can revoke roles.
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_owner();
+
+        let mut members = self.roles.get(&role).expect("account doesn't have the role");
+        require!(members.remove(&account_id), "account doesn't have the role");
+        self.roles.insert(&role, &members);
+
+        emit_event(EventLogVariant::RoleRevoke(RoleLog {
+            role,
+            account_id,
+        }));
+    }
+
+    /// Propose a new owner. Ownership is only transferred once they call `accept_ownership`.
+    pub fn propose_owner(&mut self, new_owner_id: AccountId) {
+        self.assert_owner();
+        self.pending_owner_id = Some(new_owner_id);
+    }
+
+    /// Withdraw the proposal for a new owner
+    pub fn cancel_ownership_proposal(&mut self) {
+        self.assert_owner();
+        require!(self.pending_owner_id.take().is_some(), "no owner proposed");
+    }
+
+    /// Become the owner. Only the proposed owner can call this.
+    pub fn accept_ownership(&mut self) {
+        let new_owner_id = env::predecessor_account_id();
+        require!(self.pending_owner_id.as_ref() == Some(&new_owner_id), "predecessor isn't the proposed owner");
+        self.pending_owner_id = None;
+        let old_owner_id = std::mem::replace(&mut self.owner_id, new_owner_id.clone());
+
+        emit_event(EventLogVariant::OwnershipTransfer(OwnershipTransferLog {
+            old_owner_id,
+            new_owner_id,
+        }));
+    }
+
+    /// Returns the owner of the contract
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    /// Returns the owner that's been proposed and hasn't accepted yet
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner_id.clone()
+    }
+
+    /// Returns whether an account can act as a role. The owner can act as every role.
+    pub fn has_role(&self, role: Role, account_id: AccountId) -> bool {
+        account_id == self.owner_id || self.roles.get(&role).map(|members| members.contains(&account_id)).unwrap_or(false)
+    }
+
+    /// Paginate through the accounts that have been granted a role
+    pub fn get_role_members(
+        &self,
+        role: Role,
+        from_index: Option<U128>,
+        limit: Option<u64>
+    ) -> Vec<AccountId> {
+        // Where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        if let Some(members) = self.roles.get(&role) {
+            members.iter()
+                // Skip to the index we specified in the start variable
+                .skip(start as usize)
+                // Take the first "limit" elements in the vector. If we didn't specify a limit, use 50
+                .take(limit.unwrap_or(50) as usize)
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
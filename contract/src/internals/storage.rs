@@ -14,11 +14,17 @@ impl DropZone {
         balance += deposit;
         // Insert the balance back into the map for that account ID
         self.user_balances.insert(&env::predecessor_account_id(), &balance);
+
+        emit_event(EventLogVariant::BalanceDeposit(BalanceLog {
+            account_id: env::predecessor_account_id(),
+            amount: U128(deposit),
+        }));
     }
 
     // Allows users to withdraw their balance
     #[payable]
     pub fn withdraw_from_balance(&mut self) {
+        self.assert_not_paused(PauseCategory::Withdrawals);
         // the account to withdraw storage to is always the predecessor
         let owner_id = env::predecessor_account_id();
         //get the amount that the user has by removing them from the map. If they're not in the map, default to 0
@@ -27,6 +33,11 @@ impl DropZone {
         //if that excess to withdraw is > 0, we transfer the amount to the user.
         if amount > 0 {
             Promise::new(owner_id.clone()).transfer(amount);
+
+            emit_event(EventLogVariant::BalanceWithdrawal(BalanceLog {
+                account_id: owner_id,
+                amount: U128(amount),
+            }));
         }
     }
 
@@ -0,0 +1,98 @@
+use crate::*;
+
+#[near_bindgen]
+impl DropZone {
+    /*
+        Withdraw fungible tokens from the predecessor's token balance. Withdraws everything if no amount is passed in.
+        The account must be registered on the token contract. If the transfer fails, the tokens are added back.
+    */
+    pub fn withdraw_token_balance(&mut self, token_id: AccountId, amount: Option<U128>) -> Promise {
+        self.assert_not_paused(PauseCategory::Withdrawals);
+        let account_id = env::predecessor_account_id();
+        let key = (account_id.clone(), token_id.clone());
+        let balance = self.token_balances.get(&key).expect("no token balance to withdraw");
+        let amount = amount.map(|amount| amount.0).unwrap_or(balance);
+        require!(amount > 0 && amount <= balance, "amount must be greater than 0 and at most the token balance");
+
+        if amount == balance {
+            // The storage for the record is refunded to the account's $NEAR balance
+            let initial_storage = env::storage_usage();
+            self.token_balances.remove(&key);
+            let storage_freed = storage_cost(initial_storage - env::storage_usage(), env::storage_byte_cost());
+            let user_balance = self.user_balances.get(&account_id).unwrap_or(0);
+            self.user_balances.insert(&account_id, &(user_balance + storage_freed));
+        } else {
+            self.token_balances.insert(&key, &(balance - amount));
+        }
+
+        ext_ft_contract::ext(token_id.clone())
+            .with_attached_deposit(1)
+            .with_static_gas(MIN_GAS_FOR_FT_TRANSFER)
+            .ft_transfer(account_id.clone(), U128(amount), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_GAS_FOR_ON_WITHDRAW_TOKEN_BALANCE)
+                    .on_withdraw_token_balance(token_id, U128(amount), account_id)
+            )
+    }
+
+    /// Callback for withdrawing from a token balance
+    #[private]
+    pub fn on_withdraw_token_balance(&mut self, token_id: AccountId, amount: U128, account_id: AccountId) -> bool {
+        let result = promise_result_as_success();
+
+        emit_event(EventLogVariant::TokenBalanceWithdrawal(TokenBalanceWithdrawalLog {
+            account_id: account_id.clone(),
+            token_id: token_id.clone(),
+            amount,
+            success: result.is_some(),
+        }));
+
+        // If something went wrong, add the tokens back to the balance
+        if result.is_none() {
+            let key = (account_id, token_id);
+            let balance = self.token_balances.get(&key).unwrap_or(0);
+            self.token_balances.insert(&key, &(balance + amount.0));
+            return false
+        }
+
+        true
+    }
+
+    /// Returns the fungible tokens an account has in its token balance for a token contract
+    pub fn get_token_balance(&self, account_id: AccountId, token_id: AccountId) -> U128 {
+        U128(self.token_balances.get(&(account_id, token_id)).unwrap_or(0))
+    }
+}
+
+impl DropZone {
+    /*
+        Add fungible tokens to an account's token balance. The first time an account holds a token, the storage for
+        the record is taken out of their $NEAR balance. Returns the amount to refund, which is everything if their
+        balance can't cover the storage.
+    */
+    pub(crate) fn internal_add_to_token_balance(&mut self, account_id: AccountId, token_id: AccountId, amount: Balance) -> Balance {
+        let key = (account_id.clone(), token_id.clone());
+        let initial_storage = env::storage_usage();
+        let balance = self.token_balances.get(&key).unwrap_or(0);
+        self.token_balances.insert(&key, &(balance + amount));
+        let record_cost = storage_cost(env::storage_usage() - initial_storage, env::storage_byte_cost());
+
+        if record_cost > 0 {
+            let user_balance = self.user_balances.get(&account_id).unwrap_or(0);
+            if user_balance < record_cost {
+                debug_log!("Balance {} can't cover the storage for the token balance. Refunding the tokens", yocto_to_near(user_balance));
+                self.token_balances.remove(&key);
+                return amount
+            }
+            self.user_balances.insert(&account_id, &(user_balance - record_cost));
+        }
+
+        emit_event(EventLogVariant::TokenBalanceDeposit(TokenBalanceLog {
+            account_id,
+            token_id,
+            amount: U128(amount),
+        }));
+        0
+    }
+}
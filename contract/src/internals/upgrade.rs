@@ -0,0 +1,138 @@
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::GasWeight;
+
+use crate::*;
+
+/// Code that's been staged by the owner and is waiting to be deployed. The wasm itself is stored under its own key.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StagedCode {
+    // sha256 of the wasm
+    pub code_hash: CryptoHash,
+    // Size of the wasm in bytes
+    pub length: u64,
+    // Block timestamp when the code was staged
+    pub staged_at: u64,
+    // Block timestamp after which the code can be deployed
+    pub deployable_at: u64,
+}
+
+/// Staged code returned by the view methods
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonStagedCode {
+    pub code_hash: Base58CryptoHash,
+    pub length: u64,
+    pub staged_at: u64,
+    pub deployable_at: u64,
+}
+
+#[near_bindgen]
+impl DropZone {
+    /*
+        Stage new code for the contract. The wasm is passed in as the raw input (not JSON) and is stored until it's deployed
+        with `deploy_staged_code`. It can only be deployed once the upgrade delay has passed. Staging new code replaces
+        whatever was staged before and restarts the delay.
+    */
+    pub fn stage_code(&mut self) {
+        self.assert_role(Role::Upgrader);
+        let code = env::input().expect("no code passed in");
+        require!(!code.is_empty(), "no code passed in");
+
+        let code_hash = env::sha256_array(&code);
+        let staged_at = env::block_timestamp();
+        let deployable_at = staged_at + self.upgrade_delay;
+        env::storage_write(&StorageKey::StagedCode.try_to_vec().unwrap(), &code);
+        self.staged_code = Some(StagedCode {
+            code_hash,
+            length: code.len() as u64,
+            staged_at,
+            deployable_at,
+        });
+
+        emit_event(EventLogVariant::CodeStaged(CodeStagedLog {
+            code_hash: code_hash.into(),
+            deployable_at,
+        }));
+    }
+
+    /*
+        Deploy the staged code and call `migrate` in the same batch. The hash of the staged code must be passed in
+        so the owner can't deploy anything other than what they verified. If `migrate` fails, the deploy is reverted
+        and the code stays staged. The code hash is only recorded (and the staged code removed) once the batch succeeds.
+    */
+    pub fn deploy_staged_code(&mut self, code_hash: Base58CryptoHash) -> Promise {
+        self.assert_role(Role::Upgrader);
+        let staged = self.staged_code.as_ref().expect("no code staged");
+        require!(CryptoHash::from(code_hash) == staged.code_hash, "code hash doesn't match the staged code");
+        require!(env::block_timestamp() >= staged.deployable_at, &format!("staged code can't be deployed until {}", staged.deployable_at));
+
+        let code = env::storage_read(&StorageKey::StagedCode.try_to_vec().unwrap()).expect("staged code not found");
+
+        // Attach the min GAS to migrate. All unspent GAS will be added on top.
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call_weight("migrate".to_string(), vec![], NO_DEPOSIT, MIN_GAS_FOR_MIGRATE, GasWeight(1))
+            .then(
+                Self::ext(env::current_account_id())
+                    .on_deploy_staged_code(code_hash)
+            )
+    }
+
+    /// Callback for deploying staged code. This runs on the newly deployed code.
+    #[private]
+    pub fn on_deploy_staged_code(&mut self, code_hash: Base58CryptoHash) -> bool {
+        // If the deploy or migrate failed, the old code is still deployed and the code stays staged
+        if promise_result_as_success().is_none() {
+            return false
+        }
+
+        let code_hash = CryptoHash::from(code_hash);
+        self.code_hash = Some(code_hash);
+
+        // Only remove the staged code if it wasn't replaced while the deploy was in flight
+        if self.staged_code.as_ref().map(|staged| staged.code_hash == code_hash).unwrap_or(false) {
+            self.staged_code = None;
+            env::storage_remove(&StorageKey::StagedCode.try_to_vec().unwrap());
+        }
+
+        emit_event(EventLogVariant::CodeDeployment(CodeDeploymentLog {
+            code_hash: code_hash.into(),
+        }));
+
+        true
+    }
+
+    /// Remove the staged code without deploying it
+    pub fn cancel_staged_code(&mut self) {
+        self.assert_role(Role::Upgrader);
+        require!(self.staged_code.take().is_some(), "no code staged");
+        env::storage_remove(&StorageKey::StagedCode.try_to_vec().unwrap());
+    }
+
+    /// Set how long (in nanoseconds) staged code must wait before it can be deployed. Only applies to code staged afterwards.
+    /// Only the owner can change this so upgraders can't skip the delay.
+    pub fn set_upgrade_delay(&mut self, upgrade_delay: u64) {
+        self.assert_owner();
+        self.upgrade_delay = upgrade_delay;
+    }
+
+    /// Returns the code waiting to be deployed
+    pub fn get_staged_code(&self) -> Option<JsonStagedCode> {
+        self.staged_code.as_ref().map(|staged| JsonStagedCode {
+            code_hash: staged.code_hash.into(),
+            length: staged.length,
+            staged_at: staged.staged_at,
+            deployable_at: staged.deployable_at,
+        })
+    }
+
+    /// Returns the hash of the last code deployed through `deploy_staged_code`
+    pub fn get_code_hash(&self) -> Option<Base58CryptoHash> {
+        self.code_hash.map(|hash| hash.into())
+    }
+
+    /// Returns how long (in nanoseconds) staged code must wait before it can be deployed
+    pub fn get_upgrade_delay(&self) -> u64 {
+        self.upgrade_delay
+    }
+}
@@ -1,6 +1,11 @@
+// Contract methods take their JSON arguments one by one, the asset enums keep the variant names they're serialized with
+// and the borsh layout of stored enums can't be boxed without a migration
+#![allow(clippy::too_many_arguments, clippy::upper_case_acronyms, clippy::large_enum_variant)]
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
-use near_sdk::json_types::U128;
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{U128, Base64VecU8};
+use std::collections::HashMap;
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::serde_json::{json};
 use near_sdk::{
@@ -29,6 +34,8 @@ const NO_DEPOSIT: u128 = 0;
     GAS Constants (outlines the minimum to attach. Any unspent GAS will be added according to the weights)
 */
 const MIN_GAS_FOR_ON_CLAIM: Gas = Gas(55_000_000_000_000); // 55 TGas
+// GAS burnt scheduling each extra asset's callback on top of its minimum (receipt creation and argument bytes)
+const GAS_FOR_CHAINING_ON_CLAIM: Gas = Gas(11_000_000_000_000); // 11 TGas
 
 // NFTs
 const MIN_GAS_FOR_SIMPLE_NFT_TRANSFER: Gas = Gas(10_000_000_000_000); // 10 TGas
@@ -38,6 +45,7 @@ const MIN_GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(15_000_000_000_000 + MIN_GAS_FOR_S
 // Actual amount of GAS to attach when querying the storage balance bounds. No unspent GAS will be attached on top of this (weight of 0)
 const GAS_FOR_STORAGE_BALANCE_BOUNDS: Gas = Gas(10_000_000_000_000); // 10 TGas
 const MIN_GAS_FOR_RESOLVE_STORAGE_CHECK: Gas = Gas(25_000_000_000_000); // 25 TGas
+const MIN_GAS_FOR_RESOLVE_TRANSFER_DROP_CREATION: Gas = Gas(30_000_000_000_000); // 30 TGas
 const MIN_GAS_FOR_FT_TRANSFER: Gas = Gas(5_000_000_000_000); // 5 TGas
 const MIN_GAS_FOR_STORAGE_DEPOSIT: Gas = Gas(5_000_000_000_000); // 5 TGas
 const MIN_GAS_FOR_RESOLVE_BATCH: Gas = Gas(13_000_000_000_000 + MIN_GAS_FOR_FT_TRANSFER.0 + MIN_GAS_FOR_STORAGE_DEPOSIT.0); // 13 TGas + 5 TGas + 5 TGas = 23 TGas
@@ -45,9 +53,21 @@ const MIN_GAS_FOR_RESOLVE_BATCH: Gas = Gas(13_000_000_000_000 + MIN_GAS_FOR_FT_T
 // Specifies the GAS being attached from the wallet site
 const ATTACHED_GAS_FROM_WALLET: Gas = Gas(100_000_000_000_000); // 100 TGas
 
+// Maximum amount of GAS that can be attached to a transaction. Drops with many assets need more GAS than the wallet attaches.
+const MAX_GAS_ATTACHABLE: Gas = Gas(300_000_000_000_000); // 300 TGas
+
 // Specifies the amount of GAS to attach on top of the FC Gas if executing a regular function call in claim
 const GAS_OFFSET_IF_FC_EXECUTE: Gas = Gas(10_000_000_000_000); // 10 TGas
 
+// Minimum GAS to attach when calling migrate after deploying staged code
+const MIN_GAS_FOR_MIGRATE: Gas = Gas(20_000_000_000_000); // 20 TGas
+
+// Actual amount of GAS to attach when querying a fee token's rate from its oracle. No unspent GAS will be attached on top of this (weight of 0)
+const GAS_FOR_FEE_ORACLE: Gas = Gas(10_000_000_000_000); // 10 TGas
+const MIN_GAS_FOR_RESOLVE_FEE_PAYMENT: Gas = Gas(10_000_000_000_000); // 10 TGas
+const MIN_GAS_FOR_ON_WITHDRAW_TOKEN_FEES: Gas = Gas(10_000_000_000_000); // 10 TGas
+const MIN_GAS_FOR_ON_WITHDRAW_TOKEN_BALANCE: Gas = Gas(10_000_000_000_000); // 10 TGas
+
 // Actual amount of GAS to attach when creating a new account. No unspent GAS will be attached on top of this (weight of 0)
 const GAS_FOR_CREATE_ACCOUNT: Gas = Gas(28_000_000_000_000); // 28 TGas
 
@@ -65,12 +85,34 @@ const KEY_ADDITION_FEE: u128 = 5_000_000_000_000_000_000_000; // 0.005 N
 
 const GAS_FOR_PANIC_OFFSET: Gas = Gas(10_000_000_000_000); // 10 TGas
 
+// Storage prepaid for each claim to record the claiming account when max_claims_per_account is set
+const CLAIMED_ACCOUNT_STORAGE: u64 = 160; // bytes
+
+// How many NFTs can be sent back per asset each time an expired drop is swept
+const MAX_NFTS_REFUNDED_PER_SWEEP: u64 = 10;
+
+/// Diagnostic logs are only emitted when the contract is built with the `debug-logs` feature. Lifecycle events are always emitted.
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "debug-logs") {
+            env::log_str(&format!($($arg)*));
+        }
+    };
+}
+
+mod accounting;
+mod events;
 mod internals;
 mod stage1;
 mod stage2;
 mod stage3;
 mod views;
 
+#[cfg(test)]
+mod tests;
+
+use accounting::*;
+use events::*;
 use internals::*;
 use stage2::*;
 use stage1::*;
@@ -78,9 +120,7 @@ use stage1::*;
 pub(crate) fn yocto_to_near(yocto: u128) -> f64 {
     //10^20 yoctoNEAR (1 NEAR would be 10_000). This is to give a precision of 4 decimal places.
     let formatted_near = yocto / 100_000_000_000_000_000_000;
-    let near = formatted_near as f64 / 10_000_f64;
-
-    near
+    formatted_near as f64 / 10_000_f64
 }
 
 #[derive(BorshSerialize, BorshStorageKey)]
@@ -91,7 +131,21 @@ enum StorageKey {
     DropIdsForFunderInner { account_id_hash: CryptoHash },
     PksForDrop { account_id_hash: CryptoHash },
     TokenIdsForDrop { account_id_hash: CryptoHash },
-    UserBalances
+    UserBalances,
+    ClaimsPerAccountForDrop { account_id_hash: CryptoHash },
+    AllowlistForDrop { account_id_hash: CryptoHash },
+    DenylistForDrop { account_id_hash: CryptoHash },
+    VersionedDropsForId,
+    StagedCode,
+    Roles,
+    RoleMembers { role: Role },
+    FunderFees,
+    KeysForFunder,
+    ReferralFees,
+    FeeTokens,
+    FeeCredits,
+    TokenFeesCollected,
+    TokenBalances,
 }
 
 #[near_bindgen]
@@ -103,8 +157,8 @@ pub struct DropZone {
     
     // Map each key to a nonce rather than repeating each drop data in memory
     pub drop_id_for_pk: UnorderedMap<PublicKey, DropId>,
-    // Map the nonce to a specific drop
-    pub drop_for_id: LookupMap<DropId, Drop>,    
+    // Map the nonce to a specific drop. Drops are stored with a version tag.
+    pub drop_for_id: DropMap,
     // Keep track of the drop ids for each funder for pagination
     pub drop_ids_for_funder: LookupMap<AccountId, UnorderedSet<DropId>>,
 
@@ -120,7 +174,48 @@ pub struct DropZone {
     pub nonce: DropId,
 
     // Keep track of the price of 1 GAS per 1 yocto
-    pub yocto_per_gas: u128
+    pub yocto_per_gas: u128,
+
+    // How long (in nanoseconds) staged code must wait before it can be deployed
+    pub upgrade_delay: u64,
+    // Code staged by the owner that's waiting to be deployed
+    pub staged_code: Option<StagedCode>,
+    // Hash of the last code deployed through `deploy_staged_code`
+    pub code_hash: Option<CryptoHash>,
+
+    // Accounts that have been granted each role
+    pub roles: LookupMap<Role, UnorderedSet<AccountId>>,
+    // Owner that's been proposed and has yet to accept ownership
+    pub pending_owner_id: Option<AccountId>,
+    // Categories of methods that have been paused by a pauser
+    pub paused: PauseFlags,
+
+    // Fees for specific funders that replace the global fees
+    pub funder_fees: LookupMap<AccountId, FunderFees>,
+    // Key fees once a funder has added enough keys. Only used for funders without a key fee override.
+    pub volume_tiers: Vec<VolumeTier>,
+    // Fee multipliers for each kind of drop
+    pub fee_multipliers: FeeMultipliers,
+    // How many keys each funder has added across all their drops
+    pub keys_for_funder: LookupMap<AccountId, u64>,
+
+    // Share of the fees (in basis points) credited to referrers
+    pub referral_split: u32,
+    // Referral fees credited to each referrer that haven't been withdrawn
+    pub referral_fees: LookupMap<AccountId, Balance>,
+
+    // Fungible tokens that can pay for fees and how they're converted to $NEAR
+    pub fee_tokens: UnorderedMap<AccountId, FeeToken>,
+    // Fees prepaid with fungible tokens (in yoctoNEAR) for each funder
+    pub fee_credits: LookupMap<AccountId, Balance>,
+    // Fungible tokens collected as fees for each token contract
+    pub token_fees_collected: LookupMap<AccountId, Balance>,
+
+    // Fungible tokens held for each account and token contract
+    pub token_balances: LookupMap<(AccountId, AccountId), Balance>,
+
+    // Incremented on every random draw so draws made with the same seed get different numbers
+    pub random_nonce: u64,
 }
 
 #[near_bindgen]
@@ -128,11 +223,13 @@ impl DropZone {
     /// Initialize contract and pass in the desired deployed linkdrop contract (i.e testnet or near)
     #[init]
     pub fn new(linkdrop_contract: AccountId, owner_id: AccountId) -> Self {
+        write_state_version();
+
         Self {
             owner_id,
             linkdrop_contract,
             drop_id_for_pk: UnorderedMap::new(StorageKey::DropIdForPk),
-            drop_for_id: LookupMap::new(StorageKey::DropsForId),
+            drop_for_id: DropMap::new(StorageKey::VersionedDropsForId, StorageKey::DropsForId),
             drop_ids_for_funder: LookupMap::new(StorageKey::DropIdsForFunder),
             user_balances: LookupMap::new(StorageKey::UserBalances),
             nonce: 0,
@@ -142,7 +239,24 @@ impl DropZone {
             drop_fee: DROP_CREATION_FEE,
             key_fee: KEY_ADDITION_FEE,
             fees_collected: 0,
-            yocto_per_gas: 100_000_000
+            yocto_per_gas: 100_000_000,
+            upgrade_delay: 0,
+            staged_code: None,
+            code_hash: None,
+            roles: LookupMap::new(StorageKey::Roles),
+            pending_owner_id: None,
+            paused: PauseFlags::default(),
+            funder_fees: LookupMap::new(StorageKey::FunderFees),
+            volume_tiers: vec![],
+            fee_multipliers: FeeMultipliers::default(),
+            keys_for_funder: LookupMap::new(StorageKey::KeysForFunder),
+            referral_split: 0,
+            referral_fees: LookupMap::new(StorageKey::ReferralFees),
+            fee_tokens: UnorderedMap::new(StorageKey::FeeTokens),
+            fee_credits: LookupMap::new(StorageKey::FeeCredits),
+            token_fees_collected: LookupMap::new(StorageKey::TokenFeesCollected),
+            token_balances: LookupMap::new(StorageKey::TokenBalances),
+            random_nonce: 0,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,118 @@
+use crate::*;
+
+#[near_bindgen]
+impl DropZone {
+    /*
+        Funder can restrict which accounts are able to claim from a drop. If there's
+        at least one account in the allowlist, only those accounts can claim. Accounts
+        in the denylist can never claim. The storage for the lists is charged to the
+        funder's balance and refunded when accounts are removed.
+    */
+    pub fn add_to_allowlist(&mut self, drop_id: DropId, account_ids: Vec<AccountId>) {
+        let initial_storage = env::storage_usage();
+        let mut drop = self.internal_get_drop_for_funder(drop_id);
+
+        for account_id in &account_ids {
+            if drop.allowlist.insert(account_id) {
+                drop.allowlist_len += 1;
+            }
+        }
+        debug_log!("Allowlist for drop {} now has {} accounts", drop_id, drop.allowlist_len);
+
+        self.drop_for_id.insert(&drop_id, &drop);
+        self.internal_settle_list_storage(&drop.funder_id, initial_storage);
+    }
+
+    /// Remove accounts from the allowlist. If the allowlist is now empty, any account can claim.
+    pub fn remove_from_allowlist(&mut self, drop_id: DropId, account_ids: Vec<AccountId>) {
+        let initial_storage = env::storage_usage();
+        let mut drop = self.internal_get_drop_for_funder(drop_id);
+
+        for account_id in &account_ids {
+            if drop.allowlist.remove(account_id) {
+                drop.allowlist_len -= 1;
+            }
+        }
+        debug_log!("Allowlist for drop {} now has {} accounts", drop_id, drop.allowlist_len);
+
+        self.drop_for_id.insert(&drop_id, &drop);
+        self.internal_settle_list_storage(&drop.funder_id, initial_storage);
+    }
+
+    /// Prevent accounts from claiming from the drop
+    pub fn add_to_denylist(&mut self, drop_id: DropId, account_ids: Vec<AccountId>) {
+        let initial_storage = env::storage_usage();
+        let mut drop = self.internal_get_drop_for_funder(drop_id);
+
+        for account_id in &account_ids {
+            drop.denylist.insert(account_id);
+        }
+
+        self.drop_for_id.insert(&drop_id, &drop);
+        self.internal_settle_list_storage(&drop.funder_id, initial_storage);
+    }
+
+    /// Allow accounts in the denylist to claim from the drop again
+    pub fn remove_from_denylist(&mut self, drop_id: DropId, account_ids: Vec<AccountId>) {
+        let initial_storage = env::storage_usage();
+        let mut drop = self.internal_get_drop_for_funder(drop_id);
+
+        for account_id in &account_ids {
+            drop.denylist.remove(account_id);
+        }
+
+        self.drop_for_id.insert(&drop_id, &drop);
+        self.internal_settle_list_storage(&drop.funder_id, initial_storage);
+    }
+
+    /// Set the suffix that new accounts created through `create_account_and_claim` are allowed to have (i.e `.mycompany.near`).
+    /// Once set, only new accounts with the suffix or in the allowlist can be created. Pass in None to remove the suffix.
+    pub fn set_new_account_suffix(&mut self, drop_id: DropId, suffix: Option<String>) {
+        let initial_storage = env::storage_usage();
+        let mut drop = self.internal_get_drop_for_funder(drop_id);
+
+        if let Some(suffix) = &suffix {
+            require!(suffix.starts_with('.') && suffix.len() > 1, "suffix must start with a `.` followed by a parent account");
+        }
+        drop.new_account_suffix = suffix;
+
+        self.drop_for_id.insert(&drop_id, &drop);
+        self.internal_settle_list_storage(&drop.funder_id, initial_storage);
+    }
+
+    /// Returns if an account is in the allowlist for a drop
+    pub fn is_in_allowlist(&self, drop_id: DropId, account_id: AccountId) -> bool {
+        self.drop_for_id.get(&drop_id).expect("no drop found").allowlist.contains(&account_id)
+    }
+
+    /// Returns if an account is in the denylist for a drop
+    pub fn is_in_denylist(&self, drop_id: DropId, account_id: AccountId) -> bool {
+        self.drop_for_id.get(&drop_id).expect("no drop found").denylist.contains(&account_id)
+    }
+
+    /// Get a drop and ensure the predecessor is its funder
+    fn internal_get_drop_for_funder(&self, drop_id: DropId) -> Drop {
+        let drop = self.drop_for_id.get(&drop_id).expect("no drop found for ID");
+        require!(drop.funder_id == env::predecessor_account_id(), "only funder can manage the accounts for a drop");
+        drop
+    }
+
+    /// Charge the funder's balance for any storage used since the initial storage or refund them for any storage freed
+    fn internal_settle_list_storage(&mut self, funder_id: &AccountId, initial_storage: u64) {
+        let final_storage = env::storage_usage();
+        let mut current_user_balance = self.user_balances.get(funder_id).expect("No user balance found");
+
+        if final_storage > initial_storage {
+            let required_deposit = storage_cost(final_storage - initial_storage, env::storage_byte_cost());
+            debug_log!("Charging {} for storage. Cur user balance {}", yocto_to_near(required_deposit), yocto_to_near(current_user_balance));
+            require!(current_user_balance >= required_deposit, "Not enough deposit");
+            current_user_balance -= required_deposit;
+        } else {
+            let amount_freed = storage_freed(initial_storage, 0, final_storage, env::storage_byte_cost());
+            debug_log!("Refunding {} for storage freed", yocto_to_near(amount_freed));
+            current_user_balance += amount_freed;
+        }
+
+        self.user_balances.insert(funder_id, &current_user_balance);
+    }
+}
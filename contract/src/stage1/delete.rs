@@ -16,6 +16,54 @@ impl DropZone {
     pub fn delete_keys(&mut self, 
         public_keys: Option<Vec<PublicKey>>,
         drop_id: DropId
+    ) {
+        // get the drop object
+        let drop = self.drop_for_id.get(&drop_id).expect("No drop found");
+        require!(drop.funder_id == env::predecessor_account_id(), "only drop funder can delete keys");
+
+        self.internal_delete_keys(public_keys, drop_id);
+    }
+
+    /*
+        Permissionless method for cleaning up a drop that has passed its end
+        timestamp or end block height. Any FTs or NFTs still registered are
+        returned to their senders first. Once every asset has been returned
+        and the refunds have resolved, each call deletes up to 100 keys and
+        refunds the funder's balance for the allowance and storage.
+    */
+    pub fn sweep_expired_drop(&mut self, drop_id: DropId) {
+        let mut drop = self.drop_for_id.get(&drop_id).expect("No drop found");
+        require!(drop.drop_config.has_expired(), "drop has not expired");
+
+        // Get the indices of the FT and NFT assets that still have claims registered
+        let asset_indices: Vec<usize> = drop.assets.iter().enumerate().filter_map(|(index, asset)| match asset {
+            DropAsset::NFT(data) if data.num_claims_registered > 0 => Some(index),
+            DropAsset::FT(data) if data.num_claims_registered > 0 => Some(index),
+            _ => None
+        }).collect();
+
+        // Assets must be returned before the keys can be deleted. The keys will be deleted on the next sweep.
+        if !asset_indices.is_empty() {
+            debug_log!("Drop {} expired. Refunding {} assets before deleting keys", drop_id, asset_indices.len());
+            for index in asset_indices {
+                // NFTs are transferred one by one so cap how many are refunded per sweep. FTs are refunded in a single transfer.
+                let assets_to_refund = match &drop.assets[index] {
+                    DropAsset::NFT(data) => Some(data.num_claims_registered.min(MAX_NFTS_REFUNDED_PER_SWEEP)),
+                    _ => None
+                };
+                self.internal_refund_asset(drop_id, &mut drop, index, assets_to_refund);
+            }
+            return;
+        }
+
+        debug_log!("Drop {} expired. Deleting keys", drop_id);
+        self.internal_delete_keys(None, drop_id);
+    }
+
+    /// Internal method for deleting keys from a drop and refunding the funder. All FTs and NFTs must be refunded, and the refunds resolved, beforehand.
+    pub(crate) fn internal_delete_keys(&mut self, 
+        public_keys: Option<Vec<PublicKey>>,
+        drop_id: DropId
     ) {
         // Measure initial storage before doing any operations
         let initial_storage = env::storage_usage();
@@ -23,25 +71,31 @@ impl DropZone {
         // get the drop object
         let mut drop = self.drop_for_id.remove(&drop_id).expect("No drop found");
         let funder_id = drop.funder_id.clone();
-        let drop_type = &drop.drop_type;
-        require!(funder_id == env::predecessor_account_id(), "only drop funder can delete keys");
+        // Refund callbacks need the drop to add the asset's claims back if the transfer fails
+        require!(drop.pending_refunds == 0, "asset refunds must resolve before keys are deleted");
         
         // ensure that there are no FTs or NFTs left to be refunded
-        match drop_type {
-            DropType::NFT(_) => {
-                require!(drop.num_claims_registered == 0, "NFTs must be refunded before keys are deleted");
-            },
-            DropType::FT(_) => {
-                require!(drop.num_claims_registered == 0, "FTs must be refunded before keys are deleted");
-            },
-            _ => {}
-        };
+        for asset in &drop.assets {
+            match asset {
+                DropAsset::NFT(data) => {
+                    require!(data.num_claims_registered == 0, "NFTs must be refunded before keys are deleted");
+                },
+                DropAsset::FT(data) => {
+                    require!(data.num_claims_registered == 0, "FTs must be refunded before keys are deleted");
+                },
+                _ => {}
+            };
+        }
         
         // Keep track of the total refund amount
         let total_refund_amount;
         // Default the keys to use to be the public keys or an empty vector. We'll populate it if no PKs are passed in.
         let keys_to_delete;
         let mut total_allowance_left = 0;
+        // Keep track of how many claims were left across all keys being deleted
+        let mut total_uses_left = 0;
+        // Keep track of the function call deposits for the uses left across all keys being deleted
+        let mut total_fc_deposits_left = 0;
 
         // If the user passed in public keys, loop through and remove them from the drop
         if let Some(keys) = public_keys {
@@ -50,125 +104,134 @@ impl DropZone {
 
             let len = keys_to_delete.len() as u128;
             require!(len <= 100, "cannot delete more than 100 keys at a time");
-            env::log_str(&format!("Removing {} keys from the drop", len));
+            debug_log!("Removing {} keys from the drop", len);
 
             // Loop through and remove keys
             for key in &keys_to_delete {
                 // Unlink key to drop ID
                 self.drop_id_for_pk.remove(key);
                 // Attempt to remove the public key. panic if it didn't exist
-                let key_usage = drop.pks.remove(key).expect("public key must be in drop");
+                let key_usage = drop.remove_key_usage(key).expect("public key must be in drop");
                 // Increment the allowance left by whatever is left on the key
                 total_allowance_left += key_usage.allowance;
+                total_uses_left += key_usage.num_uses;
+                total_fc_deposits_left += fc_deposits_for_uses_left(&drop.assets, drop.drop_config.max_claims_per_key, key_usage.num_uses);
             }
 
+            // The deleted keys can no longer claim
+            drop.num_claims_registered = drop.num_claims_registered.saturating_sub(total_uses_left);
+
             /*
                 Refund amount consists of:
                 - Storage freed
                 - Total access key allowance left across all keys deleted
-                - Access key storage for each key * claims / key
-                - Balance for linkdrop for each key * claims / key
+                - Access key storage for each claim left on the keys
+                - Balance for linkdrop for each claim left on the keys
                 
-                Optional:
-                - FC deposit for each key * claims / key
-                - storage for longest token ID for each key * claims / key
-                - FT storage registration cost for each key * claims / key
+                Optional (summed across all assets):
+                - FC deposits for the uses left on the keys
+                - storage for longest token ID for each claim left on the keys
+                - FT storage registration cost for each claim left on the keys
+                - storage for recording the claiming account for each claim left on the keys
             */ 
-            // Get optional costs for the drop
-            let optional_refund = match drop_type {
-                DropType::FC(data) => {
-                    data.deposit.0
-                },
-                DropType::NFT(data) => {
-                    data.storage_for_longest * env::storage_byte_cost()
-                },
-                DropType::FT(data) => {
-                    data.ft_storage.0
-                },
-                _ => {0}
-            };
+            // Get the costs for every claim summed across all the drop's assets
+            let claim_costs = per_claim_costs(&drop.assets, &drop.drop_config, drop.balance.0);
             
             
             // If the drop has no keys, remove it from the funder. Otherwise, insert it back with the updated keys.
-            if drop.pks.len() == 0 {
-                env::log_str("Drop empty. Removing from funder");
+            if drop.pks.is_empty() {
+                debug_log!("Drop empty. Removing from funder");
                 self.internal_remove_drop_for_funder(&funder_id, &drop_id);
             } else {
-                env::log_str(&format!("Drop non empty. Adding back. Len: {}", drop.pks.len()));
+                debug_log!("Drop non empty. Adding back. Len: {}", drop.pks.len());
                 self.drop_for_id.insert(&drop_id, &drop);
             }
             
             // Calculate the storage being freed. initial - final should be >= 0 since final should be smaller than initial.
             let final_storage = env::storage_usage();
-            let total_storage_freed = Balance::from(initial_storage - final_storage) * env::storage_byte_cost();
+            let total_storage_freed = storage_freed(initial_storage, 0, final_storage, env::storage_byte_cost());
             
-            total_refund_amount = total_storage_freed + total_allowance_left + (ACCESS_KEY_STORAGE + drop.balance.0 + optional_refund) * drop.num_claims_registered as u128 * len;
+            total_refund_amount = key_deletion_refund(&KeyDeletionInputs {
+                storage_freed: total_storage_freed,
+                allowance_left: total_allowance_left,
+                fc_deposits_left: total_fc_deposits_left,
+                uses_left: total_uses_left,
+                per_claim: claim_costs,
+            });
         } else {
             // If no PKs were passed in, attempt to remove 100 keys at a time
             keys_to_delete = drop.pks.keys().take(100).collect();
         
             let len = keys_to_delete.len() as u128;
-            env::log_str(&format!("Removing {} keys from the drop", len));
+            debug_log!("Removing {} keys from the drop", len);
             
             // Loop through and remove keys
             for key in &keys_to_delete {
                 // Unlink key to drop ID
                 self.drop_id_for_pk.remove(key);
                 // Attempt to remove the public key. panic if it didn't exist
-                let key_usage = drop.pks.remove(key).expect("public key must be in drop");
+                let key_usage = drop.remove_key_usage(key).expect("public key must be in drop");
                 // Increment the allowance left by whatever is left on the key
                 total_allowance_left += key_usage.allowance;
+                total_uses_left += key_usage.num_uses;
+                total_fc_deposits_left += fc_deposits_for_uses_left(&drop.assets, drop.drop_config.max_claims_per_key, key_usage.num_uses);
             }
 
+            // The deleted keys can no longer claim
+            drop.num_claims_registered = drop.num_claims_registered.saturating_sub(total_uses_left);
+
             /*
                 Refund amount consists of:
                 - Storage freed
                 - Total access key allowance left across all keys deleted
-                - Access key storage for each key * claims / key
-                - Balance for linkdrop for each key * claims / key
+                - Access key storage for each claim left on the keys
+                - Balance for linkdrop for each claim left on the keys
                 
-                Optional:
-                - FC deposit for each key * claims / key
-                - storage for longest token ID for each key * claims / key
-                - FT storage registration cost for each key * claims / key
+                Optional (summed across all assets):
+                - FC deposits for the uses left on the keys
+                - storage for longest token ID for each claim left on the keys
+                - FT storage registration cost for each claim left on the keys
+                - storage for recording the claiming account for each claim left on the keys
             */ 
-            // Get optional costs for the drop
-            let optional_refund = match drop_type {
-                DropType::FC(data) => {
-                    data.deposit.0
-                },
-                DropType::NFT(data) => {
-                    data.storage_for_longest * env::storage_byte_cost()
-                },
-                DropType::FT(data) => {
-                    data.ft_storage.0
-                },
-                _ => {0}
-            };
+            // Get the costs for every claim summed across all the drop's assets
+            let claim_costs = per_claim_costs(&drop.assets, &drop.drop_config, drop.balance.0);
 
             // If the drop has no keys, remove it from the funder. Otherwise, insert it back with the updated keys.
-            if drop.pks.len() == 0 {
-                env::log_str("Drop empty. Removing from funder");
+            if drop.pks.is_empty() {
+                debug_log!("Drop empty. Removing from funder");
                 self.internal_remove_drop_for_funder(&funder_id, &drop_id);
             } else {
-                env::log_str(&format!("Drop non empty. Adding back. Len: {}", drop.pks.len()));
+                debug_log!("Drop non empty. Adding back. Len: {}", drop.pks.len());
                 self.drop_for_id.insert(&drop_id, &drop);
             }
 
             // Calculate the storage being freed. initial - final should be >= 0 since final should be smaller than initial.
             let final_storage = env::storage_usage();
-            let total_storage_freed = Balance::from(initial_storage - final_storage) * env::storage_byte_cost();
-            env::log_str(&format!("Storage freed: {} bytes: {}", yocto_to_near(total_storage_freed), total_storage_freed));
+            let total_storage_freed = storage_freed(initial_storage, 0, final_storage, env::storage_byte_cost());
+            debug_log!("Storage freed: {} bytes: {}", yocto_to_near(total_storage_freed), total_storage_freed);
             
-            total_refund_amount = total_storage_freed + total_allowance_left + (ACCESS_KEY_STORAGE + drop.balance.0 + optional_refund) * drop.num_claims_registered as u128 * len;
+            total_refund_amount = key_deletion_refund(&KeyDeletionInputs {
+                storage_freed: total_storage_freed,
+                allowance_left: total_allowance_left,
+                fc_deposits_left: total_fc_deposits_left,
+                uses_left: total_uses_left,
+                per_claim: claim_costs,
+            });
         }
 
         // Refund the user
         let mut cur_balance = self.user_balances.get(&funder_id).unwrap_or(0);
-        env::log_str(&format!("Refunding user {} old balance: {}. Total allowance left: {}", yocto_to_near(total_refund_amount), yocto_to_near(cur_balance), yocto_to_near(total_allowance_left)));
+        debug_log!("Refunding user {} old balance: {}. Total allowance left: {}", yocto_to_near(total_refund_amount), yocto_to_near(cur_balance), yocto_to_near(total_allowance_left));
         cur_balance += total_refund_amount;
         self.user_balances.insert(&funder_id, &cur_balance);
 
+        emit_event(EventLogVariant::KeyDeletion(KeyDeletionLog {
+            funder_id: funder_id.clone(),
+            drop_id: U128(drop_id),
+            public_keys: keys_to_delete.clone(),
+            refund: U128(total_refund_amount),
+        }));
+
         // Loop through and delete keys
         for key in &keys_to_delete {
             // Create the batch promise
@@ -176,7 +239,7 @@ impl DropZone {
 
             env::promise_batch_action_delete_key(
                 promise, 
-            &key, 
+            key, 
             );
 
             env::promise_return(promise);
@@ -185,31 +248,72 @@ impl DropZone {
 
     /*
         Refund NFTs or FTs for a drop. User can optionally pass in a number of assets to
-        refund. If not, it will try to refund all assets.
+        refund. If not, it will try to refund all assets. If an asset index is passed in,
+        only that asset is refunded. Otherwise every FT and NFT asset with claims registered
+        is refunded.
     */
     pub fn refund_assets(&mut self, 
         drop_id: DropId,
-        assets_to_refund: Option<u64>
+        assets_to_refund: Option<u64>,
+        asset_index: Option<u64>
     ) {
         // get the drop object
         let mut drop = self.drop_for_id.get(&drop_id).expect("No drop found");
         let funder_id = drop.funder_id.clone();
         require!(funder_id == env::predecessor_account_id(), "only drop funder can delete keys");
 
-        // Get the number of claims registered for the drop.
-        let claims_registered = drop.num_claims_registered;
+        // Get the indices of the assets to refund
+        let asset_indices: Vec<usize> = if let Some(index) = asset_index {
+            vec![index as usize]
+        } else {
+            drop.assets.iter().enumerate().filter_map(|(index, asset)| match asset {
+                DropAsset::NFT(data) if data.num_claims_registered > 0 => Some(index),
+                DropAsset::FT(data) if data.num_claims_registered > 0 => Some(index),
+                _ => None
+            }).collect()
+        };
+        require!(!asset_indices.is_empty(), "no claims left to unregister");
+
+        for index in asset_indices {
+            self.internal_refund_asset(drop_id, &mut drop, index, assets_to_refund);
+        }
+    }
+
+    /// Internal method for refunding an FT or NFT asset in a drop. The drop is inserted back with the asset's claims registered decremented.
+    pub(crate) fn internal_refund_asset(&mut self,
+        drop_id: DropId,
+        drop: &mut Drop,
+        asset_index: usize,
+        assets_to_refund: Option<u64>
+    ) {
+        // Get the number of claims registered for the asset.
+        let claims_registered = match drop.assets.get(asset_index) {
+            Some(DropAsset::NFT(data)) => data.num_claims_registered,
+            Some(DropAsset::FT(data)) => data.num_claims_registered,
+            _ => env::panic_str("can only refund assets for FT and NFT drops")
+        };
         require!(claims_registered > 0, "no claims left to unregister");
 
         // Get the claims to refund. If not specified, this is the number of claims currently registered.
         let num_to_refund = assets_to_refund.unwrap_or(claims_registered);
         require!(num_to_refund <= claims_registered, "can only refund less than or equal to the amount of keys registered");
+        // The claims registered for an FT asset with amount tiers are funded by the whole table
+        if let Some(DropAsset::FT(data)) = drop.assets.get(asset_index) {
+            require!(data.amount_tiers.is_none() || num_to_refund == claims_registered, "FT assets with amount tiers can only be refunded in full");
+        }
 
-        // Decrement the drop's keys registered temporarily. If the transfer is unsuccessful, revert in callback. 
-        drop.num_claims_registered -= num_to_refund;
-        self.drop_for_id.insert(&drop_id, &drop);
+        // Decrement the asset's claims registered temporarily. If the transfer is unsuccessful, revert in callback. 
+        match &mut drop.assets[asset_index] {
+            DropAsset::NFT(data) => data.num_claims_registered -= num_to_refund,
+            DropAsset::FT(data) => data.num_claims_registered -= num_to_refund,
+            _ => {}
+        };
+        // Keys can't be deleted until the callback has resolved the refund
+        drop.pending_refunds += 1;
+        self.drop_for_id.insert(&drop_id, drop);
 
-        match drop.drop_type {
-            DropType::NFT(data) => {
+        match &drop.assets[asset_index] {
+            DropAsset::NFT(data) => {
                 /*
                     NFTs need to be batched together. Loop through and transfer all NFTs.
                     Keys registered will be decremented and the token IDs will be removed
@@ -245,20 +349,28 @@ impl DropZone {
                 env::promise_batch_action_function_call_weight(
                     batch_ft_resolve_promise_id,
                     "nft_resolve_refund",
-                    json!({ "drop_id": U128(drop_id), "token_ids": token_ids }).to_string().as_bytes(),
+                    json!({
+                        "drop_id": U128(drop_id),
+                        "token_ids": token_ids,
+                        "asset_index": asset_index as u64,
+                        "nft_contract": data.nft_contract,
+                        "nft_sender": data.nft_sender
+                    }).to_string().as_bytes(),
                     NO_DEPOSIT,
                     MIN_GAS_FOR_RESOLVE_BATCH,
                     GasWeight(10)
                 );
             },
-            DropType::FT(data) => {
+            DropAsset::FT(data) => {
+                // Assets with amount tiers refund what's left in the table
+                let amount = data.amount_tiers_total().map(|(_, total)| total).unwrap_or(data.ft_balance.0 * num_to_refund as u128);
                 // All FTs can be refunded at once. Funder responsible for registering themselves 
-                ext_ft_contract::ext(data.ft_contract)
+                ext_ft_contract::ext(data.ft_contract.clone())
                     // Call ft transfer with 1 yoctoNEAR. 1/2 unspent GAS will be added on top
                     .with_attached_deposit(1)
                     .ft_transfer(
-                        data.ft_sender, 
-                        U128(data.ft_balance.0 * num_to_refund as u128),
+                        data.ft_sender.clone(), 
+                        U128(amount),
                         None,
                     )
                 // We then resolve the promise and call nft_resolve_transfer on our own contract
@@ -267,11 +379,15 @@ impl DropZone {
                     Self::ext(env::current_account_id())
                         .ft_resolve_refund(
                             drop_id,
-                            num_to_refund
+                            num_to_refund,
+                            asset_index as u64,
+                            data.ft_contract.clone(),
+                            data.ft_sender.clone(),
+                            U128(amount)
                         )
-                ).as_return();
+                );
             },
-            _ => {env::panic_str("can only refund assets for FT and NFT drops")}
+            _ => {}
         };
     }
-}
\ No newline at end of file
+}
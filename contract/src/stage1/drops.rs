@@ -3,9 +3,9 @@ use near_sdk::{Balance, require};
 
 pub type DropId = u128;
 
+/// A single asset sent alongside the $NEAR balance every time a key in the drop is claimed
 #[derive(BorshSerialize, BorshDeserialize)]
-pub enum DropType {
-    Simple,
+pub enum DropAsset {
     NFT(NFTData),
     FT(FTData),
     FC(FCData),
@@ -21,8 +21,34 @@ pub struct KeyUsage {
     // When was the last time the key was used
     pub last_used: u64,
 
+    // Block height when the key was last used
+    pub last_used_block: u64,
+
+    // Epoch when the key was last used and how many times it was used in that epoch
+    pub last_used_epoch: u64,
+    pub claims_in_epoch: u64,
+
     // How much allowance does the key have left. When the key is deleted, this is refunded to the funder's balance.
     pub allowance: u128,
+
+    // Hash of the password needed for every use of the key: sha256(password + pk). Hidden from views.
+    #[serde(skip)]
+    pub pw_per_key: Option<Vec<u8>>,
+
+    // Hashes of the passwords needed for specific uses of the key: sha256(password + pk + use_number). Hidden from views.
+    // Uses are numbered starting at 1. A per-use password takes precedence over the per-key password.
+    #[serde(skip)]
+    pub pw_per_use: Option<HashMap<u64, Vec<u8>>>,
+}
+
+/// Password hash that must be provided for a specific use of a key
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonPasswordForUse {
+    // sha256(password + pk + use_number)
+    pub pw: Base64VecU8,
+    // Which use of the key (starting at 1) this password is for
+    pub key_use: u64,
 }
 
 /// Keep track of different configuration options for each key in a drop
@@ -36,9 +62,31 @@ pub struct DropConfig {
     // Measured in number of non-leap-nanoseconds since January 1, 1970 0:00:00 UTC.
     pub start_timestamp: Option<u64>,
 
+    // Block timestamp after which keys can no longer be used. If None, keys never expire.
+    // Once expired, anyone can sweep the drop to delete its keys and refund the funder.
+    pub end_timestamp: Option<u64>,
+
+    // Minimum block height that keys can be used. If None, keys can be used immediately
+    pub start_block: Option<u64>,
+
+    // Block height after which keys can no longer be used. If None, keys never expire.
+    pub end_block: Option<u64>,
+
     // How often can a key be used 
     pub usage_interval: Option<u64>,
 
+    // How many blocks must pass between uses of a key
+    pub usage_interval_blocks: Option<u64>,
+
+    // How many times can a key be used within a single epoch
+    pub max_claims_per_epoch: Option<u64>,
+
+    // How many claims can be made across all keys in the drop. If None, every claim of every key can be used.
+    pub max_total_claims: Option<u64>,
+
+    // How many times can the same account claim from the drop. If None, accounts can claim any number of times.
+    pub max_claims_per_account: Option<u64>,
+
     // If regular claim is called and no account is created, should the balance be refunded to the funder
     pub refund_if_claim: Option<bool>,
 
@@ -46,6 +94,28 @@ pub struct DropConfig {
     pub only_call_claim: Option<bool>
 }
 
+impl DropConfig {
+    /// Has the current block passed either the end timestamp or end block of the drop?
+    pub fn has_expired(&self) -> bool {
+        self.end_timestamp.map(|end| env::block_timestamp() > end).unwrap_or(false)
+            || self.end_block.map(|end| env::block_height() > end).unwrap_or(false)
+    }
+}
+
+impl Drop {
+    /// Can the account claim from the drop given its allowlist, denylist and new account suffix?
+    pub fn is_account_allowed(&self, account_id: &AccountId, is_new_account: bool) -> bool {
+        if self.denylist.contains(account_id) {
+            return false;
+        }
+
+        let matches_suffix = is_new_account && self.new_account_suffix.as_ref().map(|suffix| account_id.as_str().ends_with(suffix.as_str())).unwrap_or(false);
+        let restricted = self.allowlist_len > 0 || (is_new_account && self.new_account_suffix.is_some());
+        
+        !restricted || matches_suffix || self.allowlist.contains(account_id)
+    }
+}
+
 /// Keep track of specific data related to an access key. This allows us to optionally refund funders later. 
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Drop {
@@ -57,17 +127,39 @@ pub struct Drop {
     // Balance for all keys of this drop. Can be 0 if specified.
     pub balance: U128,
 
-    // Every drop must have a type
-    pub drop_type: DropType,
+    // Assets sent alongside the balance with every claim. Simple drops have no assets.
+    pub assets: Vec<DropAsset>,
 
     // The drop as a whole can have a config as well
     pub drop_config: DropConfig,
 
-    // How many claims
+    // How many claims are left across all keys. FT and NFT assets keep track of their own registered claims.
     pub num_claims_registered: u64,
 
     // Ensure this drop can only be used when the function has the required gas to attach
     pub required_gas_attached: Gas,
+
+    // How many claims have passed every check across all keys
+    pub total_claims: u64,
+
+    // How many times each account has claimed from the drop. Only tracked if max_claims_per_account is set.
+    pub claims_per_account: LookupMap<AccountId, u64>,
+
+    // Accounts that are allowed to claim. Only enforced if there's at least one account in the allowlist.
+    pub allowlist: LookupSet<AccountId>,
+    // How many accounts are in the allowlist
+    pub allowlist_len: u64,
+    // Accounts that can never claim from the drop
+    pub denylist: LookupSet<AccountId>,
+    // Suffix that new accounts created through `create_account_and_claim` are allowed to have (i.e `.mycompany.near`). 
+    // These accounts can claim even if they aren't in the allowlist.
+    pub new_account_suffix: Option<String>,
+
+    // Has the funder paused claims on the drop
+    pub paused: bool,
+
+    // How many FT and NFT refunds are waiting on their callback. Keys can't be deleted until every refund has resolved.
+    pub pending_refunds: u64,
 }
 
 #[near_bindgen]
@@ -78,37 +170,288 @@ impl DropZone {
         on this contract.
 
         The balance is the amount of $NEAR the sender wants each linkdrop to contain.
+        If a referrer is passed in, they're credited their share of the fees.
     */
     #[payable]
     pub fn create_drop(
         &mut self, 
         public_keys: Vec<PublicKey>, 
         balance: U128,
-        ft_data: Option<FTDataConfig>,
-        nft_data: Option<NFTDataConfig>,
-        fc_data: Option<FCData>,
-        drop_config: DropConfig
+        ft_data: Option<Vec<FTDataConfig>>,
+        nft_data: Option<Vec<NFTDataConfig>>,
+        fc_data: Option<Vec<FCData>>,
+        drop_config: DropConfig,
+        passwords_per_key: Option<Vec<Option<Base64VecU8>>>,
+        passwords_per_use: Option<Vec<Option<Vec<JsonPasswordForUse>>>>,
+        referrer: Option<AccountId>
     ) -> DropId {
-        // Ensure the user has only specified one type of callback data
-        let num_cbs_specified = ft_data.is_some() as u8 + nft_data.is_some() as u8 + fc_data.is_some() as u8;        
-        require!(num_cbs_specified <= 1, "You cannot specify more than one callback data");
+        self.assert_not_paused(PauseCategory::Creation);
+        // Funder is the predecessor
+        let funder_id = env::predecessor_account_id();
+        let (drop_id, required_deposit, storage_checks) = self.internal_create_drop(
+            funder_id,
+            public_keys.clone(),
+            balance,
+            ft_data,
+            nft_data,
+            fc_data,
+            drop_config,
+            passwords_per_key,
+            passwords_per_use,
+            referrer
+        );
+
+        // Drops with FT assets are finished once the FT contracts have been queried for their storage
+        if let Some((storage_checks, fees)) = storage_checks {
+            storage_checks.then(
+                Self::ext(env::current_account_id())
+                    // Resolve the promise with the min GAS. All unspent GAS will be added to this call.
+                    .with_static_gas(MIN_GAS_FOR_RESOLVE_STORAGE_CHECK)
+                    .resolve_storage_check(
+                        public_keys,
+                        drop_id,
+                        required_deposit,
+                        fees
+                    )
+            );
+        }
+
+        drop_id
+    }
+
+    /*
+        Allows users to add to an existing drop.
+        Only the funder can call this method. If a referrer is passed in, they're credited their share of the key fees.
+    */
+    #[payable]
+    pub fn add_to_drop(
+        &mut self, 
+        public_keys: Vec<PublicKey>, 
+        drop_id: DropId,
+        passwords_per_key: Option<Vec<Option<Base64VecU8>>>,
+        passwords_per_use: Option<Vec<Option<Vec<JsonPasswordForUse>>>>,
+        referrer: Option<AccountId>
+    ) -> DropId {
+        self.assert_not_paused(PauseCategory::Creation);
+        let mut drop = self.drop_for_id.get(&drop_id).expect("no drop found for ID");
+        let drop_config = &drop.drop_config;
+        let funder = &drop.funder_id;
+
+        require!(funder == &env::predecessor_account_id(), "only funder can add to drops");
+        require!(!drop_config.has_expired(), "cannot add keys to a drop that has expired");
+        // The amount tiers were sized for the claims the drop was created with
+        require!(
+            !drop.assets.iter().any(|asset| matches!(asset, DropAsset::FT(data) if data.amount_tiers.is_some())),
+            "cannot add keys to a drop with FT amount tiers"
+        );
+        assert_passwords_match_keys(&passwords_per_key, &passwords_per_use, public_keys.len());
+
+        let len = public_keys.len() as u128;
+
+        /*
+            Add data to storage
+        */
+        // Pessimistically measure storage
+        let initial_storage = env::storage_usage();
+        
+        // Get the number of claims per key
+        let num_claims_per_key = drop_config.max_claims_per_key;
+
+        // get the existing key set and add new PKs
+        let mut exiting_key_map = drop.pks;
+        
+        // The allowance is the base * number of claims per key since each claim can potentially use the max pessimistic GAS.
+        let actual_allowance = self.calculate_base_allowance(drop.required_gas_attached) * num_claims_per_key as u128;
+        // Loop through and add each drop ID to the public keys. Also populate the key set.
+        for (i, pk) in public_keys.clone().into_iter().enumerate() {
+            let (pw_per_key, pw_per_use) = key_passwords(&passwords_per_key, &passwords_per_use, i, num_claims_per_key);
+            exiting_key_map.insert(&pk, &KeyUsage {
+                num_uses: num_claims_per_key,
+                last_used: 0, // Set to 0 since this will make the key always claimable.
+                last_used_block: 0,
+                last_used_epoch: 0,
+                claims_in_epoch: 0,
+                allowance: actual_allowance,
+                pw_per_key,
+                pw_per_use,
+            });
+            require!(self.drop_id_for_pk.insert(&pk, &drop_id).is_none(), "Keys cannot belong to another drop");
+        }
+
+        // Set the drop's PKs to the newly populated set
+        drop.pks = exiting_key_map;
+
+        // Decide what methods the access keys can call
+        let mut access_key_method_names = ACCESS_KEY_BOTH_METHOD_NAMES;
+        if drop_config.only_call_claim.unwrap_or(false) {
+            access_key_method_names = ACCESS_KEY_CLAIM_METHOD_NAME;
+        }
+
+        // Increment the claims left for the drop. FT and NFT assets are registered separately when the assets are sent.
+        drop.num_claims_registered += num_claims_per_key * len as u64;
+
+        // If GAS is specified for a function call, the keys can only call claim
+        for asset in &drop.assets {
+            if let DropAsset::FC(data) = asset {
+                if data.gas_if_straight_execute.is_some() {
+                    access_key_method_names = ACCESS_KEY_CLAIM_METHOD_NAME;
+                }
+            }
+        }
+
+        // Add the drop back in for the drop ID 
+        self.drop_for_id.insert(
+            &drop_id, 
+            &drop
+        );
+        // Count the keys towards the funder's volume tiers. The record is charged as part of the storage.
+        let keys_before = self.internal_record_keys_for_funder(funder, len as u64);
+        
+        // Get the current balance of the funder. 
+        let mut current_user_balance = self.user_balances.get(funder).expect("No user balance found");
+        debug_log!("Cur user balance {}", yocto_to_near(current_user_balance));
+        
+        // Get the costs for every claim summed across all the drop's assets
+        let claim_costs = per_claim_costs(&drop.assets, &drop.drop_config, drop.balance.0);
+        // Function call deposits can differ for each use of a key so they're summed across all uses
+        let fc_deposits = fc_deposits_for_uses_left(&drop.assets, num_claims_per_key, num_claims_per_key);
+        
+        // Calculate the storage being used for the entire drop
+        let final_storage = env::storage_usage();
+        let total_required_storage = storage_cost(final_storage - initial_storage, env::storage_byte_cost());
+        debug_log!("Total required storage Yocto {}", total_required_storage);
+
+        // Required deposit is the storage + key fees for the public keys (plus all other basic stuff). There's no drop fee since the drop exists.
+        let (_, key_fees) = self.internal_fees_for(funder, self.fee_multipliers.for_assets(&drop.assets), keys_before, len as u64);
+        let deposit = required_deposit(&KeyDepositInputs {
+            num_keys: len,
+            claims_per_key: num_claims_per_key,
+            drop_fee: 0,
+            key_fees,
+            allowance_per_key: actual_allowance,
+            fc_deposits_per_key: fc_deposits,
+            storage: total_required_storage,
+            per_claim: claim_costs,
+        });
+        // Fees prepaid with fungible tokens are taken out of the deposit
+        let fee_credit = self.internal_use_fee_credit(funder, deposit.fees);
+        let required_deposit = deposit.total() - fee_credit;
+        debug_log!(
+            "Current User Balance: {}, 
+            Required Deposit: {}, 
+            Total required storage: {}, 
+            Key fees: {}, 
+            ACCESS_KEY_ALLOWANCE: {},
+            ACCESS_KEY_STORAGE: {}, 
+            Balance per key: {}, 
+            optional costs: {}, 
+            function call deposits per key: {}, 
+            Number of claims per key: {},
+            length: {}", 
+            yocto_to_near(current_user_balance), 
+            yocto_to_near(required_deposit),
+            yocto_to_near(total_required_storage),
+            yocto_to_near(key_fees), 
+            yocto_to_near(actual_allowance), 
+            yocto_to_near(ACCESS_KEY_STORAGE), 
+            yocto_to_near(drop.balance.0), 
+            yocto_to_near(claim_costs.total() - claim_costs.balance - claim_costs.access_key_storage), 
+            yocto_to_near(fc_deposits), 
+            num_claims_per_key,
+            len
+        );
+        /*
+            Ensure the attached deposit can cover: 
+        */ 
+        require!(current_user_balance >= required_deposit, "Not enough deposit");
+        // Decrement the user's balance by the required deposit and insert back into the map
+        current_user_balance -= required_deposit;
+        self.user_balances.insert(funder, &current_user_balance);
+        debug_log!("New user balance {}", yocto_to_near(current_user_balance));
+
+        // Increment our fees earned. The referrer gets their share of the fees paid in $NEAR if one was passed in.
+        self.internal_collect_fees(funder, deposit.fees - fee_credit, referrer, drop_id);
+
+        emit_event(EventLogVariant::KeyAddition(KeyAdditionLog {
+            funder_id: funder.clone(),
+            drop_id: U128(drop_id),
+            public_keys: public_keys.clone(),
+            required_deposit: U128(required_deposit),
+        }));
+        
+        // Create a new promise batch to create all the access keys
+        let current_account_id = env::current_account_id();
+        let promise = env::promise_batch_create(&current_account_id);
+        
+        // Loop through each public key and create the access keys
+        for pk in public_keys.clone() {
+            // Must assert in the loop so no access keys are made?
+            env::promise_batch_action_add_key_with_function_call(
+                promise, 
+                &pk, 
+                0, 
+                actual_allowance, 
+                &current_account_id, 
+                access_key_method_names
+            );
+        }
+
+        env::promise_return(promise);
+
+        drop_id
+    }
+}
+
+impl DropZone {
+    /*
+        Create a drop for the funder and take the required deposit out of their balance. Drops without FT assets have
+        their access keys added straight away. For drops with FT assets, the joined storage balance bounds queries are
+        returned along with the fees to collect so the caller can chain the resolver that adds the keys.
+    */
+    pub(crate) fn internal_create_drop(
+        &mut self,
+        funder_id: AccountId,
+        public_keys: Vec<PublicKey>, 
+        balance: U128,
+        ft_data: Option<Vec<FTDataConfig>>,
+        nft_data: Option<Vec<NFTDataConfig>>,
+        fc_data: Option<Vec<FCData>>,
+        drop_config: DropConfig,
+        passwords_per_key: Option<Vec<Option<Base64VecU8>>>,
+        passwords_per_use: Option<Vec<Option<Vec<JsonPasswordForUse>>>>,
+        referrer: Option<AccountId>
+    ) -> (DropId, Balance, Option<(Promise, DropCreationFees)>) {
+        // Every drop can contain any number of FT, NFT and FC assets alongside the $NEAR balance
+        let ft_data = ft_data.unwrap_or_default();
+        let nft_data = nft_data.unwrap_or_default();
+        let fc_data = fc_data.unwrap_or_default();
+        let num_assets = ft_data.len() + nft_data.len() + fc_data.len();
 
         // Warn if the balance for each drop is less than the minimum
         if balance.0 < NEW_ACCOUNT_BASE {
-            env::log_str(&format!("Warning: Balance is less than absolute minimum for creating an account: {}", NEW_ACCOUNT_BASE));
+            debug_log!("Warning: Balance is less than absolute minimum for creating an account: {}", NEW_ACCOUNT_BASE);
         }
 
-        // Funder is the predecessor
-        let funder_id = env::predecessor_account_id();
         let len = public_keys.len() as u128;
         let drop_id = self.nonce;
         // Get the number of claims per key to dictate what key usage data we should put in the map
         let num_claims_per_key = drop_config.max_claims_per_key;
         require!(num_claims_per_key > 0, "cannot have less than 1 claim per key");
+        if let (Some(start), Some(end)) = (drop_config.start_timestamp, drop_config.end_timestamp) {
+            require!(start < end, "end timestamp must be after the start timestamp");
+        }
+        if let (Some(start), Some(end)) = (drop_config.start_block, drop_config.end_block) {
+            require!(start < end, "end block must be after the start block");
+        }
+        require!(drop_config.max_claims_per_epoch != Some(0), "cannot have less than 1 claim per epoch");
+        require!(drop_config.max_total_claims != Some(0), "cannot have less than 1 total claim");
+        require!(drop_config.max_claims_per_account != Some(0), "cannot have less than 1 claim per account");
+        require!(!drop_config.has_expired(), "cannot create a drop that has already expired");
+        assert_passwords_match_keys(&passwords_per_key, &passwords_per_use, public_keys.len());
 
         // Get the current balance of the funder. 
         let mut current_user_balance = self.user_balances.get(&funder_id).expect("No user balance found");
-        env::log_str(&format!("Cur User balance {}", yocto_to_near(current_user_balance)));
+        debug_log!("Cur User balance {}", yocto_to_near(current_user_balance));
         
         // Pessimistically measure storage
         let initial_storage = env::storage_usage();
@@ -123,181 +466,202 @@ impl DropZone {
             access_key_method_names = ACCESS_KEY_CLAIM_METHOD_NAME;
         }
 
-        // Default the gas to attach to be the gas from the wallet. This will be used to calculate allowances.
-        let mut gas_to_attach = ATTACHED_GAS_FROM_WALLET;
         // Depending on the FC Data, set the Gas to attach and the access key method names
-        if let Some(data) = fc_data.clone() {
+        for data in &fc_data {
+            // Reject malformed args now rather than having the function calls fail when keys are claimed
+            data.assert_valid_args();
+
+            // Either a schedule with an entry for every use of a key or a list of methods to call on every use must be specified
+            if let Some(schedule) = &data.methods_per_use {
+                require!(schedule.len() as u64 == num_claims_per_key, "methods_per_use must have one entry per claim for each key");
+            } else {
+                require!(!data.methods.is_empty(), "function call data must have at least one method");
+            }
+
             if let Some(gas) = data.gas_if_straight_execute {
+                require!(num_assets == 1, "gas_if_straight_execute can only be specified if the function call is the only asset in the drop");
                 require!(gas <= ATTACHED_GAS_FROM_WALLET - GAS_OFFSET_IF_FC_EXECUTE, &format!("cannot attach more than {:?} GAS.", ATTACHED_GAS_FROM_WALLET - GAS_OFFSET_IF_FC_EXECUTE));
-                gas_to_attach = gas + GAS_OFFSET_IF_FC_EXECUTE;
+                require!(
+                    (0..num_claims_per_key).all(|use_index| data.clone().for_use(use_index).total_min_gas() <= gas), 
+                    "the minimum GAS across all methods cannot be greater than gas_if_straight_execute"
+                );
                 access_key_method_names = ACCESS_KEY_CLAIM_METHOD_NAME;
             }
         }
 
-        // Calculate the base allowance to attach
-        let calculated_base_allowance = self.calculate_base_allowance(gas_to_attach);
-        // The actual allowance is the base * number of claims per key since each claim can potentially use the max pessimistic GAS.
-        let actual_allowance = calculated_base_allowance * num_claims_per_key as u128;
+        // Get the GAS the keys must attach. This will be used to calculate allowances.
+        let gas_to_attach = gas_for_assets(num_assets, &fc_data);
+        // The allowance is the base * number of claims per key since each claim can potentially use the max pessimistic GAS.
+        let actual_allowance = self.calculate_base_allowance(gas_to_attach) * num_claims_per_key as u128;
         
         // Loop through and add each drop ID to the public keys. Also populate the key set.
-        for pk in &public_keys {
+        for (i, pk) in public_keys.iter().enumerate() {
+            let (pw_per_key, pw_per_use) = key_passwords(&passwords_per_key, &passwords_per_use, i, num_claims_per_key);
             key_map.insert(pk, &KeyUsage {
                 num_uses: num_claims_per_key,
                 last_used: 0, // Set to 0 since this will make the key always claimable.
+                last_used_block: 0,
+                last_used_epoch: 0,
+                claims_in_epoch: 0,
                 allowance: actual_allowance,
+                pw_per_key,
+                pw_per_use,
             });
             require!(self.drop_id_for_pk.insert(pk, &drop_id).is_none(), "Keys cannot belong to another drop");
         }
 
         // Add this drop ID to the funder's set of drops
-        self.internal_add_drop_to_funder(&env::predecessor_account_id(), &drop_id);
+        self.internal_add_drop_to_funder(&funder_id, &drop_id);
+        // Count the keys towards the funder's volume tiers. The record is charged as part of the drop's storage.
+        let keys_before = self.internal_record_keys_for_funder(&funder_id, len as u64);
 
-        // Create drop object 
+        // Create drop object. Assets are pushed in the order FTs, NFTs, FCs.
         let mut drop = Drop { 
-            funder_id: env::predecessor_account_id(), 
+            funder_id: funder_id.clone(), 
             balance, 
             pks: key_map,
-            drop_type: DropType::Simple, // Default to simple but will overwrite if not
+            assets: Vec::with_capacity(num_assets),
             drop_config: drop_config.clone(),
             num_claims_registered: num_claims_per_key * len as u64,
-            required_gas_attached: gas_to_attach
+            required_gas_attached: gas_to_attach,
+            total_claims: 0,
+            claims_per_account: LookupMap::new(StorageKey::ClaimsPerAccountForDrop {
+                // We get a new unique prefix for the collection
+                account_id_hash: hash_account_id(&format!("claims-{}{}", self.nonce, funder_id)),
+            }),
+            allowlist: LookupSet::new(StorageKey::AllowlistForDrop {
+                account_id_hash: hash_account_id(&format!("allow-{}{}", self.nonce, funder_id)),
+            }),
+            allowlist_len: 0,
+            denylist: LookupSet::new(StorageKey::DenylistForDrop {
+                account_id_hash: hash_account_id(&format!("deny-{}{}", self.nonce, funder_id)),
+            }),
+            new_account_suffix: None,
+            paused: false,
+            pending_refunds: 0,
         };
 
-        
-        // For NFT drops, measure the storage for adding the longest token ID
+        // Cast each FT config to actual FT data. The storage is set once the FT contract has been queried.
+        for FTDataConfig{ft_sender, ft_contract, ft_balance, amount_tiers} in ft_data.clone() {
+            assert_valid_amount_tiers(&amount_tiers, num_claims_per_key * len as u64);
+            drop.assets.push(DropAsset::FT(FTData {
+                ft_contract,
+                ft_sender,
+                ft_balance,
+                ft_storage: U128(u128::MAX),
+                // The number of claims is 0 until FTs are sent to the contract
+                num_claims_registered: 0,
+                amount_tiers,
+            }));
+        }
+
+        // For NFT assets, measure the storage for adding the longest token ID. This is summed across all NFT assets.
         let mut storage_per_longest = 0;
-        // If NFT data was provided, we need to build the set of token IDs and cast the config to actual NFT data
-        if let Some(data) = nft_data {
+        // The NFT configs are consumed below but the drop creation event needs to know how many there were
+        let num_nft_assets = nft_data.len();
+        for (nft_index, data) in nft_data.into_iter().enumerate() {
             let NFTDataConfig{nft_sender, nft_contract, longest_token_id} = data;
 
-            // Create the token ID set and insert the longest token ID
-            let token_ids = UnorderedSet::new(StorageKey::TokenIdsForDrop {
+            // Create the token ID set for this asset
+            let mut token_ids = UnorderedSet::new(StorageKey::TokenIdsForDrop {
                 //we get a new unique prefix for the collection
-                account_id_hash: hash_account_id(&format!("nft-{}{}", self.nonce, funder_id)),
+                account_id_hash: hash_account_id(&format!("nft-{}{}-{}", self.nonce, funder_id, nft_index)),
             });
 
-            // Create the NFT data
-            let actual_nft_data = NFTData {
-                nft_sender,
-                nft_contract,
-                longest_token_id: longest_token_id.clone(),
-                storage_for_longest: u128::MAX,
-                token_ids,
-            };
-
-            // The number of claims is 0 until NFTs are sent to the contract
-            drop.num_claims_registered = 0;
-            drop.drop_type = DropType::NFT(actual_nft_data);
-            
-            // Add the drop with the empty token IDs
-            self.drop_for_id.insert(
-                &drop_id, 
-                &drop
-            );
-            
-            // Measure how much storage it costs to insert the 1 longest token ID
+            // Measure how much storage it costs to insert the 1 longest token ID and then clear the set
             let initial_nft_storage_one = env::storage_usage();
-            // Now that the drop has been added, insert the longest token ID and measure storage
-            if let DropType::NFT(data) = &mut drop.drop_type {
-                data.token_ids.insert(&longest_token_id);
-            }
-
-            // Add drop with the longest possible token ID and max storage
-            self.drop_for_id.insert(
-                &drop_id, 
-                &drop
-            );
+            token_ids.insert(&longest_token_id);
             let final_nft_storage_one = env::storage_usage();
-            env::log_str(&format!("i1: {} f1: {}", initial_nft_storage_one, final_nft_storage_one));
+            debug_log!("i1: {} f1: {}", initial_nft_storage_one, final_nft_storage_one);
+            token_ids.clear();
 
             // Measure the storage per single longest token ID
-            storage_per_longest = Balance::from(final_nft_storage_one - initial_nft_storage_one);
-            env::log_str(&format!("TOKS BEFORE {:?}", self.get_token_ids_for_drop(self.nonce, None, None)));
+            let storage_for_longest = Balance::from(final_nft_storage_one - initial_nft_storage_one);
+            storage_per_longest += storage_for_longest;
 
-            // Clear the token IDs so it's an empty set and put the storage in the drop's nft data
-            if let DropType::NFT(data) = &mut drop.drop_type {
-                data.token_ids.clear();
-                data.storage_for_longest = storage_per_longest;
-            }
-
-            self.drop_for_id.insert(
-                &drop_id, 
-                &drop
-            );
-        } else if let Some(data) = ft_data.clone() {
-            // If FT Data was provided, we need to cast the FT Config to actual FT data and insert into the drop type
-            let FTDataConfig{ft_sender, ft_contract, ft_balance} = data;
-
-            // Create the NFT data
-            let actual_ft_data = FTData {
-                ft_contract,
-                ft_sender,
-                ft_balance,
-                ft_storage: U128(u128::MAX),
-            };
+            drop.assets.push(DropAsset::NFT(NFTData {
+                nft_sender,
+                nft_contract,
+                longest_token_id,
+                storage_for_longest,
+                token_ids,
+                // The number of claims is 0 until NFTs are sent to the contract
+                num_claims_registered: 0,
+            }));
+        }
 
-            // The number of claims is 0 until FTs are sent to the contract
-            drop.num_claims_registered = 0;
-            drop.drop_type = DropType::FT(actual_ft_data);
-            
-            // Add the drop with the empty token IDs
-            self.drop_for_id.insert(
-                &drop_id, 
-                &drop
-            );
-        } else if let Some(data) = fc_data.clone() {
-            drop.drop_type = DropType::FC(data);
-            
-            // Add the drop with the empty token IDs
-            self.drop_for_id.insert(
-                &drop_id, 
-                &drop
-            );
-        } else {
-            // In simple case, we just insert the drop with whatever it was initialized with.
-            self.drop_for_id.insert(
-                &drop_id, 
-                &drop
-            );
+        // Function calls can be added straight to the drop
+        for data in fc_data.clone() {
+            drop.assets.push(DropAsset::FC(data));
         }
 
-        // Calculate the storage being used for the entire drop
+        // Add the drop with all its assets
+        self.drop_for_id.insert(
+            &drop_id, 
+            &drop
+        );
+
+        // Calculate the storage being used for the entire drop. The storage for the token IDs is paid for with every claim.
         let final_storage = env::storage_usage();
-        let total_required_storage = (Balance::from(final_storage - initial_storage) + storage_per_longest) * env::storage_byte_cost();
-        env::log_str(&format!("Total required storage Yocto {}", total_required_storage));
+        let total_required_storage = storage_cost(final_storage - initial_storage, env::storage_byte_cost());
+        debug_log!("Total required storage Yocto {}", total_required_storage);
 
         // Increment the drop ID nonce
         self.nonce += 1;
 
-        let required_deposit = self.drop_fee + total_required_storage + (self.key_fee + actual_allowance + (ACCESS_KEY_STORAGE + balance.0 + if fc_data.is_some() {fc_data.clone().unwrap().deposit.0} else {0} + storage_per_longest * env::storage_byte_cost()) * num_claims_per_key as u128) * len;
-        env::log_str(&format!(
+        // Sum the deposits for every method of every function call across all uses of a key
+        let total_fc_deposits: u128 = fc_data.iter().map(|data| data.deposit_for_uses_left(num_claims_per_key, num_claims_per_key)).sum();
+        let (drop_fee, key_fees) = self.internal_fees_for(&funder_id, self.fee_multipliers.for_assets(&drop.assets), keys_before, len as u64);
+        // The FT storage isn't known until the FT contracts have been queried so it's charged in the resolver
+        let deposit = required_deposit(&KeyDepositInputs {
+            num_keys: len,
+            claims_per_key: num_claims_per_key,
+            drop_fee,
+            key_fees,
+            allowance_per_key: actual_allowance,
+            fc_deposits_per_key: total_fc_deposits,
+            storage: total_required_storage,
+            per_claim: PerClaimCosts {
+                balance: balance.0,
+                access_key_storage: ACCESS_KEY_STORAGE,
+                nft_storage: storage_cost(storage_per_longest as u64, env::storage_byte_cost()),
+                ft_storage: 0,
+                claimed_account_storage: claimed_account_storage_per_claim(&drop_config),
+            },
+        });
+        // Fees prepaid with fungible tokens are taken out of the deposit
+        let fee_credit = self.internal_use_fee_credit(&funder_id, deposit.fees);
+        let required_deposit = deposit.total() - fee_credit;
+        debug_log!(
             "Current balance: {}, 
             Required Deposit: {}, 
             Drop Fee: {}, 
             Total Required Storage: {}, 
-            Key Fee: {}, 
+            Key Fees: {}, 
             ACCESS_KEY_STORAGE: {},
             ACCESS_KEY_ALLOWANCE: {}, 
             Linkdrop Balance: {}, 
-            total function call deposits (if applicable): {}, 
+            total function call deposits per key (if applicable): {}, 
             Storage for longest token ID (if applicable): {},
             Num claims per key: {}
+            Num assets: {}
             length: {}
             GAS to attach: {}", 
             yocto_to_near(current_user_balance), 
             yocto_to_near(required_deposit),
-            yocto_to_near(self.drop_fee),
+            yocto_to_near(drop_fee),
             yocto_to_near(total_required_storage), 
-            yocto_to_near(self.key_fee),
+            yocto_to_near(key_fees),
             yocto_to_near(ACCESS_KEY_STORAGE), 
             yocto_to_near(actual_allowance), 
             yocto_to_near(balance.0), 
-            yocto_to_near(if fc_data.is_some() {fc_data.clone().unwrap().deposit.0} else {0}), 
-            yocto_to_near(storage_per_longest * env::storage_byte_cost()), 
+            yocto_to_near(total_fc_deposits), 
+            yocto_to_near(storage_cost(storage_per_longest as u64, env::storage_byte_cost())), 
             num_claims_per_key,
+            num_assets,
             len,
             gas_to_attach.0
-        ));
+        );
 
         /*
             Ensure the attached deposit can cover: 
@@ -306,19 +670,38 @@ impl DropZone {
         // Decrement the user's balance by the required deposit and insert back into the map
         current_user_balance -= required_deposit;
         self.user_balances.insert(&funder_id, &current_user_balance);
-        env::log_str(&format!("New user balance {}", yocto_to_near(current_user_balance)));
+        debug_log!("New user balance {}", yocto_to_near(current_user_balance));
+
+        // The referrer gets their share of the fees paid in $NEAR if one was passed in. Drops with FT assets only
+        // collect the fees once the storage check succeeds so a failed check can refund everything.
+        assert_valid_referrer(&funder_id, &referrer);
+        let fees = DropCreationFees { fees: U128(deposit.fees - fee_credit), fee_credit: U128(fee_credit), referrer };
+        if ft_data.is_empty() {
+            self.internal_collect_fees(&funder_id, fees.fees.0, fees.referrer.clone(), drop_id);
+        }
 
-        // Increment our fees earned
-        self.fees_collected += self.drop_fee + self.key_fee * len;
-        env::log_str(&format!("Fees collected {}", yocto_to_near(self.drop_fee + self.key_fee * len)));
+        // Assets are in the order FTs, NFTs, FCs
+        let asset_types = ft_data.iter().map(|_| "ft")
+            .chain((0..num_nft_assets).map(|_| "nft"))
+            .chain(fc_data.iter().map(|_| "fc"))
+            .map(|asset_type| asset_type.to_string())
+            .collect();
+        emit_event(EventLogVariant::DropCreation(DropCreationLog {
+            funder_id: funder_id.clone(),
+            drop_id: U128(drop_id),
+            num_keys: len as u64,
+            balance,
+            asset_types,
+            required_deposit: U128(required_deposit),
+        }));
 
         let current_account_id = env::current_account_id();
         
         /*
-            Only add the access keys if it's not a FT drop. If it is,
+            Only add the access keys if there are no FT assets. If there are,
             keys will be added in the FT resolver
         */
-        if ft_data.is_none() {
+        if ft_data.is_empty() {
             // Create a new promise batch to create all the access keys
             let promise = env::promise_batch_create(&current_account_id);
             
@@ -338,184 +721,27 @@ impl DropZone {
             env::promise_return(promise);
         } else {
             /*
-                Get the storage required by the FT contract and ensure the user has attached enough
-                deposit to cover the storage and perform refunds if they overpayed.
+                Get the storage required by each FT contract and ensure the user has attached enough
+                deposit to cover the storage and perform refunds if they overpayed. The queries are
+                joined so the resolver receives one result per FT asset in the same order as the assets.
             */ 
+            let mut storage_checks: Option<Promise> = None;
+            for data in ft_data {
+                let storage_check = ext_ft_contract::ext(data.ft_contract)
+                    // Call storage balance bounds with exactly this amount of GAS. No unspent GAS will be added on top.
+                    .with_static_gas(GAS_FOR_STORAGE_BALANCE_BOUNDS)
+                    .with_unused_gas_weight(0)
+                    .storage_balance_bounds();
+
+                storage_checks = Some(match storage_checks {
+                    Some(checks) => checks.and(storage_check),
+                    None => storage_check
+                });
+            }
 
-            ext_ft_contract::ext(ft_data.unwrap().ft_contract)
-                // Call storage balance bounds with exactly this amount of GAS. No unspent GAS will be added on top.
-                .with_static_gas(GAS_FOR_STORAGE_BALANCE_BOUNDS)
-                .with_unused_gas_weight(0)
-                .storage_balance_bounds()
-            .then(
-                Self::ext(current_account_id)
-                    // Resolve the promise with the min GAS. All unspent GAS will be added to this call.
-                    .with_static_gas(MIN_GAS_FOR_RESOLVE_STORAGE_CHECK)
-                    .resolve_storage_check(
-                        public_keys,
-                        drop_id,
-                        required_deposit
-                    )
-            );
-        }
-
-        drop_id
-    }
-
-    /*
-        Allows users to add to an existing drop.
-        Only the funder can call this method
-    */
-    #[payable]
-    pub fn add_to_drop(
-        &mut self, 
-        public_keys: Vec<PublicKey>, 
-        drop_id: DropId
-    ) -> DropId {
-        let mut drop = self.drop_for_id.get(&drop_id).expect("no drop found for ID");
-        let drop_config = &drop.drop_config;
-        let funder = &drop.funder_id;
-
-        require!(funder == &env::predecessor_account_id(), "only funder can add to drops");
-
-        let len = public_keys.len() as u128;
-
-        /*
-            Add data to storage
-        */
-        // Pessimistically measure storage
-        let initial_storage = env::storage_usage();
-        
-        // Get the number of claims per key
-        let num_claims_per_key = drop_config.max_claims_per_key;
-
-        // get the existing key set and add new PKs
-        let mut exiting_key_map = drop.pks;
-        
-        // Calculate the base allowance to attach
-        let calculated_base_allowance = self.calculate_base_allowance(drop.required_gas_attached);
-        // The actual allowance is the base * number of claims per key since each claim can potentially use the max pessimistic GAS.
-        let actual_allowance = calculated_base_allowance * num_claims_per_key as u128;
-        // Loop through and add each drop ID to the public keys. Also populate the key set.
-        for pk in public_keys.clone() {
-            exiting_key_map.insert(&pk, &KeyUsage {
-                num_uses: num_claims_per_key,
-                last_used: 0, // Set to 0 since this will make the key always claimable.
-                allowance: actual_allowance
-            });
-            require!(self.drop_id_for_pk.insert(&pk, &drop_id).is_none(), "Keys cannot belong to another drop");
-        }
-
-        // Set the drop's PKs to the newly populated set
-        drop.pks = exiting_key_map;
-
-        // Decide what methods the access keys can call
-        let mut access_key_method_names = ACCESS_KEY_BOTH_METHOD_NAMES;
-        if drop_config.only_call_claim.unwrap_or(false) {
-            access_key_method_names = ACCESS_KEY_CLAIM_METHOD_NAME;
-        }
-
-        // Increment the claims registered if drop is FC or Simple
-        match &drop.drop_type {
-            DropType::FC(data) => {
-                drop.num_claims_registered += num_claims_per_key * len as u64;
-                
-                // If GAS is specified, set the GAS to attach for allowance calculations
-                if let Some(_) = data.gas_if_straight_execute {
-                    access_key_method_names = ACCESS_KEY_CLAIM_METHOD_NAME;
-                }
-            },
-            DropType::Simple => {
-                drop.num_claims_registered += num_claims_per_key * len as u64;
-            },
-            _ => {}
-        };
-
-        // Add the drop back in for the drop ID 
-        self.drop_for_id.insert(
-            &drop_id, 
-            &drop
-        );
-        
-        // Get the current balance of the funder. 
-        let mut current_user_balance = self.user_balances.get(&funder).expect("No user balance found");
-        env::log_str(&format!("Cur user balance {}", yocto_to_near(current_user_balance)));
-        
-        // Get optional costs for the drop
-        let optional_costs = match drop.drop_type {
-            DropType::FC(data) => {
-                data.deposit.0
-            },
-            DropType::NFT(data) => {
-                data.storage_for_longest * env::storage_byte_cost()
-            },
-            DropType::FT(data) => {
-                data.ft_storage.0
-            },
-            _ => {0}
-        };
-        
-        // Calculate the storage being used for the entire drop
-        let final_storage = env::storage_usage();
-        let total_required_storage = Balance::from(final_storage - initial_storage) * env::storage_byte_cost();
-        env::log_str(&format!("Total required storage Yocto {}", total_required_storage));
-
-        // Required deposit is the existing storage per key + key fee * length of public keys (plus all other basic stuff)
-        let required_deposit = total_required_storage + (self.key_fee + actual_allowance + (ACCESS_KEY_STORAGE + drop.balance.0 + optional_costs) * num_claims_per_key as u128) * len;
-        env::log_str(&format!(
-            "Current User Balance: {}, 
-            Required Deposit: {}, 
-            Total required storage: {}, 
-            Key fee: {}, 
-            ACCESS_KEY_ALLOWANCE: {},
-            ACCESS_KEY_STORAGE: {}, 
-            Balance per key: {}, 
-            optional costs: {}, 
-            Number of claims per key: {},
-            length: {}", 
-            yocto_to_near(current_user_balance), 
-            yocto_to_near(required_deposit),
-            yocto_to_near(total_required_storage),
-            yocto_to_near(self.key_fee), 
-            yocto_to_near(actual_allowance), 
-            yocto_to_near(ACCESS_KEY_STORAGE), 
-            yocto_to_near(drop.balance.0), 
-            yocto_to_near(optional_costs), 
-            num_claims_per_key,
-            len
-        ));
-        /*
-            Ensure the attached deposit can cover: 
-        */ 
-        require!(current_user_balance >= required_deposit, "Not enough deposit");
-        // Decrement the user's balance by the required deposit and insert back into the map
-        current_user_balance -= required_deposit;
-        self.user_balances.insert(&funder, &current_user_balance);
-        env::log_str(&format!("New user balance {}", yocto_to_near(current_user_balance)));
-
-        // Increment our fees earned
-        self.fees_collected += self.key_fee * len;
-        env::log_str(&format!("Fees collected {}", yocto_to_near(self.key_fee * len)));
-        
-        // Create a new promise batch to create all the access keys
-        let current_account_id = env::current_account_id();
-        let promise = env::promise_batch_create(&current_account_id);
-        
-        // Loop through each public key and create the access keys
-        for pk in public_keys.clone() {
-            // Must assert in the loop so no access keys are made?
-            env::promise_batch_action_add_key_with_function_call(
-                promise, 
-                &pk, 
-                0, 
-                actual_allowance, 
-                &current_account_id, 
-                access_key_method_names
-            );
+            return (drop_id, required_deposit, storage_checks.map(|checks| (checks, fees)));
         }
 
-        env::promise_return(promise);
-
-        drop_id
+        (drop_id, required_deposit, None)
     }
-}
\ No newline at end of file
+}
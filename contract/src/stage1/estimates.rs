@@ -0,0 +1,261 @@
+use crate::*;
+
+/// Itemised breakdown of the deposit needed to create a drop or add keys to one
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonCostEstimate {
+    pub drop_fee: U128,
+    pub key_fees: U128,
+    // Fees prepaid with fungible tokens that are taken out of the total
+    pub fee_credit: U128,
+    // Allowance attached to the access keys. Whatever isn't burned is refunded.
+    pub allowance: U128,
+    pub access_key_storage: U128,
+    // Estimated from the size of the records that will be written. The actual storage is measured when the call is made.
+    pub contract_storage: U128,
+    pub fc_deposits: U128,
+    pub nft_storage: U128,
+    pub ft_storage: U128,
+    // $NEAR sent with every claim
+    pub balance: U128,
+    // Storage for recording the claiming accounts if claims per account are limited
+    pub claimed_account_storage: U128,
+    pub total: U128,
+    // FT registration storage is only known once the FT contracts have been queried. Views can't make cross contract calls.
+    pub ft_storage_included: bool,
+}
+
+/// Turn the required deposit into an itemised estimate. FT storage charged once the FT contracts have been queried is passed separately.
+fn itemise(deposit: RequiredDeposit, drop_fee: Balance, fee_credit: Balance, per_claim: PerClaimCosts, total_claims: u128, resolved_ft_storage: Balance, ft_storage_included: bool) -> JsonCostEstimate {
+    let fee_credit = fee_credit.min(deposit.fees);
+    JsonCostEstimate {
+        drop_fee: U128(drop_fee),
+        key_fees: U128(deposit.fees - drop_fee),
+        fee_credit: U128(fee_credit),
+        allowance: U128(deposit.allowance),
+        access_key_storage: U128(per_claim.access_key_storage * total_claims),
+        contract_storage: U128(deposit.storage),
+        fc_deposits: U128(deposit.fc_deposits),
+        nft_storage: U128(per_claim.nft_storage * total_claims),
+        ft_storage: U128(per_claim.ft_storage * total_claims + resolved_ft_storage),
+        balance: U128(per_claim.balance * total_claims),
+        claimed_account_storage: U128(per_claim.claimed_account_storage * total_claims),
+        total: U128(deposit.total() - fee_credit + resolved_ft_storage),
+        ft_storage_included,
+    }
+}
+
+/// Length of a value once serialized into storage
+fn borsh_len<T: BorshSerialize>(value: &T) -> usize {
+    value.try_to_vec().expect("unable to serialize value").len()
+}
+
+/// Estimated bytes for adding `num_keys` keys to the drop's key map and the contract's key to drop map
+fn estimate_key_storage(
+    num_keys: usize,
+    max_claims_per_key: u64,
+    allowance: Balance,
+    passwords_per_key: &Option<Vec<Option<Base64VecU8>>>,
+    passwords_per_use: &Option<Vec<Option<Vec<JsonPasswordForUse>>>>
+) -> u64 {
+    // Every key is assumed to be ed25519
+    let pk: PublicKey = "ed25519:11111111111111111111111111111111".parse().unwrap();
+    let pk_len = borsh_len(&pk);
+    let drop_prefix_len = borsh_len(&StorageKey::PksForDrop { account_id_hash: [0; 32] });
+    let contract_prefix_len = borsh_len(&StorageKey::DropIdForPk);
+
+    (0..num_keys).map(|i| {
+        let (pw_per_key, pw_per_use) = key_passwords(passwords_per_key, passwords_per_use, i, max_claims_per_key);
+        let key_usage = KeyUsage {
+            num_uses: max_claims_per_key,
+            last_used: 0,
+            last_used_block: 0,
+            last_used_epoch: 0,
+            claims_in_epoch: 0,
+            allowance,
+            pw_per_key,
+            pw_per_use,
+        };
+
+        unordered_map_entry_storage(drop_prefix_len, pk_len, borsh_len(&key_usage))
+            + unordered_map_entry_storage(contract_prefix_len, pk_len, borsh_len(&(0 as DropId)))
+    }).sum()
+}
+
+#[near_bindgen]
+impl DropZone {
+    /*
+        Estimate the deposit `create_drop` would take from the funder's balance given the same inputs. Nothing is written
+        to storage so the contract storage is estimated from the size of the records. FT registration storage is only
+        included if the `storage_balance_bounds().min` of each FT contract is passed in the same order as `ft_data`.
+    */
+    pub fn estimate_create_drop_cost(
+        &self,
+        funder_id: AccountId,
+        num_keys: u64,
+        balance: U128,
+        ft_data: Option<Vec<FTDataConfig>>,
+        nft_data: Option<Vec<NFTDataConfig>>,
+        fc_data: Option<Vec<FCData>>,
+        drop_config: DropConfig,
+        passwords_per_key: Option<Vec<Option<Base64VecU8>>>,
+        passwords_per_use: Option<Vec<Option<Vec<JsonPasswordForUse>>>>,
+        ft_storage: Option<Vec<U128>>
+    ) -> JsonCostEstimate {
+        let ft_data = ft_data.unwrap_or_default();
+        let nft_data = nft_data.unwrap_or_default();
+        let fc_data = fc_data.unwrap_or_default();
+        let num_assets = ft_data.len() + nft_data.len() + fc_data.len();
+        let num_claims_per_key = drop_config.max_claims_per_key;
+        require!(num_claims_per_key > 0, "cannot have less than 1 claim per key");
+        assert_passwords_match_keys(&passwords_per_key, &passwords_per_use, num_keys as usize);
+        if let Some(storage) = &ft_storage {
+            require!(storage.len() == ft_data.len(), "ft_storage must have one entry per FT asset");
+        }
+
+        let gas_to_attach = gas_for_assets(num_assets, &fc_data);
+        let actual_allowance = self.calculate_base_allowance(gas_to_attach) * num_claims_per_key as u128;
+
+        // Storage for the keys
+        let mut storage = estimate_key_storage(num_keys as usize, num_claims_per_key, actual_allowance, &passwords_per_key, &passwords_per_use);
+
+        // Storage for adding the drop ID to the funder's set of drops
+        let hashed_prefix_len = borsh_len(&StorageKey::DropIdsForFunderInner { account_id_hash: [0; 32] });
+        if self.drop_ids_for_funder.get(&funder_id).is_none() {
+            let drop_set: UnorderedSet<DropId> = UnorderedSet::new(StorageKey::DropIdsForFunderInner {
+                account_id_hash: hash_account_id(&funder_id.to_string()),
+            });
+            storage += record_storage(borsh_len(&StorageKey::DropIdsForFunder) + borsh_len(&funder_id), borsh_len(&drop_set));
+        }
+        storage += unordered_set_entry_storage(hashed_prefix_len, borsh_len(&self.nonce));
+        storage += self.estimate_keys_for_funder_storage(&funder_id);
+
+        // Build the drop as create_drop would so its record can be measured. Collections don't write anything until they're used.
+        let mut drop = Drop {
+            funder_id: funder_id.clone(),
+            balance,
+            pks: UnorderedMap::new(StorageKey::PksForDrop { account_id_hash: hash_account_id(&format!("{}{}", self.nonce, funder_id)) }),
+            assets: Vec::with_capacity(num_assets),
+            drop_config: drop_config.clone(),
+            num_claims_registered: num_claims_per_key * num_keys,
+            required_gas_attached: gas_to_attach,
+            total_claims: 0,
+            claims_per_account: LookupMap::new(StorageKey::ClaimsPerAccountForDrop { account_id_hash: hash_account_id(&format!("claims-{}{}", self.nonce, funder_id)) }),
+            allowlist: LookupSet::new(StorageKey::AllowlistForDrop { account_id_hash: hash_account_id(&format!("allow-{}{}", self.nonce, funder_id)) }),
+            allowlist_len: 0,
+            denylist: LookupSet::new(StorageKey::DenylistForDrop { account_id_hash: hash_account_id(&format!("deny-{}{}", self.nonce, funder_id)) }),
+            new_account_suffix: None,
+            paused: false,
+            pending_refunds: 0,
+        };
+        for FTDataConfig{ft_sender, ft_contract, ft_balance, amount_tiers} in ft_data {
+            drop.assets.push(DropAsset::FT(FTData {
+                ft_contract,
+                ft_sender,
+                ft_balance,
+                ft_storage: U128(u128::MAX),
+                num_claims_registered: 0,
+                amount_tiers,
+            }));
+        }
+        // Storage for the longest token ID is estimated the same way as the keys. This is summed across all NFT assets.
+        let mut storage_per_longest = 0;
+        for (nft_index, NFTDataConfig{nft_sender, nft_contract, longest_token_id}) in nft_data.into_iter().enumerate() {
+            let storage_for_longest = unordered_set_entry_storage(hashed_prefix_len, borsh_len(&longest_token_id));
+            storage_per_longest += storage_for_longest;
+
+            drop.assets.push(DropAsset::NFT(NFTData {
+                nft_sender,
+                nft_contract,
+                longest_token_id,
+                storage_for_longest: Balance::from(storage_for_longest),
+                token_ids: UnorderedSet::new(StorageKey::TokenIdsForDrop {
+                    account_id_hash: hash_account_id(&format!("nft-{}{}-{}", self.nonce, funder_id, nft_index)),
+                }),
+                num_claims_registered: 0,
+            }));
+        }
+        for data in fc_data.clone() {
+            drop.assets.push(DropAsset::FC(data));
+        }
+        // Drops are stored with a 1 byte version tag
+        storage += record_storage(borsh_len(&StorageKey::VersionedDropsForId) + borsh_len(&self.nonce), 1 + borsh_len(&drop));
+
+        // Mirror the required deposit from create_drop
+        let per_claim = PerClaimCosts {
+            balance: balance.0,
+            access_key_storage: ACCESS_KEY_STORAGE,
+            nft_storage: storage_cost(storage_per_longest, env::storage_byte_cost()),
+            ft_storage: 0,
+            claimed_account_storage: claimed_account_storage_per_claim(&drop_config),
+        };
+        let keys_before = self.keys_for_funder.get(&funder_id).unwrap_or(0);
+        let (drop_fee, key_fees) = self.internal_fees_for(&funder_id, self.fee_multipliers.for_assets(&drop.assets), keys_before, num_keys);
+        let deposit = required_deposit(&KeyDepositInputs {
+            num_keys: num_keys as u128,
+            claims_per_key: num_claims_per_key,
+            drop_fee,
+            key_fees,
+            allowance_per_key: actual_allowance,
+            fc_deposits_per_key: fc_data.iter().map(|data| data.deposit_for_uses_left(num_claims_per_key, num_claims_per_key)).sum(),
+            storage: storage_cost(storage, env::storage_byte_cost()),
+            per_claim,
+        });
+
+        // The FT storage is charged once the FT contracts have been queried
+        let ft_storage_per_claim: Balance = ft_storage.as_ref().map(|storage| storage.iter().map(|s| s.0).sum()).unwrap_or(0);
+        let resolved_ft_storage = ft_storage_deposit(ft_storage_per_claim, num_claims_per_key, num_keys as u128);
+        let ft_storage_included = ft_storage.is_some() || drop.assets.iter().all(|asset| !matches!(asset, DropAsset::FT(_)));
+
+        itemise(deposit, drop_fee, self.fee_credits.get(&funder_id).unwrap_or(0), per_claim, num_claims_per_key as u128 * num_keys as u128, resolved_ft_storage, ft_storage_included)
+    }
+
+    /// Estimate the deposit `add_to_drop` would take from the funder's balance for adding `num_keys` keys to an existing drop
+    pub fn estimate_add_to_drop_cost(
+        &self,
+        drop_id: DropId,
+        num_keys: u64,
+        passwords_per_key: Option<Vec<Option<Base64VecU8>>>,
+        passwords_per_use: Option<Vec<Option<Vec<JsonPasswordForUse>>>>
+    ) -> JsonCostEstimate {
+        let drop = self.drop_for_id.get(&drop_id).expect("no drop found for ID");
+        assert_passwords_match_keys(&passwords_per_key, &passwords_per_use, num_keys as usize);
+
+        let num_claims_per_key = drop.drop_config.max_claims_per_key;
+        let actual_allowance = self.calculate_base_allowance(drop.required_gas_attached) * num_claims_per_key as u128;
+
+        // The FT storage is recorded once the FT contracts have been queried after the drop is created
+        require!(
+            drop.assets.iter().all(|asset| !matches!(asset, DropAsset::FT(data) if data.ft_storage.0 == u128::MAX)),
+            "FT storage for the drop hasn't been resolved yet"
+        );
+        let per_claim = per_claim_costs(&drop.assets, &drop.drop_config, drop.balance.0);
+
+        let storage = estimate_key_storage(num_keys as usize, num_claims_per_key, actual_allowance, &passwords_per_key, &passwords_per_use)
+            + self.estimate_keys_for_funder_storage(&drop.funder_id);
+        let keys_before = self.keys_for_funder.get(&drop.funder_id).unwrap_or(0);
+        let (_, key_fees) = self.internal_fees_for(&drop.funder_id, self.fee_multipliers.for_assets(&drop.assets), keys_before, num_keys);
+        let deposit = required_deposit(&KeyDepositInputs {
+            num_keys: num_keys as u128,
+            claims_per_key: num_claims_per_key,
+            drop_fee: 0,
+            key_fees,
+            allowance_per_key: actual_allowance,
+            fc_deposits_per_key: fc_deposits_for_uses_left(&drop.assets, num_claims_per_key, num_claims_per_key),
+            storage: storage_cost(storage, env::storage_byte_cost()),
+            per_claim,
+        });
+
+        itemise(deposit, 0, self.fee_credits.get(&drop.funder_id).unwrap_or(0), per_claim, num_claims_per_key as u128 * num_keys as u128, 0, true)
+    }
+}
+
+impl DropZone {
+    /// Estimated bytes for recording how many keys a funder has added if they haven't added any yet
+    fn estimate_keys_for_funder_storage(&self, funder_id: &AccountId) -> u64 {
+        if self.keys_for_funder.contains_key(funder_id) {
+            return 0;
+        }
+        record_storage(borsh_len(&StorageKey::KeysForFunder) + borsh_len(funder_id), borsh_len(&0u64))
+    }
+}
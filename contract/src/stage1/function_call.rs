@@ -1,63 +1,268 @@
 use near_sdk::GasWeight;
+use near_sdk::serde_json::{self, Map, Value};
 
 use crate::*;
 
-/// Keep track of nft data 
+/// Keep track of a single method to call as part of a function call asset
 #[derive(PanicOnDefault, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
-pub struct FCData {
+pub struct MethodData {
     // Contract that will be called
     pub receiver: AccountId,
     // Method to call on receiver contract
     pub method: String,
-    // Arguments to pass in (stringified JSON)
+    // Arguments to pass in (stringified JSON). Must be a JSON object (or empty) if any fields are injected at claim time.
     pub args: String,
     // Amount of yoctoNEAR to attach along with the call
     pub deposit: U128,
-    // Should the refund that normally goes to the funder be attached alongside the deposit?
+    // Minimum GAS to attach to the call. Defaults to 0.
+    pub min_gas: Option<Gas>,
+    // Share of the unspent GAS that is attached on top of the minimum. Defaults to 1.
+    pub gas_weight: Option<u64>,
+}
+
+/// Types of values that a claimer can pass in for a user-overridable field
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum UserArgType {
+    String,
+    Number,
+    Bool,
+    Object,
+    Array,
+}
+
+/// A field in the args that the claimer is allowed to set when claiming
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UserArgField {
+    // Name of the field in the args
+    pub field: String,
+    // Type that the value must be
+    pub arg_type: UserArgType,
+    // Maximum length of the value in bytes. Strings are measured by their contents, everything else by its serialized JSON.
+    pub max_len: Option<u64>,
+}
+
+impl UserArgField {
+    /// Is the value passed in by the claimer within the funder's limits for this field?
+    pub fn allows(&self, value: &Value) -> bool {
+        let (type_matches, len) = match value {
+            Value::String(string) => (self.arg_type == UserArgType::String, string.len()),
+            Value::Number(_) => (self.arg_type == UserArgType::Number, value.to_string().len()),
+            Value::Bool(_) => (self.arg_type == UserArgType::Bool, value.to_string().len()),
+            Value::Object(_) => (self.arg_type == UserArgType::Object, value.to_string().len()),
+            Value::Array(_) => (self.arg_type == UserArgType::Array, value.to_string().len()),
+            Value::Null => (false, 0)
+        };
+
+        type_matches && self.max_len.map(|max_len| len as u64 <= max_len).unwrap_or(true)
+    }
+}
+
+/// Keep track of function call data 
+#[derive(PanicOnDefault, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FCData {
+    // Methods to call in order when the key is claimed
+    pub methods: Vec<MethodData>,
+    // Optional schedule of methods to call for each use of a key (index 0 being the first use). A `None` entry skips the function call for that use.
+    // If specified, there must be one entry per claim for each key and it takes precedence over `methods`.
+    pub methods_per_use: Option<Vec<Option<Vec<MethodData>>>>,
+    // Should each method be called only once the previous one has finished (`.then`)? Defaults to calling every method in parallel.
+    pub chain_methods: Option<bool>,
+    // Should the refund that normally goes to the funder be attached alongside the deposit of the first method?
     pub refund_to_deposit: Option<bool>,
-    // Specifies what field the claiming account should go in when calling the function
+    // Specifies what field the claiming account should go in when calling the functions
     pub claimed_account_field: Option<String>,
-    // How much GAS should be attached to the function call if it's a straight execute. Cannot be greater than ATTACHED_GAS_FROM_WALLET - GAS_OFFSET_IF_FC_EXECUTE (90 TGas).
+    // Specifies what field the drop ID should go in when calling the functions
+    pub drop_id_field: Option<String>,
+    // Specifies what field the public key used to claim should go in when calling the functions
+    pub key_field: Option<String>,
+    // Specifies what field the funder's account ID should go in when calling the functions
+    pub funder_id_field: Option<String>,
+    // Specifies what field the index of the key use being claimed (starting at 0) should go in when calling the functions
+    pub key_use_field: Option<String>,
+    // Specifies what field the block timestamp should go in when calling the functions
+    pub timestamp_field: Option<String>,
+    // Fields that the claimer is allowed to set in the args by passing `fc_args` when claiming. Injected fields always take precedence.
+    pub user_args: Option<Vec<UserArgField>>,
+    // How much GAS should be attached to the function calls if it's a straight execute. Cannot be greater than ATTACHED_GAS_FROM_WALLET - GAS_OFFSET_IF_FC_EXECUTE (90 TGas).
     // This makes it so the keys can only call `claim`
     pub gas_if_straight_execute: Option<Gas>
 }
 
+impl FCData {
+    /// Total yoctoNEAR attached across every method
+    pub fn total_deposit(&self) -> Balance {
+        self.methods.iter().map(|method| method.deposit.0).sum()
+    }
+
+    /// Total minimum GAS attached across every method
+    pub fn total_min_gas(&self) -> Gas {
+        Gas(self.methods.iter().map(|method| method.min_gas.unwrap_or(Gas(0)).0).sum())
+    }
+
+    /// Methods to call for a given use of a key (0 being the first use). None if the function call is skipped for that use.
+    pub fn methods_for_use(&self, use_index: u64) -> Option<Vec<MethodData>> {
+        match &self.methods_per_use {
+            Some(schedule) => schedule.get(use_index as usize).cloned().flatten(),
+            None => Some(self.methods.clone())
+        }
+    }
+
+    /// Total yoctoNEAR attached for the last `num_uses` uses of a key that has `max_uses` claims in total
+    pub fn deposit_for_uses_left(&self, max_uses: u64, num_uses: u64) -> Balance {
+        (max_uses - num_uses..max_uses)
+            .filter_map(|use_index| self.methods_for_use(use_index))
+            .map(|methods| methods.iter().map(|method| method.deposit.0).sum::<Balance>())
+            .sum()
+    }
+
+    /// Does this function call inject any runtime values into the args of its methods?
+    pub fn injects_fields(&self) -> bool {
+        self.claimed_account_field.is_some()
+            || self.drop_id_field.is_some()
+            || self.key_field.is_some()
+            || self.funder_id_field.is_some()
+            || self.key_use_field.is_some()
+            || self.timestamp_field.is_some()
+            || self.user_args.is_some()
+    }
+
+    /// Get the fields from the claimer's args that this function call allows to be set. Fields that aren't allowed are ignored.
+    /// Returns None if the claimer's args aren't a JSON object or a value is outside the limits set by the funder.
+    pub fn allowed_user_args(&self, fc_args: &Option<String>) -> Option<Map<String, Value>> {
+        let mut allowed_args = Map::new();
+        let (allowed_fields, fc_args) = match (&self.user_args, fc_args) {
+            (Some(allowed_fields), Some(fc_args)) => (allowed_fields, fc_args),
+            _ => return Some(allowed_args)
+        };
+
+        let user_args: Map<String, Value> = match serde_json::from_str(fc_args) {
+            Ok(user_args) => user_args,
+            Err(_) => {
+                debug_log!("User args are not a valid JSON object");
+                return None;
+            }
+        };
+
+        for allowed_field in allowed_fields {
+            if let Some(value) = user_args.get(&allowed_field.field) {
+                if !allowed_field.allows(value) {
+                    debug_log!("User value for field {} is outside the limits set by the funder", allowed_field.field);
+                    return None;
+                }
+                allowed_args.insert(allowed_field.field.clone(), value.clone());
+            }
+        }
+
+        Some(allowed_args)
+    }
+
+    /// Ensure the args of every method (including the per use schedule) are valid JSON. If any fields are injected, the args must be a JSON object or empty.
+    pub fn assert_valid_args(&self) {
+        let scheduled_methods = self.methods_per_use.iter().flatten().flatten().flatten();
+        for method_data in self.methods.iter().chain(scheduled_methods) {
+            if method_data.args.is_empty() {
+                continue;
+            }
+
+            let args: Value = serde_json::from_str(&method_data.args).unwrap_or_else(|_| env::panic_str(&format!("args for method {} are not valid JSON", method_data.method)));
+            require!(!self.injects_fields() || args.is_object(), &format!("args for method {} must be a JSON object to inject fields", method_data.method));
+        }
+    }
+
+    /// Get the function call data to execute for a given use of a key. The methods are empty if the function call is skipped for that use.
+    pub fn for_use(mut self, use_index: u64) -> Self {
+        if let Some(schedule) = self.methods_per_use.take() {
+            self.methods = schedule.into_iter().nth(use_index as usize).flatten().unwrap_or_default();
+        }
+        self
+    }
+}
+
 #[near_bindgen]
 impl DropZone {
-    // Internal method for transfer NFTs.
+    // Internal method for executing the function calls.
     pub(crate) fn internal_fc_execute(
         &mut self,
         fc_data: FCData,
         amount_to_refund: u128,
         account_id: AccountId,
+        funder_id: AccountId,
+        drop_id: DropId,
+        use_index: u64,
+        user_args: Map<String, Value>
     ) {
         /*
             Function Calls
         */
-        let mut final_args = fc_data.args.clone();
+        let should_refund_to_deposit = fc_data.refund_to_deposit.unwrap_or(false);
+        debug_log!(
+            "Attaching Total: {:?} Deposit: {:?} Should Refund?: {:?} Amount To Refund: {:?} Num methods: {:?} Chained?: {:?}", 
+            yocto_to_near(fc_data.total_deposit() + if should_refund_to_deposit {amount_to_refund} else {0}), 
+            yocto_to_near(fc_data.total_deposit()), should_refund_to_deposit, yocto_to_near(amount_to_refund), 
+            fc_data.methods.len(),
+            fc_data.chain_methods.unwrap_or(false)
+        );
+
+        // Runtime values to add to the args of every method in the fields specified by the funder.
+        // The signer is preserved across callbacks so this is the public key that was used to claim.
+        let injected_fields: Vec<(&String, Value)> = vec![
+            (&fc_data.claimed_account_field, Value::String(account_id.to_string())),
+            (&fc_data.drop_id_field, Value::String(drop_id.to_string())),
+            (&fc_data.key_field, Value::String(String::from(&env::signer_account_pk()))),
+            (&fc_data.funder_id_field, Value::String(funder_id.to_string())),
+            (&fc_data.key_use_field, Value::from(use_index)),
+            (&fc_data.timestamp_field, Value::String(env::block_timestamp().to_string())),
+        ].into_iter().filter_map(|(field, value)| field.as_ref().map(|field| (field, value))).collect();
+
+        // Keep track of the last promise so the methods can be chained if specified
+        let mut last_promise: Option<Promise> = None;
+        for (index, method_data) in fc_data.methods.into_iter().enumerate() {
+            let mut final_args = method_data.args.clone();
+
+            // Rebuild the args with the user's and injected fields. The args were checked to be a JSON object (or empty) when the drop was created.
+            if !injected_fields.is_empty() || !user_args.is_empty() {
+                let mut args: Map<String, Value> = if final_args.is_empty() {
+                    Map::new()
+                } else {
+                    serde_json::from_str(&final_args).expect("args must be a JSON object to inject fields")
+                };
+
+                // User fields can override the funder's args but never the injected runtime values
+                for (field, value) in &user_args {
+                    debug_log!("Setting user value {} for field: {:?} in args", value, field);
+                    args.insert(field.clone(), value.clone());
+                }
 
-        // Add the account ID that claimed the linkdrop as part of the args to the function call in the key specified by the user
-        if let Some(account_field) = fc_data.claimed_account_field {
-            final_args.insert_str(final_args.len()-1, &format!(",\"{}\":\"{}\"", account_field, account_id));
-            env::log_str(&format!("Adding claimed account ID to specified field: {:?} in args: {:?}", account_field, fc_data.args));
+                for (field, value) in &injected_fields {
+                    debug_log!("Adding {} to specified field: {:?} in args", value, field);
+                    args.insert(field.to_string(), value.clone());
+                }
+
+                final_args = serde_json::to_string(&args).expect("unable to serialize args");
+            }
+
+            // The claim is successful so attach the amount to refund to the first deposit instead of refunding the funder.
+            let deposit = method_data.deposit.0 + if should_refund_to_deposit && index == 0 {amount_to_refund} else {0};
+            debug_log!("Calling {} on {} with deposit {:?} and args: {:?}", method_data.method, method_data.receiver, yocto_to_near(deposit), final_args);
+
+            // Call function with the min GAS and deposit. unspent GAS will be added on top according to the weight
+            let promise = Promise::new(method_data.receiver).function_call_weight(
+                method_data.method, 
+                final_args.as_bytes().to_vec(), 
+                deposit, 
+                method_data.min_gas.unwrap_or(Gas(0)),
+                GasWeight(method_data.gas_weight.unwrap_or(1))
+            );
+
+            // Either chain the promise after the previous method or fire it in parallel
+            last_promise = Some(match last_promise {
+                Some(previous) if fc_data.chain_methods.unwrap_or(false) => previous.then(promise),
+                _ => promise
+            });
         }
-    
-        env::log_str(&format!(
-            "Attaching Total: {:?} Deposit: {:?} Should Refund?: {:?} Amount To Refund: {:?} With args: {:?}", 
-            yocto_to_near(fc_data.deposit.0 + if fc_data.refund_to_deposit.unwrap_or(false) {amount_to_refund} else {0}), 
-            yocto_to_near(fc_data.deposit.0), fc_data.refund_to_deposit.unwrap_or(false), yocto_to_near(amount_to_refund), 
-            final_args
-        ));
-
-        // Call function with the min GAS and deposit. all unspent GAS will be added on top
-        Promise::new(fc_data.receiver).function_call_weight(
-            fc_data.method, 
-            final_args.as_bytes().to_vec(), 
-            // The claim is successful so attach the amount to refund to the deposit instead of refunding the funder.
-            fc_data.deposit.0 + if fc_data.refund_to_deposit.unwrap_or(false) {amount_to_refund} else {0}, 
-            fc_data.gas_if_straight_execute.unwrap_or(Gas(0)),
-            GasWeight(1)
-        );
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,7 @@
+mod account_lists;
 mod delete;
 mod drops;
+pub mod estimates;
 pub mod function_call;
 
 pub use drops::*;
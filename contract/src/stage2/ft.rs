@@ -9,6 +9,10 @@ pub struct FTData {
     pub ft_sender: AccountId,
     pub ft_balance: U128,
     pub ft_storage: U128,
+    // How many claims have FTs registered for this asset
+    pub num_claims_registered: u64,
+    // Weighted amounts sent instead of the FT balance. Each tier counts down its claims as they're made.
+    pub amount_tiers: Option<Vec<FTAmountTier>>,
 }
 
 /// FT Data to be passed in by the user
@@ -18,51 +22,110 @@ pub struct FTDataConfig {
     pub ft_contract: AccountId,
     pub ft_sender: AccountId,
     pub ft_balance: U128,
+    // Weighted amounts to send instead of the FT balance. The claims across the tiers must match the claims in the drop.
+    pub amount_tiers: Option<Vec<FTAmountTier>>,
 }
 
-// Returned from the storage balance bounds cross contract call on the FT contract
+/// A tier of a weighted FT amount table (i.e 70 claims of 10, 25 claims of 50 and 5 claims of 500)
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FTAmountTier {
+    // Fungible tokens sent with each claim in this tier
+    pub amount: U128,
+    // How many claims are left in this tier
+    pub num_claims: u64,
+}
+
+impl FTData {
+    /// Claims left across the amount tiers and the tokens needed to register all of them. None if there are no tiers.
+    pub(crate) fn amount_tiers_total(&self) -> Option<(u64, Balance)> {
+        self.amount_tiers.as_ref().map(|tiers| tiers.iter().fold((0, 0), |(claims, total), tier| {
+            (claims + tier.num_claims, total + tier.amount.0 * tier.num_claims as u128)
+        }))
+    }
+
+    /*
+        Get the amount to send for a claim. For assets with amount tiers, a tier is picked at random (weighted by
+        the claims each tier has left) and one of its claims is taken. Otherwise this is always the FT balance.
+    */
+    pub(crate) fn take_claim_amount(&mut self, random: u64) -> U128 {
+        let tiers = match self.amount_tiers.as_mut() {
+            Some(tiers) => tiers,
+            None => return self.ft_balance
+        };
+
+        let claims_left: u64 = tiers.iter().map(|tier| tier.num_claims).sum();
+        require!(claims_left > 0, "no claims left in the amount tiers");
+
+        let mut pick = random % claims_left;
+        for tier in tiers.iter_mut() {
+            if pick < tier.num_claims {
+                tier.num_claims -= 1;
+                return tier.amount
+            }
+            pick -= tier.num_claims;
+        }
+
+        env::panic_str("no claims left in the amount tiers")
+    }
+}
+
+/// Ensure an FT amount table is usable for a drop with the given number of claims
+pub(crate) fn assert_valid_amount_tiers(amount_tiers: &Option<Vec<FTAmountTier>>, num_claims: u64) {
+    if let Some(tiers) = amount_tiers {
+        require!(!tiers.is_empty(), "amount tiers can't be empty");
+        require!(tiers.iter().all(|tier| tier.amount.0 > 0 && tier.num_claims > 0), "every amount tier must have an amount and claims");
+        require!(tiers.iter().map(|tier| tier.num_claims).sum::<u64>() == num_claims, "amount tiers must cover every claim in the drop");
+    }
+}
+
+/// Fees for a drop with FT assets. These are only collected once the storage check succeeds.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DropCreationFees {
+    // Fees paid out of the funder's $NEAR balance
+    pub fees: U128,
+    // Fees paid out of the funder's fee credit. This is given back if the drop can't be created.
+    pub fee_credit: U128,
+    // Credited a share of the fees once they're collected
+    pub referrer: Option<AccountId>,
+}
+
+// Returned from the storage balance bounds cross contract call on the FT contract. Only the minimum is read.
 #[derive(Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct StorageBalanceBounds {
     pub min: U128,
-    pub max: Option<U128>,
 }
 
 #[near_bindgen]
 impl DropZone {
-    /// Allows users to attach fungible tokens to the Linkdrops. Must have storage recorded by this point. You can only attach one set of FTs or NFT at a time.
+    /*
+        Allows users to attach fungible tokens to the Linkdrops, create a drop, top up their token balance or pay for
+        fees. The msg is either a drop ID or a `TransferMsg`. Any tokens that can't be used are refunded to the sender.
+    */
     pub fn ft_on_transfer(
         &mut self,
         sender_id: AccountId,
         amount: U128,
-        msg: U128,
+        msg: String,
     ) -> PromiseOrValue<U128> {
-        let contract_id = env::predecessor_account_id();
-
-        let mut drop = self.drop_for_id.get(&msg.0).expect("No drop found for ID");
-        if let DropType::FT(ft_data) = &drop.drop_type {
-            require!(amount.0 % ft_data.ft_balance.0 == 0, "amount must be a multiple of the drop balance");
-            require!(ft_data.ft_contract == contract_id && ft_data.ft_sender == sender_id, "FT data must match what was sent");
-            
-            // Get the number of claims to register with the amount that is sent.
-            let claims_to_register = (amount.0 / ft_data.ft_balance.0) as u64;
-            drop.num_claims_registered += claims_to_register;
-            env::log_str(&format!("New claims registered {}", claims_to_register));
-    
-            // Ensure that the keys to register can't exceed the number of keys in the drop.
-            if drop.num_claims_registered > drop.pks.len() * drop.drop_config.max_claims_per_key {
-                env::log_str("Too many FTs sent. Contract is keeping the rest.");
-                drop.num_claims_registered = drop.pks.len() * drop.drop_config.max_claims_per_key;
-            }
-    
-            // Insert the drop with the updated data
-            self.drop_for_id.insert(&msg.0, &drop);
-
-            // Everything went well and we don't need to return any tokens (if they over-sent, we keep it)
-            PromiseOrValue::Value(U128(0))
-        } else {
-            env::panic_str("drop type isn't FT");
-        }
+        // Panicking returns the tokens to the sender
+        self.assert_not_paused(PauseCategory::AssetIntake);
+        let token_id = env::predecessor_account_id();
+
+        let unused = match TransferMsg::parse(&msg) {
+            Some(TransferIntent::RegisterClaims { drop_id }) => self.internal_register_fts(token_id, sender_id, amount.0, drop_id),
+            Some(TransferIntent::CreateDrop(create)) => return self.internal_create_drop_from_transfer(sender_id, amount, create),
+            Some(TransferIntent::TopUpBalance { account_id }) => self.internal_add_to_token_balance(account_id.unwrap_or(sender_id), token_id, amount.0),
+            Some(TransferIntent::PayFees { account_id }) => return self.internal_pay_fees_with_token(account_id.unwrap_or(sender_id), amount),
+            None => {
+                debug_log!("Unknown msg {}. Refunding the tokens", msg);
+                amount.0
+            },
+        };
+
+        PromiseOrValue::Value(U128(unused))
     }
 
     #[private]
@@ -76,12 +139,12 @@ impl DropZone {
         let mut used_gas = env::used_gas();
         let mut prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("Beginning of resolve transfer used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Beginning of resolve transfer used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
         let transfer_succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
         
         used_gas = env::used_gas();
         prepaid_gas = env::prepaid_gas();
-        env::log_str(&format!("Before refunding token sender in resolve transfer: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Before refunding token sender in resolve transfer: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
 
         if transfer_succeeded {
             return true
@@ -118,170 +181,216 @@ impl DropZone {
     }
 
     #[private]
-    /// Self callback checks if fungible tokens were successfully refunded. If yes, set keys registered to 0.
+    /*
+        Self callback checks if fungible tokens were successfully refunded. If yes, the keys registered stay decremented.
+        If not, they're added back to the asset. If the drop has been removed in the meantime, there's nothing to add
+        them back to so the tokens are sent to their sender again.
+    */
     pub fn ft_resolve_refund(
         &mut self, 
         drop_id: DropId,
-        num_to_refund: u64
+        num_to_refund: u64,
+        asset_index: u64,
+        ft_contract: AccountId,
+        ft_sender: AccountId,
+        amount: U128
     ) -> bool {
         let transfer_succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
     
+        emit_event(EventLogVariant::AssetRefund(AssetRefundLog {
+            drop_id: U128(drop_id),
+            asset_index,
+            num_refunded: num_to_refund,
+            success: transfer_succeeded,
+        }));
+
+        let mut drop = match self.drop_for_id.get(&drop_id) {
+            Some(drop) => drop,
+            None if transfer_succeeded => return true,
+            None => {
+                debug_log!("Drop {} removed before the refund resolved. Sending {} FTs to {} again", drop_id, amount.0, ft_sender);
+                ext_ft_contract::ext(ft_contract)
+                    .with_attached_deposit(1)
+                    .with_static_gas(MIN_GAS_FOR_FT_TRANSFER)
+                    .ft_transfer(ft_sender, amount, None);
+                return false
+            }
+        };
+        drop.pending_refunds = drop.pending_refunds.saturating_sub(1);
+
         // Everything went well so we return true since the keys registered have already been decremented
         if transfer_succeeded {
-            env::log_str(&format!("Successfully refunded FTs for drop ID {}. {} keys unregistered. Returning true.", drop_id, num_to_refund));
+            self.drop_for_id.insert(&drop_id, &drop);
+            debug_log!("Successfully refunded FTs for drop ID {}. {} keys unregistered. Returning true.", drop_id, num_to_refund);
             return true
         }
 
-        // Transfer failed so we need to increment the claims registered and return false
-        let mut drop = self.drop_for_id.get(&drop_id).expect("no drop for ID");
-        drop.num_claims_registered += num_to_refund;
+        // Transfer failed so we need to increment the claims registered for the asset and return false
+        if let Some(DropAsset::FT(data)) = drop.assets.get_mut(asset_index as usize) {
+            data.num_claims_registered += num_to_refund;
+        }
         self.drop_for_id.insert(&drop_id, &drop);
 
-        env::log_str(&format!("Unsuccessful refund for drop ID {}. {} keys added back as registered. Returning false.", drop_id, num_to_refund));
+        debug_log!("Unsuccessful refund for drop ID {}. {} keys added back as registered. Returning false.", drop_id, num_to_refund);
         false
     }
 
     #[payable]
     #[private]
-    /// self callback gets the storage balance bounds and inserts that into account data for each public key passed in
+    /// self callback gets the storage balance bounds for every FT asset and inserts that into the drop's FT data.
+    /// The promise results are in the same order as the FT assets in the drop.
     pub fn resolve_storage_check(
         &mut self,
         public_keys: Vec<PublicKey>,
         drop_id: DropId,
         required_deposit: u128,
+        fees: DropCreationFees,
     ) -> bool {
-        // Check promise result.
-        let result = promise_result_as_success();
         let pub_keys_len = public_keys.len() as u128;
 
-        // If things went wrong, we need to delete the data and refund the user.
-        if result.is_none() {
-            // Refund the funder any excess $NEAR
-            env::log_str(&format!("Unsuccessful query to get storage. Refunding funder's balance: {}", yocto_to_near(required_deposit)));
-            // Remove the drop
-            let mut drop = self.drop_for_id.remove(&drop_id).expect("drop not found");
-            // Clear the map
-            drop.pks.clear();
-            let funder_id = drop.funder_id.clone();
-            
-            // Remove the drop ID from the funder's list
-            self.internal_remove_drop_for_funder(&drop.funder_id, &drop_id);
-            
-            // Loop through the keys and remove the public keys' mapping
-            for pk in public_keys {
-                self.drop_id_for_pk.remove(&pk.clone());
+        // Try to get the storage balance bounds from the result of each promise
+        let mut storage_mins = vec![];
+        for i in 0..env::promise_results_count() {
+            let min = match env::promise_result(i) {
+                PromiseResult::Successful(result) => near_sdk::serde_json::from_slice::<StorageBalanceBounds>(&result).ok().map(|bounds| bounds.min),
+                _ => None
+            };
+
+            // If things went wrong, we need to delete the data and refund the user.
+            if min.is_none() {
+                // Refund the funder any excess $NEAR
+                debug_log!("Unsuccessful query to get storage. Refunding funder's balance: {}", yocto_to_near(required_deposit));
+                self.internal_revert_drop_creation(drop_id, public_keys, required_deposit, fees.fee_credit.0);
+                return false;
             }
-            
-            // Refund the user's balance for the required deposit
-            let mut user_balance = self.user_balances.get(&funder_id).unwrap();
-            user_balance += required_deposit;
-            self.user_balances.insert(&funder_id, &user_balance);
 
+            storage_mins.push(min.unwrap());
+        }
+
+        let mut drop = self.drop_for_id.get(&drop_id).unwrap();
+        let funder_id = drop.funder_id.clone();
+
+        // Get the current user balance ad ensure that they have the extra $NEAR for covering the FT storage for every FT asset
+        let mut cur_user_balance = self.user_balances.get(&funder_id).unwrap();
+        let total_storage_min: u128 = storage_mins.iter().map(|min| min.0).sum();
+        let extra_storage_required = ft_storage_deposit(total_storage_min, drop.drop_config.max_claims_per_key, pub_keys_len);
+        
+        // Ensure the user's current balance can cover the extra storage required
+        if cur_user_balance < extra_storage_required {
+            debug_log!("Not enough balance to cover FT storage for each key and their claims. Refunding funder's balance: {}", yocto_to_near(required_deposit));
+            self.internal_revert_drop_creation(drop_id, public_keys, required_deposit, fees.fee_credit.0);
             return false;
         }
 
-        // Try to get the storage balance bounds from the result of the promise
-		if let Ok(StorageBalanceBounds{ min, max: _ }) = near_sdk::serde_json::from_slice::<StorageBalanceBounds>(&result.unwrap()) {
-            let mut drop = self.drop_for_id.get(&drop_id).unwrap();
-            let funder_id = drop.funder_id.clone();
-
-            // Get the current user balance ad ensure that they have the extra $NEAR for covering the FT storage
-            let mut cur_user_balance = self.user_balances.get(&funder_id).unwrap();
-            let extra_storage_required =  min.0 * drop.drop_config.max_claims_per_key as u128 * pub_keys_len;
-            
-            // Ensure the user's current balance can cover the extra storage required
-            if cur_user_balance < extra_storage_required {
-                env::log_str(&format!("Not enough balance to cover FT storage for each key and their claims. Refunding funder's balance: {}", yocto_to_near(required_deposit)));
-                // Remove the drop
-                self.drop_for_id.remove(&drop_id).unwrap();
-                // Clear the map
-                drop.pks.clear();
-                
-                // Remove the drop ID from the funder's list
-                self.internal_remove_drop_for_funder(&drop.funder_id, &drop_id);
-                
-                // Loop through the keys and remove the keys from the drop and remove the drop ID for the key
-                for pk in public_keys {
-                    self.drop_id_for_pk.remove(&pk.clone());
-                }
+        // Update each FT asset to include the storage and insert the drop back with the updated FT data
+        let ft_assets = drop.assets.iter_mut().filter_map(|asset| match asset {
+            DropAsset::FT(data) => Some(data),
+            _ => None
+        });
+        for (ft_data, min) in ft_assets.zip(storage_mins) {
+            ft_data.ft_storage = min;
+        }
 
-                // Refund the user's balance for the required deposit
-                cur_user_balance += required_deposit;
-                self.user_balances.insert(&funder_id, &cur_user_balance);
+        self.drop_for_id.insert(
+            &drop_id, 
+            &drop
+        );
 
-                return false;
-            }
+        // Decrement the user's balance by the extra required and insert back into the map
+        cur_user_balance -= extra_storage_required;
+        self.user_balances.insert(&funder_id, &cur_user_balance);
 
-            // Update the FT data to include the storage and insert the drop back with the updated FT data
-            if let DropType::FT(mut ft_data) = drop.drop_type {
-                ft_data.ft_storage = min;
-                drop.drop_type = DropType::FT(ft_data);
-    
-                self.drop_for_id.insert(
-                    &drop_id, 
-                    &drop
-                );
-    
-                // Decrement the user's balance by the extra required and insert back into the map
-                cur_user_balance -= extra_storage_required;
-                self.user_balances.insert(&funder_id, &cur_user_balance);
-    
-                // Create the keys for the contract
-                let promise = env::promise_batch_create(&env::current_account_id());
-            
-                // Decide what methods the access keys can call
-                let mut access_key_method_names = ACCESS_KEY_BOTH_METHOD_NAMES;
-                if drop.drop_config.only_call_claim.unwrap_or(false) {
-                    access_key_method_names = ACCESS_KEY_CLAIM_METHOD_NAME;
-                }
+        // The drop is created so the fees can be collected
+        self.internal_collect_fees(&funder_id, fees.fees.0, fees.referrer, drop_id);
 
-                // Dynamically calculate the access key allowance
-                let access_key_allowance = self.calculate_base_allowance(drop.required_gas_attached);
-
-                // Loop through each public key and create the access keys
-                for pk in public_keys.clone() {
-                    env::promise_batch_action_add_key_with_function_call(
-                        promise, 
-                        &pk, 
-                        0, 
-                        access_key_allowance, 
-                        &env::current_account_id(), 
-                        access_key_method_names
-                    );
-                }
+        // Create the keys for the contract
+        let promise = env::promise_batch_create(&env::current_account_id());
     
-                env::promise_return(promise);
-    
-                // Everything went well and we return true
-                return true
-            } else {
-                false
-            }
+        // Decide what methods the access keys can call
+        let mut access_key_method_names = ACCESS_KEY_BOTH_METHOD_NAMES;
+        if drop.drop_config.only_call_claim.unwrap_or(false) {
+            access_key_method_names = ACCESS_KEY_CLAIM_METHOD_NAME;
+        }
 
-        } else {
-            // Refund the funder any excess $NEAR
-            env::log_str(&format!("Unsuccessful query to get storage. Refunding funder's balance: {}", yocto_to_near(required_deposit)));
-            // Remove the drop
-            let mut drop = self.drop_for_id.remove(&drop_id).expect("drop not found");
-            // Clear the map
-            drop.pks.clear();
-            let funder_id = drop.funder_id.clone();
-            
-            // Remove the drop ID from the funder's list
-            self.internal_remove_drop_for_funder(&drop.funder_id, &drop_id);
-            
-            // Loop through the keys and remove the public keys' mapping
-            for pk in public_keys {
-                self.drop_id_for_pk.remove(&pk.clone());
-            }
-            
-            // Refund the user's balance for the required deposit
-            let mut user_balance = self.user_balances.get(&funder_id).unwrap();
-            user_balance += required_deposit;
-            self.user_balances.insert(&funder_id, &user_balance);
+        // Dynamically calculate the access key allowance
+        let access_key_allowance = key_allowance(drop.required_gas_attached, drop.drop_config.max_claims_per_key, self.yocto_per_gas);
+
+        // Loop through each public key and create the access keys
+        for pk in public_keys.clone() {
+            env::promise_batch_action_add_key_with_function_call(
+                promise, 
+                &pk, 
+                0, 
+                access_key_allowance, 
+                &env::current_account_id(), 
+                access_key_method_names
+            );
+        }
 
-            return false;
+        // Everything went well and we return true. The keys are added in a detached batch so callers that chain
+        // this check can return their own value.
+        true
+    }
+
+    #[private]
+    /*
+        Self callback for drops created from a fungible token transfer. Once the storage check resolves, claims are
+        registered with the tokens that were sent. Returns the amount the FT contract should refund, which is
+        everything if the drop couldn't be created.
+    */
+    pub fn resolve_transfer_drop_creation(
+        &mut self,
+        public_keys: Vec<PublicKey>,
+        drop_id: DropId,
+        required_deposit: u128,
+        fees: DropCreationFees,
+        amount: U128,
+    ) -> U128 {
+        if !self.resolve_storage_check(public_keys, drop_id, required_deposit, fees) {
+            return amount
         }
+
+        let drop = self.drop_for_id.get(&drop_id).expect("drop not found");
+        let (contract_id, sender_id) = match &drop.assets[0] {
+            DropAsset::FT(data) => (data.ft_contract.clone(), data.ft_sender.clone()),
+            _ => env::panic_str("drop created from a transfer must have an FT asset")
+        };
+
+        U128(self.internal_register_fts(contract_id, sender_id, amount.0, U128(drop_id)))
+    }
+
+    /*
+        Remove a drop whose creation couldn't be completed. The funder's balance is refunded the required deposit,
+        which is everything they paid in $NEAR, and any fee credit used for the drop is given back. The drop's keys no
+        longer count towards the funder's volume tiers.
+    */
+    pub(crate) fn internal_revert_drop_creation(
+        &mut self,
+        drop_id: DropId,
+        public_keys: Vec<PublicKey>,
+        required_deposit: u128,
+        fee_credit: Balance,
+    ) {
+        // Remove the drop
+        let mut drop = self.drop_for_id.remove(&drop_id).expect("drop not found");
+        // Clear the map
+        drop.pks.clear();
+        let funder_id = drop.funder_id.clone();
+        
+        // Remove the drop ID from the funder's list and stop counting its keys towards the funder's volume tiers
+        self.internal_remove_drop_for_funder(&drop.funder_id, &drop_id);
+        self.internal_unrecord_keys_for_funder(&funder_id, public_keys.len() as u64);
+        
+        // Loop through the keys and remove the public keys' mapping
+        for pk in public_keys {
+            self.drop_id_for_pk.remove(&pk.clone());
+        }
+        
+        // Refund the user's balance for the required deposit
+        let mut user_balance = self.user_balances.get(&funder_id).unwrap();
+        user_balance += required_deposit;
+        self.user_balances.insert(&funder_id, &user_balance);
+        self.internal_restore_fee_credit(&funder_id, fee_credit);
     }
 
     // Internal method for transfer FTs. Whether the claim was successful or not is passed in
@@ -369,3 +478,120 @@ impl DropZone {
         }
     }
 }
+
+impl DropZone {
+    /*
+        Create a drop whose only asset is the tokens that were sent. The sender is the funder and the $NEAR costs are
+        taken from their balance. Claims are registered once the FT contract has been queried for its storage and any
+        tokens left over are refunded. Transfers that can't register a single claim are refunded straight away.
+    */
+    pub(crate) fn internal_create_drop_from_transfer(&mut self, sender_id: AccountId, amount: U128, create: CreateDropMsg) -> PromiseOrValue<U128> {
+        if self.paused.is_paused(PauseCategory::Creation) || create.ft_balance.0 == 0 || amount.0 < create.ft_balance.0 {
+            debug_log!("Drop can't be created or the tokens can't cover a claim. Refunding the tokens");
+            return PromiseOrValue::Value(amount)
+        }
+        if !self.user_balances.contains_key(&sender_id) {
+            debug_log!("No user balance found for {}. Refunding the tokens", sender_id);
+            return PromiseOrValue::Value(amount)
+        }
+
+        let CreateDropMsg { public_keys, balance, ft_balance, drop_config, passwords_per_key, passwords_per_use, referrer } = create;
+        let ft_data = FTDataConfig { ft_contract: env::predecessor_account_id(), ft_sender: sender_id.clone(), ft_balance, amount_tiers: None };
+        let (drop_id, required_deposit, storage_checks) = self.internal_create_drop(
+            sender_id,
+            public_keys.clone(),
+            balance,
+            Some(vec![ft_data]),
+            None,
+            None,
+            drop_config,
+            passwords_per_key,
+            passwords_per_use,
+            referrer
+        );
+
+        let (storage_checks, fees) = storage_checks.expect("FT drops must query the storage");
+        storage_checks.then(
+            Self::ext(env::current_account_id())
+                // Resolve the promise with the min GAS. All unspent GAS will be added to this call.
+                .with_static_gas(MIN_GAS_FOR_RESOLVE_TRANSFER_DROP_CREATION)
+                .resolve_transfer_drop_creation(
+                    public_keys,
+                    drop_id,
+                    required_deposit,
+                    fees,
+                    amount
+                )
+        ).into()
+    }
+
+    /*
+        Register claims for the first FT asset in the drop that matches the contract and sender. Only whole claims
+        are registered and never more than the number of claims left for the drop. Returns the amount to refund.
+    */
+    pub(crate) fn internal_register_fts(&mut self, contract_id: AccountId, sender_id: AccountId, amount: Balance, drop_id: U128) -> Balance {
+        let mut drop = match self.drop_for_id.get(&drop_id.0) {
+            Some(drop) => drop,
+            None => {
+                debug_log!("No drop found for ID {}. Refunding the tokens", drop_id.0);
+                return amount
+            }
+        };
+        // The claims registered for an asset can't exceed the number of claims left for the drop.
+        let max_claims_registered = drop.num_claims_registered;
+
+        let ft_index = match drop.assets.iter().position(|asset| matches!(
+            asset,
+            DropAsset::FT(data) if data.ft_contract == contract_id && data.ft_sender == sender_id
+        )) {
+            Some(ft_index) => ft_index,
+            None => {
+                debug_log!("FT data doesn't match what was sent. Refunding the tokens");
+                return amount
+            }
+        };
+        let ft_data = match &mut drop.assets[ft_index] {
+            DropAsset::FT(data) => data,
+            _ => return amount
+        };
+
+        // Get the number of claims to register with the amount that is sent.
+        let claims_left = max_claims_registered.saturating_sub(ft_data.num_claims_registered);
+        let (claims_to_register, used) = match ft_data.amount_tiers_total() {
+            // Assets with amount tiers are registered all at once with the sum of the table
+            Some((tier_claims, tier_total)) => {
+                if ft_data.num_claims_registered > 0 || amount < tier_total || tier_claims > claims_left {
+                    debug_log!("Amount tiers need {} FTs for {} claims. Refunding the tokens", tier_total, tier_claims);
+                    return amount
+                }
+                (tier_claims, tier_total)
+            },
+            None => {
+                let claims_to_register = (amount.checked_div(ft_data.ft_balance.0).unwrap_or(0) as u64).min(claims_left);
+                // Anything past the claims registered is refunded
+                (claims_to_register, claims_to_register as u128 * ft_data.ft_balance.0)
+            }
+        };
+        if claims_to_register == 0 {
+            debug_log!("Not enough FTs sent for a claim or no claims left to register. Refunding the tokens");
+            return amount
+        }
+        ft_data.num_claims_registered += claims_to_register;
+        debug_log!("New claims registered {}", claims_to_register);
+
+        emit_event(EventLogVariant::AssetRegistration(AssetRegistrationLog {
+            drop_id,
+            asset_index: ft_index as u64,
+            contract_id,
+            sender_id,
+            num_claims_registered: ft_data.num_claims_registered,
+            amount: Some(U128(used)),
+            token_id: None,
+        }));
+
+        // Insert the drop with the updated data
+        self.drop_for_id.insert(&drop_id.0, &drop);
+
+        amount - used
+    }
+}
@@ -1,5 +1,7 @@
 pub mod ft;
 pub mod nft;
+pub mod transfer_msg;
 
 pub use ft::*;
-pub use nft::*;
\ No newline at end of file
+pub use nft::*;
+pub use transfer_msg::*;
@@ -8,6 +8,8 @@ pub struct NFTData {
     pub longest_token_id: String,
     pub storage_for_longest: Balance,
     pub token_ids: UnorderedSet<String>,
+    // How many claims have an NFT registered for this asset
+    pub num_claims_registered: u64,
 }
 
 /// Keep track of nft data. This is passed in by the user
@@ -21,88 +23,94 @@ pub struct NFTDataConfig {
 
 #[near_bindgen]
 impl DropZone {
+    /*
+        Allows users to attach NFTs to the Linkdrops. The msg is either a drop ID or a `TransferMsg` that registers
+        claims. The token is returned to the sender if it can't be registered.
+    */
     pub fn nft_on_transfer(
         &mut self,
         token_id: String,
         sender_id: AccountId,
-        msg: U128,
+        msg: String,
     ) -> PromiseOrValue<bool> {
-        let contract_id = env::predecessor_account_id();
+        // Panicking returns the token to the sender
+        self.assert_not_paused(PauseCategory::AssetIntake);
 
-        let mut drop = self.drop_for_id.get(&msg.0).expect("No drop found for ID");
-        if let DropType::NFT(mut nft_data) = drop.drop_type {
-            let mut token_ids = nft_data.token_ids;
+        let registered = match TransferMsg::parse(&msg) {
+            Some(TransferIntent::RegisterClaims { drop_id }) => self.internal_register_nft(sender_id, token_id, drop_id),
+            Some(_) => {
+                debug_log!("NFTs can only register claims. Returning the token");
+                false
+            },
+            None => {
+                debug_log!("Unknown msg {}. Returning the token", msg);
+                false
+            },
+        };
 
-            require!(nft_data.nft_sender == sender_id && nft_data.nft_contract == contract_id, "NFT data must match what was sent");
-            require!(token_id.len() <= nft_data.longest_token_id.len(), "token ID must be less than largest token specified");
-        
-            require!(token_ids.insert(&token_id) == true, "token ID already registered");
-    
-            // Re-insert the token IDs into the NFT Data struct 
-            nft_data.token_ids = token_ids;
-    
-            // Increment the claims registered
-            drop.num_claims_registered += 1;
-            env::log_str(&format!("drop.num_claims_registered {}", drop.num_claims_registered));
-    
-            // Ensure that the keys to register can't exceed the number of keys in the drop.
-            if drop.num_claims_registered > drop.pks.len() * drop.drop_config.max_claims_per_key {
-                env::log_str("Too many NFTs sent. Contract is keeping the rest.");
-                drop.num_claims_registered = drop.pks.len() * drop.drop_config.max_claims_per_key;
-            }
-    
-            // Add the nft data back with the updated set
-            drop.drop_type = DropType::NFT(nft_data);
-    
-            // Insert the drop with the updated data
-            self.drop_for_id.insert(&msg.0, &drop);
-    
-            // Everything went well and we don't need to return the token.
-            PromiseOrValue::Value(false);
-        } {
-            env::panic_str("drop type isn't NFT");
-        }  
+        PromiseOrValue::Value(!registered)
     }
 
     #[private]
-    /// self callback checks if NFT was successfully transferred to the new account. If yes, do nothing. If no, refund original sender
+    /*
+        self callback checks if the NFTs were successfully refunded. If yes, their token IDs are removed from the asset.
+        If no, the claims are added back to the asset. If the drop has been removed in the meantime, there's nothing
+        to add them back to so the NFTs are sent to their sender again.
+    */
     pub fn nft_resolve_refund(
         &mut self, 
         drop_id: U128,
         token_ids: Vec<String>, 
+        asset_index: u64,
+        nft_contract: AccountId,
+        nft_sender: AccountId
     ) -> bool {
         let used_gas = env::used_gas();
         let prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("Beginning of resolve refund used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Beginning of resolve refund used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
         let transfer_succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
-        
-        // If not successful, the length of the token IDs needs to be added back to the drop.
-        if !transfer_succeeded {
-            let mut drop = self.drop_for_id.get(&drop_id.0).unwrap();
-            drop.num_claims_registered += token_ids.len() as u64;
-            self.drop_for_id.insert(&drop_id.0, &drop);
-
-            env::log_str(&format!("Transfer failed. Adding {} back to drop's keys registered", token_ids.len() as u64));
 
-            return false
-        }
-
-        // Loop through and remove each token ID from the drop's NFT data token IDs
-        let mut drop = self.drop_for_id.get(&drop_id.0).unwrap();
-        if let DropType::NFT(mut nft_data) = drop.drop_type {
-            let mut ids = nft_data.token_ids;
-
-            for id in token_ids {
-                env::log_str(&format!("Removing {}. Present: {}", id, ids.remove(&id)));
+        emit_event(EventLogVariant::AssetRefund(AssetRefundLog {
+            drop_id,
+            asset_index,
+            num_refunded: token_ids.len() as u64,
+            success: transfer_succeeded,
+        }));
+        
+        let mut drop = match self.drop_for_id.get(&drop_id.0) {
+            Some(drop) => drop,
+            None if transfer_succeeded => return true,
+            None => {
+                debug_log!("Drop {} removed before the refund resolved. Sending {} NFTs to {} again", drop_id.0, token_ids.len(), nft_sender);
+                for token_id in token_ids {
+                    ext_nft_contract::ext(nft_contract.clone())
+                        .with_attached_deposit(1)
+                        .with_static_gas(MIN_GAS_FOR_SIMPLE_NFT_TRANSFER)
+                        .nft_transfer(nft_sender.clone(), token_id, None, Some("Refund".to_string()));
+                }
+                return false
             }
-    
-            nft_data.token_ids = ids;
-            drop.drop_type = DropType::NFT(nft_data);
-    
-            return true
         };
-        false
+        drop.pending_refunds = drop.pending_refunds.saturating_sub(1);
+
+        let mut refunded = false;
+        if let Some(DropAsset::NFT(nft_data)) = drop.assets.get_mut(asset_index as usize) {
+            if transfer_succeeded {
+                // Loop through and remove each token ID from the asset's token IDs
+                for id in token_ids {
+                    let present = nft_data.token_ids.remove(&id);
+                    debug_log!("Removing {}. Present: {}", id, present);
+                }
+                refunded = true;
+            } else {
+                // If not successful, the length of the token IDs needs to be added back to the asset.
+                nft_data.num_claims_registered += token_ids.len() as u64;
+                debug_log!("Transfer failed. Adding {} back to asset's claims registered", token_ids.len() as u64);
+            }
+        }
+        self.drop_for_id.insert(&drop_id.0, &drop);
+        refunded
     }
 
     #[private]
@@ -116,16 +124,16 @@ impl DropZone {
         let mut used_gas = env::used_gas();
         let mut prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("Beginning of resolve transfer used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Beginning of resolve transfer used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
         let transfer_succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
 
         used_gas = env::used_gas();
         prepaid_gas = env::prepaid_gas();
-        env::log_str(&format!("Before refunding token sender in resolve transfer: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Before refunding token sender in resolve transfer: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
 
         // If not successful, the balance is added to the amount to refund since it was never transferred.
         if !transfer_succeeded {
-            env::log_str("Attempt to transfer the new account was unsuccessful. Sending the NFT to the original sender.");
+            debug_log!("Attempt to transfer the new account was unsuccessful. Sending the NFT to the original sender.");
             ext_nft_contract::ext(token_contract)
                 // Call nft transfer with the min GAS and 1 yoctoNEAR. all unspent GAS will be added on top
                 .with_static_gas(MIN_GAS_FOR_SIMPLE_NFT_TRANSFER)
@@ -192,3 +200,59 @@ impl DropZone {
         }
     }
 }
+
+impl DropZone {
+    /*
+        Register the token to the first NFT asset in the drop that matches the contract and sender and still has
+        claims left to register. Returns whether the token was registered.
+    */
+    pub(crate) fn internal_register_nft(&mut self, sender_id: AccountId, token_id: String, drop_id: U128) -> bool {
+        let contract_id = env::predecessor_account_id();
+
+        let mut drop = match self.drop_for_id.get(&drop_id.0) {
+            Some(drop) => drop,
+            None => {
+                debug_log!("No drop found for ID {}. Returning the token", drop_id.0);
+                return false
+            }
+        };
+        // The claims registered for an asset can't exceed the number of claims left for the drop.
+        let max_claims_registered = drop.num_claims_registered;
+
+        let nft_index = match drop.assets.iter().position(|asset| matches!(
+            asset, 
+            DropAsset::NFT(data) if data.nft_sender == sender_id && data.nft_contract == contract_id && data.num_claims_registered < max_claims_registered
+        )) {
+            Some(nft_index) => nft_index,
+            None => {
+                debug_log!("NFT data doesn't match what was sent or every claim is registered. Returning the token");
+                return false
+            }
+        };
+
+        if let Some(DropAsset::NFT(nft_data)) = drop.assets.get_mut(nft_index) {
+            if token_id.len() > nft_data.longest_token_id.len() || !nft_data.token_ids.insert(&token_id) {
+                debug_log!("Token ID is longer than the largest token specified or already registered. Returning the token");
+                return false
+            }
+    
+            // Increment the claims registered
+            nft_data.num_claims_registered += 1;
+            debug_log!("nft_data.num_claims_registered {}", nft_data.num_claims_registered);
+
+            emit_event(EventLogVariant::AssetRegistration(AssetRegistrationLog {
+                drop_id,
+                asset_index: nft_index as u64,
+                contract_id,
+                sender_id,
+                num_claims_registered: nft_data.num_claims_registered,
+                amount: None,
+                token_id: Some(token_id),
+            }));
+        }
+    
+        // Insert the drop with the updated data
+        self.drop_for_id.insert(&drop_id.0, &drop);
+        true
+    }
+}
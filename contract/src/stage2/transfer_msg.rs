@@ -0,0 +1,56 @@
+use crate::*;
+
+/*
+    Messages that can be passed in as the msg of `ft_transfer_call` and `nft_transfer_call`. Each message is tagged
+    with the version of the format (i.e `{"v1": {"register_claims": {"drop_id": "0"}}}`) so the format can change
+    without breaking older callers. A plain drop ID is still read as registering claims for that drop.
+*/
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum TransferMsg {
+    V1(TransferIntent),
+}
+
+/// What the sender wants done with the tokens they've sent
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum TransferIntent {
+    // Register claims for the first asset in the drop that matches the contract and sender
+    RegisterClaims { drop_id: U128 },
+    // Create a new drop with the tokens as its asset and register claims with them
+    CreateDrop(CreateDropMsg),
+    // Add fungible tokens to the token balance of an account (the sender if None)
+    TopUpBalance { account_id: Option<AccountId> },
+    // Pay for fees with a whitelisted fungible token. The $NEAR value is credited to an account (the sender if None).
+    PayFees { account_id: Option<AccountId> },
+}
+
+/// Drop to create from a transfer. The token contract and sender make up the drop's asset.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreateDropMsg {
+    pub public_keys: Vec<PublicKey>,
+    // $NEAR sent with each claim
+    pub balance: U128,
+    // Fungible tokens sent with each claim
+    pub ft_balance: U128,
+    pub drop_config: DropConfig,
+    pub passwords_per_key: Option<Vec<Option<Base64VecU8>>>,
+    pub passwords_per_use: Option<Vec<Option<Vec<JsonPasswordForUse>>>>,
+    pub referrer: Option<AccountId>,
+}
+
+impl TransferMsg {
+    /// Read the intent out of a msg. Returns None if the msg is neither a drop ID nor a message in a known format.
+    pub fn parse(msg: &str) -> Option<TransferIntent> {
+        if let Ok(drop_id) = msg.parse::<DropId>() {
+            return Some(TransferIntent::RegisterClaims { drop_id: U128(drop_id) });
+        }
+
+        match near_sdk::serde_json::from_str(msg).ok()? {
+            TransferMsg::V1(intent) => Some(intent),
+        }
+    }
+}
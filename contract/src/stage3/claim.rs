@@ -3,69 +3,75 @@ use crate::*;
 #[near_bindgen]
 impl DropZone {
     /// Claim tokens for specific account that are attached to the public key this tx is signed with.
-    pub fn claim(&mut self, account_id: AccountId) {
+    /// If the key is password protected, the password for the current use must be passed in.
+    /// Function call assets can optionally take fields from the claimer as a stringified JSON object in `fc_args`.
+    pub fn claim(&mut self, account_id: AccountId, password: Option<String>, fc_args: Option<String>) {
         // Delete the access key and remove / return drop data and optional token ID for nft drops. Also return the storage freed.
-        let (drop_data_option, storage_freed_option, token_id, storage_for_longest) = self.process_claim();
+        let (drop_data_option, storage_freed_option, token_ids, use_index, drop_id) = self.process_claim(&account_id, false, password);
 
         if drop_data_option.is_none() {
-            env::log_str("Invalid claim. Returning.");
+            debug_log!("Invalid claim. Returning.");
             return;
         }
         let drop_data = drop_data_option.unwrap();
         let storage_freed = storage_freed_option.unwrap();
+        // The claim is logged by the callbacks once the outcome is known
+        let claim = claim_log(&drop_data, drop_id, &account_id, false, &token_ids, use_index);
 
         // Should we refund send back the $NEAR since an account isn't being created and just send the assets to the claiming account?
-        let account_to_transfer = if drop_data.drop_config.refund_if_claim.unwrap_or(false) == true {drop_data.funder_id.clone()} else {account_id.clone()};
+        let account_to_transfer = if drop_data.drop_config.refund_if_claim.unwrap_or(false) {drop_data.funder_id.clone()} else {account_id.clone()};
 
         let mut promise = None;
         // Only create a promise to transfer $NEAR if the drop's balance is > 0.
         if drop_data.balance.0 > 0 {
-            // Send the account ID the desired balance.
+            // Send the account ID the desired balance. The callback for each asset will be chained after this promise.
             promise = Some(Promise::new(account_to_transfer).transfer(drop_data.balance.0));
         }
 
-        // Execute the callback depending on the drop type. If the drop balance is 0, the promise will be none and the callback function will just straight up be executed instead of resolving the promise.
-        self.internal_execute(drop_data, account_id, storage_freed, token_id, storage_for_longest, promise);
+        // Execute the callbacks for each asset. If the drop balance is 0, the promise will be none and the callback functions will just straight up be executed instead of resolving the promise.
+        self.internal_execute(drop_data, drop_id, account_id, storage_freed, token_ids, use_index, fc_args, claim, promise);
 
         let used_gas = env::used_gas();
         let prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("End of regular claim function: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("End of regular claim function: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
     }
 
-    /// Create new account and and claim tokens to it.
+    /// Create new account and and claim tokens to it. If the key is password protected, the password for the current use must be passed in.
+    /// Function call assets can optionally take fields from the claimer as a stringified JSON object in `fc_args`.
     pub fn create_account_and_claim(
         &mut self,
         new_account_id: AccountId,
         new_public_key: PublicKey,
+        password: Option<String>,
+        fc_args: Option<String>
     ) {
-        let (drop_data_option, storage_freed_option, token_id, storage_for_longest) = self.process_claim();
+        let (drop_data_option, storage_freed_option, token_ids, use_index, drop_id) = self.process_claim(&new_account_id, true, password);
 
         if drop_data_option.is_none() {
-            env::log_str("Invalid claim. Returning.");
+            debug_log!("Invalid claim. Returning.");
             return;
         }
         let drop_data = drop_data_option.unwrap();
         let storage_freed = storage_freed_option.unwrap();
+        // The claim is logged by the callbacks once the outcome is known
+        let claim = claim_log(&drop_data, drop_id, &new_account_id, true, &token_ids, use_index);
 
         // CCC to the linkdrop contract to create the account with the desired balance as the linkdrop amount
+        // Attach the balance of the linkdrop along with the exact gas for create account. No unspent GAS is attached.
         let promise = ext_linkdrop::ext(self.linkdrop_contract.clone())
-            // Attach the balance of the linkdrop along with the exact gas for create account. No unspent GAS is attached.
             .with_attached_deposit(drop_data.balance.0)
             .with_static_gas(GAS_FOR_CREATE_ACCOUNT)
             .with_unused_gas_weight(0)
-            .create_account(
-                new_account_id.clone(),
-                new_public_key,  
-            );
+            .create_account(new_account_id.clone(), new_public_key);
         
-        // Execute the callback depending on the drop type. We'll pass in the promise to resolve
-        self.internal_execute(drop_data, new_account_id, storage_freed, token_id, storage_for_longest, Some(promise));
+        // Execute the callbacks for each asset. We'll pass in the promise to resolve
+        self.internal_execute(drop_data, drop_id, new_account_id, storage_freed, token_ids, use_index, fc_args, claim, Some(promise));
 
         let used_gas = env::used_gas();
         let prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("End of on CAAC function: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("End of on CAAC function: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
     }
 
     #[private]
@@ -76,97 +82,104 @@ impl DropZone {
         funder_id: AccountId, 
         // Balance contained within the linkdrop
         balance: U128, 
-        // How much storage was freed when the key was claimed
-        storage_used: Balance,
+        // How much storage was freed when the key was claimed (including the access key storage)
+        storage_used: U128,
+        // Claim being resolved. Only passed into the callback that refunds the funder.
+        claim: Option<ClaimLog>,
+        // Was this function invoked via an execute (no callback)
+        execute: bool
     ) -> bool {
-        // Get the status of the cross contract call
-        let claim_succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        // Get the status of the cross contract call. If this function is invoked directly via an execute, default the claim succeeded to true 
+        let mut claim_succeeded = true;
+        if !execute {
+            claim_succeeded = promise_claim_succeeded();
+        }
+        self.internal_resolve_claim(claim, claim_succeeded);
 
         let used_gas = env::used_gas();
         let prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("Simple on claim used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
-
-        // Default amount to refund to be everything except balance and burnt GAS since balance was sent to new account.
-        let mut amount_to_refund = ACCESS_KEY_STORAGE + storage_used;
+        debug_log!("Simple on claim used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
+
+        // Refund everything except the balance and burnt GAS since the balance was sent to the new account.
+        // If not successful, the balance is refunded as well since it was never transferred.
+        let amount_to_refund = claim_refund(&ClaimRefundInputs {
+            claim_succeeded,
+            balance: balance.0,
+            storage_freed: storage_used.0,
+            fc_args_accepted: true,
+            ..Default::default()
+        }).to_funder;
         
-        env::log_str(&format!(
+        debug_log!(
             "Refund Amount: {}, 
-            Access Key Storage: {}, 
-            Storage Used: {}", 
+            Storage Used: {}
+            Claim succeeded: {}", 
             yocto_to_near(amount_to_refund), 
-            yocto_to_near(ACCESS_KEY_STORAGE), 
-            yocto_to_near(storage_used))
-        );
-
-        // If not successful, the balance is added to the amount to refund since it was never transferred.
-        if !claim_succeeded {
-            env::log_str(&format!("Claim unsuccessful. Refunding linkdrop balance as well: {}", balance.0));
-            amount_to_refund += balance.0
-        }
+            yocto_to_near(storage_used.0),
+            claim_succeeded);
 
-        env::log_str(&format!("Refunding funder: {:?} For amount: {:?}", funder_id, yocto_to_near(amount_to_refund)));
+        debug_log!("Refunding funder: {:?} For amount: {:?}", funder_id, yocto_to_near(amount_to_refund));
         
         // Get the funder's balance and increment it by the amount to refund
-        let mut cur_funder_balance = self.user_balances.get(&funder_id).expect("No funder balance found");
-        cur_funder_balance += amount_to_refund;
-        self.user_balances.insert(&funder_id, &cur_funder_balance);
+        self.internal_refund_funder(&funder_id, amount_to_refund);
 
         claim_succeeded
     }
 
     #[private]
-    /// self callback for FT linkdrop
+    /// self callback for an FT asset in a linkdrop
     pub fn on_claim_ft(
         &mut self, 
         // Account ID that claimed the linkdrop
         account_id: AccountId, 
         // Account ID that funded the linkdrop
         funder_id: AccountId, 
-        // Balance associated with the linkdrop
+        // Balance associated with the linkdrop. Only passed into one callback per claim.
         balance: U128, 
-        // How much storage was freed when the key was claimed
-        storage_used: Balance,
-        // FT Data for the drop
+        // How much storage was freed when the key was claimed (including the access key storage). Only passed into one callback per claim.
+        storage_used: U128,
+        // FT Data for the asset
         ft_data: FTData,
+        // Claim being resolved. Only passed into the callback that refunds the funder.
+        claim: Option<ClaimLog>,
         // Was this function invoked via an execute (no callback)
         execute: bool
     ) -> bool {
         let used_gas = env::used_gas();
         let prepaid_gas = env::prepaid_gas();
-        env::log_str(&format!("Beginning of on claim FT used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Beginning of on claim FT used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
 
         
         // Get the status of the cross contract call. If this function is invoked directly via an execute, default the claim succeeded to true 
         let mut claim_succeeded = true;
         if !execute {
-            claim_succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+            claim_succeeded = promise_claim_succeeded();
         }
-        env::log_str(&format!("Has function been executed via CCC: {}", !execute));
-
-        // Default amount to refund to be everything except balance and burnt GAS since balance was sent to new account.
-        let mut amount_to_refund = ACCESS_KEY_STORAGE + storage_used;
+        self.internal_resolve_claim(claim, claim_succeeded);
+        debug_log!("Has function been executed via CCC: {}", !execute);
+
+        // Refund everything except the balance and burnt GAS since the balance was sent to the new account.
+        // If not successful, the balance is refunded as well since it was never transferred.
+        let amount_to_refund = claim_refund(&ClaimRefundInputs {
+            claim_succeeded,
+            balance: balance.0,
+            storage_freed: storage_used.0,
+            fc_args_accepted: true,
+            ..Default::default()
+        }).to_funder;
         
-        env::log_str(&format!(
+        debug_log!(
             "Refund Amount: {}, 
-            Access Key Storage: {}, 
-            Storage Used: {}", 
+            Storage Used: {}
+            Claim succeeded: {}", 
             yocto_to_near(amount_to_refund), 
-            yocto_to_near(ACCESS_KEY_STORAGE), 
-            yocto_to_near(storage_used)
-        ));
-        
-        // If not successful, the balance is added to the amount to refund since it was never transferred.
-        if !claim_succeeded {
-            env::log_str(&format!("Claim unsuccessful. Refunding linkdrop balance as well: {}", balance.0));
-            amount_to_refund += balance.0
-        }
+            yocto_to_near(storage_used.0),
+            claim_succeeded);
 
-        env::log_str(&format!("Refunding funder: {:?} balance For amount: {:?}", funder_id, yocto_to_near(amount_to_refund)));
+        debug_log!("Refunding funder: {:?} balance For amount: {:?}", funder_id, yocto_to_near(amount_to_refund));
         // Get the funder's balance and increment it by the amount to refund
-        let mut cur_funder_balance = self.user_balances.get(&funder_id).expect("No funder balance found");
-        cur_funder_balance += amount_to_refund;
-        self.user_balances.insert(&funder_id, &cur_funder_balance);
+        self.internal_refund_funder(&funder_id, amount_to_refund);
 
         // Perform the FT transfer functionality
         self.internal_ft_transfer(claim_succeeded, ft_data, account_id);
@@ -175,65 +188,68 @@ impl DropZone {
     }
 
     #[private]
-    /// self callback for a linkdrop loaded with an NFT
+    /// self callback for an NFT asset in a linkdrop
     pub fn on_claim_nft(&mut self, 
         // Account ID that claimed the linkdrop
         account_id: AccountId, 
         // Account ID that funded the linkdrop
         funder_id: AccountId, 
-        // Balance associated with the linkdrop
+        // Balance associated with the linkdrop. Only passed into one callback per claim.
         balance: U128, 
-        // How much storage was freed when the key was claimed
-        storage_used: Balance,
+        // How much storage was freed when the key was claimed (including the access key storage). Only passed into one callback per claim.
+        storage_used: U128,
         // How much storage was prepaid to cover the longest token ID being inserted.
-        storage_for_longest: Balance,
+        storage_for_longest: U128,
         // Sender of the NFT
         nft_sender: AccountId,
         // Contract where the NFT is stored
         nft_contract: AccountId,
         // Token ID for the NFT
         token_id: String,
+        // Claim being resolved. Only passed into the callback that refunds the funder.
+        claim: Option<ClaimLog>,
         // Was this function invoked via an execute (no callback)
         execute: bool
     ) -> bool {
         let used_gas = env::used_gas();
         let prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("Beginning of on claim NFT used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Beginning of on claim NFT used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
 
         // Get the status of the cross contract call. If this function is invoked directly via an execute, default the claim succeeded to true 
         let mut claim_succeeded = true;
         if !execute {
-            claim_succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+            claim_succeeded = promise_claim_succeeded();
         }
-        env::log_str(&format!("Has function been executed via CCC: {}", !execute));
-
-        // Default amount to refund to be everything except balance and burnt GAS since balance was sent to new account.
-        // In addition, we refund them for the cost of storing the longest token ID now that a key has been claimed
-        let mut amount_to_refund = ACCESS_KEY_STORAGE + storage_used + storage_for_longest * env::storage_byte_cost();
+        self.internal_resolve_claim(claim, claim_succeeded);
+        debug_log!("Has function been executed via CCC: {}", !execute);
+
+        // Refund everything except the balance and burnt GAS since the balance was sent to the new account.
+        // In addition, we refund them for the cost of storing the longest token ID now that a key has been claimed.
+        // If not successful, the balance is refunded as well since it was never transferred.
+        let amount_to_refund = claim_refund(&ClaimRefundInputs {
+            claim_succeeded,
+            balance: balance.0,
+            storage_freed: storage_used.0,
+            asset_storage_released: storage_cost(storage_for_longest.0 as u64, env::storage_byte_cost()),
+            fc_args_accepted: true,
+            ..Default::default()
+        }).to_funder;
         
-        env::log_str(&format!(
+        debug_log!(
             "Refund Amount: {}, 
-            Access Key Storage: {}, 
             Storage Used: {}
-            Storage for longest: {}", 
+            Storage for longest: {}
+            Claim succeeded: {}", 
             yocto_to_near(amount_to_refund), 
-            yocto_to_near(ACCESS_KEY_STORAGE), 
-            yocto_to_near(storage_used),
-            yocto_to_near(storage_for_longest * env::storage_byte_cost())
-        ));
-    
-        // If not successful, the balance is added to the amount to refund since it was never transferred.
-        if !claim_succeeded {
-            env::log_str(&format!("Claim unsuccessful. Refunding linkdrop balance as well: {}", balance.0));
-            amount_to_refund += balance.0
-        }
+            yocto_to_near(storage_used.0),
+            yocto_to_near(storage_cost(storage_for_longest.0 as u64, env::storage_byte_cost())),
+            claim_succeeded
+        );
 
-        env::log_str(&format!("Refunding funder: {:?} balance For amount: {:?}", funder_id, yocto_to_near(amount_to_refund)));
+        debug_log!("Refunding funder: {:?} balance For amount: {:?}", funder_id, yocto_to_near(amount_to_refund));
         // Get the funder's balance and increment it by the amount to refund
-        let mut cur_funder_balance = self.user_balances.get(&funder_id).expect("No funder balance found");
-        cur_funder_balance += amount_to_refund;
-        self.user_balances.insert(&funder_id, &cur_funder_balance);
+        self.internal_refund_funder(&funder_id, amount_to_refund);
 
         // Transfer the NFT
         self.internal_nft_transfer(claim_succeeded, nft_contract, token_id, nft_sender, account_id);
@@ -241,87 +257,109 @@ impl DropZone {
     }
 
     #[private]
-    /// self callback checks if account was created successfully or not. If yes, refunds excess storage, sends NFTs, FTs etc..
+    /// self callback for a function call asset. If the claim was successful, the function is called. Otherwise the deposit is refunded.
     pub fn on_claim_fc(&mut self, 
         // Account ID that claimed the linkdrop
         account_id: AccountId,
         // Account ID that funded the linkdrop
         funder_id: AccountId, 
-        // Balance associated with the linkdrop
+        // Balance associated with the linkdrop. Only passed into one callback per claim.
         balance: U128, 
-        // How much storage was freed when the key was claimed
-        storage_used: Balance,
-        // FC Data for the drop
+        // How much storage was freed when the key was claimed (including the access key storage). Only passed into one callback per claim.
+        storage_used: U128,
+        // FC Data for the asset
         fc_data: FCData,
+        // Drop ID that the claimed key belongs to
+        drop_id: U128,
+        // Which use of the key was claimed (starting at 0)
+        use_index: u64,
+        // Stringified JSON object of fields passed in by the claimer
+        fc_args: Option<String>,
+        // Claim being resolved. Only passed into the callback that refunds the funder.
+        claim: Option<ClaimLog>,
         // Was this function invoked via an execute (no callback)
         execute: bool
     ) -> bool {
         let used_gas = env::used_gas();
         let prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("Beginning of on claim Function Call used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Beginning of on claim Function Call used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
 
         // Get the status of the cross contract call. If this function is invoked directly via an execute, default the claim succeeded to true 
         let mut claim_succeeded = true;
         if !execute {
-            claim_succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+            claim_succeeded = promise_claim_succeeded();
         }
-        env::log_str(&format!("Has function been executed via CCC: {}", !execute));
+        self.internal_resolve_claim(claim, claim_succeeded);
+        debug_log!("Has function been executed via CCC: {}", !execute);
 
-        // Default amount to refund to be everything except balance and burnt GAS since balance was sent to new account.
-        let mut amount_to_refund = ACCESS_KEY_STORAGE + storage_used;
-        
-        env::log_str(&format!(
-            "Refund Amount: {}, 
-            Access Key Storage: {}, 
-            Storage Used: {}", 
-            yocto_to_near(amount_to_refund), 
-            yocto_to_near(ACCESS_KEY_STORAGE), 
-            yocto_to_near(storage_used))
-        );
-        
-        // If not successful, the balance and deposit is added to the amount to refund since it was never transferred.
-        if !claim_succeeded {
-            env::log_str(&format!("Claim unsuccessful. Refunding linkdrop balance: {} and deposit: {}", balance.0, fc_data.deposit.0));
-            amount_to_refund += balance.0 + fc_data.deposit.0
-        }
+        // Only the fields the funder allows can be set by the claimer. If any value is outside the funder's limits, the function call is rejected.
+        let user_args = fc_data.allowed_user_args(&fc_args);
 
         /* 
-            If the claim is not successful, we should always refund. The only case where we don't refund is
-            if the claim was successful and the user specified that the refund should go into the
-            deposit.
+            If the claim is not successful, we should always refund the balance and deposit since they were never transferred.
+            If the user args were rejected, the deposit is refunded. The only case where the funder isn't refunded is
+            if the claim was successful and the funder specified that the refund should go into the deposit.
 
             0 0     Refund     !success  -> do refund
             0 1     Refund      success  -> do refund
             1 0     No Refund  !success  -> do refund
             1 1     No Refund   Success  -> don't do refund
         */ 
-        if !claim_succeeded || (!fc_data.refund_to_deposit.unwrap_or(false) && claim_succeeded) {
+        // If there are no methods to call for this use or the user args were rejected, there's no deposit to attach the refund to.
+        let refund = claim_refund(&ClaimRefundInputs {
+            claim_succeeded,
+            balance: balance.0,
+            storage_freed: storage_used.0,
+            fc_deposit: fc_data.total_deposit(),
+            fc_args_accepted: user_args.is_some(),
+            refund_to_deposit: fc_data.refund_to_deposit.unwrap_or(false) && !fc_data.methods.is_empty() && user_args.is_some(),
+            ..Default::default()
+        });
+        
+        debug_log!(
+            "Refund Amount: {}, 
+            Storage Used: {}
+            Claim succeeded: {}
+            User args accepted: {}", 
+            yocto_to_near(refund.to_funder + refund.to_deposit), 
+            yocto_to_near(storage_used.0),
+            claim_succeeded,
+            user_args.is_some());
+
+        if refund.to_deposit == 0 {
             // Refunding
-            env::log_str(&format!("Refunding funder: {:?} balance For amount: {:?}", funder_id, yocto_to_near(amount_to_refund)));
+            debug_log!("Refunding funder: {:?} balance For amount: {:?}", funder_id, yocto_to_near(refund.to_funder));
             // Get the funder's balance and increment it by the amount to refund
-            let mut cur_funder_balance = self.user_balances.get(&funder_id).expect("No funder balance found");
-            cur_funder_balance += amount_to_refund;
-            self.user_balances.insert(&funder_id, &cur_funder_balance);
+            self.internal_refund_funder(&funder_id, refund.to_funder);
         } else {
-            env::log_str(&format!("Skipping the refund to funder: {:?} claim success: {:?} refund to deposit?: {:?}", funder_id, claim_succeeded, fc_data.refund_to_deposit.unwrap_or(false)));
+            debug_log!("Skipping the refund to funder: {:?} claim success: {:?} refund to deposit: {:?}", funder_id, claim_succeeded, yocto_to_near(refund.to_deposit));
         }
 
-        self.internal_fc_execute(
-            fc_data, 
-            amount_to_refund, 
-            account_id
-        );
+        // Only call the functions if the claim was successful, the user args were accepted and there are methods scheduled for this use. Otherwise the deposit has been refunded to the funder.
+        if let Some(user_args) = user_args.filter(|_| claim_succeeded && !fc_data.methods.is_empty()) {
+            self.internal_fc_execute(
+                fc_data, 
+                refund.to_deposit, 
+                account_id,
+                funder_id,
+                drop_id.0,
+                use_index,
+                user_args
+            );
+        }
         claim_succeeded
     }
 
     /// Internal method for deleting the used key and removing / returning linkdrop data.
+    /// Also returns the token ID to send for every asset in the drop (None for non NFT assets), which use of the key is being claimed and the drop ID.
+    /// The account ID is the account receiving the assets and is used to enforce the per account claim limit and the drop's allowlist and denylist.
     /// If drop is none, simulate a panic.
-    fn process_claim(&mut self) -> (Option<Drop>, Option<Balance>, Option<String>, Option<Balance>) {
+    fn process_claim(&mut self, account_id: &AccountId, is_new_account: bool, password: Option<String>) -> (Option<Drop>, Option<Balance>, Vec<Option<String>>, u64, DropId) {
         let mut used_gas = env::used_gas();
         let prepaid_gas = env::prepaid_gas();
 
-        env::log_str(&format!("Beginning of process claim used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0));
+        debug_log!("Beginning of process claim used gas: {:?} prepaid gas: {:?}", used_gas.0, prepaid_gas.0);
 
         // Pessimistically measure storage
         let initial_storage = env::storage_usage();
@@ -333,6 +371,10 @@ impl DropZone {
             "predecessor != current"
         );
 
+        // Claims that are paused fail before the allowance is charged
+        // Panic doesn't affect allowance
+        self.assert_not_paused(PauseCategory::Claims);
+
         // Get the PK of the signer which should be the contract's function call access key
         let signer_pk = env::signer_account_pk();
 
@@ -342,30 +384,38 @@ impl DropZone {
         // Remove the drop. If the drop shouldn't be removed, we re-insert later.
         // Panic doesn't affect allowance
         let mut drop = self.drop_for_id.remove(&drop_id).expect("drop not found");
+        // Panic doesn't affect allowance
+        require!(!drop.paused, "drop is paused");
         // Remove the pk from the drop's set and check for key usage.
         // Panic doesn't affect allowance
-        let mut key_usage = drop.pks.remove(&signer_pk).unwrap();
+        let mut key_usage = drop.remove_key_usage(&signer_pk).unwrap();
+
+        // Every FT and NFT asset must have a claim registered for the key to be used
+        let assets_registered = drop.assets.iter().all(|asset| match asset {
+            DropAsset::FT(data) => data.num_claims_registered > 0,
+            DropAsset::NFT(data) => data.num_claims_registered > 0,
+            DropAsset::FC(_) => true
+        });
 
         // Ensure there's enough claims left for the key to be used. (this *should* only happen in NFT or FT cases)
-        if drop.num_claims_registered < 1 || prepaid_gas != drop.required_gas_attached {
+        if drop.num_claims_registered < 1 || !assets_registered || prepaid_gas != drop.required_gas_attached {
             used_gas = env::used_gas();
             
-            let amount_to_decrement = (used_gas.0 + GAS_FOR_PANIC_OFFSET.0) as u128 * self.yocto_per_gas;
-            if drop.num_claims_registered < 1 {
-                env::log_str(&format!("Not enough claims left for the drop. Decrementing allowance by {}. Used GAS: {}", amount_to_decrement, used_gas.0));
+            let amount_to_decrement = failed_claim_charge(used_gas, self.yocto_per_gas);
+            if drop.num_claims_registered < 1 || !assets_registered {
+                debug_log!("Not enough claims left for the drop. Decrementing allowance by {}. Used GAS: {}", amount_to_decrement, used_gas.0);
             } else {
-                env::log_str(&format!("Prepaid GAS different than what is specified in the drop: {}. Decrementing allowance by {}. Used GAS: {}", drop.required_gas_attached.0, amount_to_decrement, used_gas.0));
+                debug_log!("Prepaid GAS different than what is specified in the drop: {}. Decrementing allowance by {}. Used GAS: {}", drop.required_gas_attached.0, amount_to_decrement, used_gas.0);
             }
             
+            emit_claim_failure(drop_id, &signer_pk, account_id, if drop.num_claims_registered < 1 || !assets_registered {"no claims registered"} else {"prepaid gas mismatch"}, amount_to_decrement);
             key_usage.allowance -= amount_to_decrement;
-            env::log_str(&format!("Allowance is now {}", key_usage.allowance));
+            debug_log!("Allowance is now {}", key_usage.allowance);
             drop.pks.insert(&signer_pk, &key_usage);
             self.drop_for_id.insert(&drop_id, &drop);
-            return (None, None, None, None);
+            return (None, None, vec![], 0, drop_id);
         }
 
-        drop.num_claims_registered -= 1;
-
         // Ensure enough time has passed if a start timestamp was specified in the config.
         let current_timestamp = env::block_timestamp();
         let desired_timestamp = drop.drop_config.start_timestamp.unwrap_or(current_timestamp);
@@ -373,68 +423,203 @@ impl DropZone {
         if current_timestamp < desired_timestamp {
             used_gas = env::used_gas();
             
-            let amount_to_decrement = (used_gas.0 + GAS_FOR_PANIC_OFFSET.0) as u128 * self.yocto_per_gas;
-            env::log_str(&format!("Drop isn't claimable until {}. Current timestamp is {}. Decrementing allowance by {}. Used GAS: {}", desired_timestamp, current_timestamp, amount_to_decrement, used_gas.0));
+            let amount_to_decrement = failed_claim_charge(used_gas, self.yocto_per_gas);
+            debug_log!("Drop isn't claimable until {}. Current timestamp is {}. Decrementing allowance by {}. Used GAS: {}", desired_timestamp, current_timestamp, amount_to_decrement, used_gas.0);
+            
+            emit_claim_failure(drop_id, &signer_pk, account_id, "before start timestamp", amount_to_decrement);
+            key_usage.allowance -= amount_to_decrement;
+            debug_log!("Allowance is now {}", key_usage.allowance);
+            drop.pks.insert(&signer_pk, &key_usage);
+            self.drop_for_id.insert(&drop_id, &drop);
+            return (None, None, vec![], 0, drop_id);
+        }
+
+        // Ensure the drop hasn't passed its end timestamp or end block height if specified in the config.
+        if drop.drop_config.has_expired() {
+            used_gas = env::used_gas();
+            
+            let amount_to_decrement = failed_claim_charge(used_gas, self.yocto_per_gas);
+            debug_log!("Drop has expired. Current timestamp is {} and block height is {}. Decrementing allowance by {}. Used GAS: {}", current_timestamp, env::block_height(), amount_to_decrement, used_gas.0);
             
+            emit_claim_failure(drop_id, &signer_pk, account_id, "drop expired", amount_to_decrement);
             key_usage.allowance -= amount_to_decrement;
-            env::log_str(&format!("Allowance is now {}", key_usage.allowance));
+            debug_log!("Allowance is now {}", key_usage.allowance);
             drop.pks.insert(&signer_pk, &key_usage);
             self.drop_for_id.insert(&drop_id, &drop);
-            return (None, None, None, None);
+            return (None, None, vec![], 0, drop_id);
         }
+
+        // Ensure the correct password was passed in if the key is password protected. Per-use passwords take precedence over the per-key password.
+        let cur_use = drop.drop_config.max_claims_per_key - key_usage.num_uses + 1;
+        let expected_hash = key_usage.pw_per_use.as_ref()
+            .and_then(|pws| pws.get(&cur_use))
+            .map(|hash| (hash, format!("{}{}{}", password.clone().unwrap_or_default(), String::from(&signer_pk), cur_use)))
+            .or_else(|| key_usage.pw_per_key.as_ref().map(|hash| (hash, format!("{}{}", password.clone().unwrap_or_default(), String::from(&signer_pk)))));
+
+        if let Some((hash, preimage)) = expected_hash {
+            if password.is_none() || &env::sha256(preimage.as_bytes()) != hash {
+                used_gas = env::used_gas();
+                
+                let amount_to_decrement = failed_claim_charge(used_gas, self.yocto_per_gas);
+                debug_log!("Invalid password for use {}. Decrementing allowance by {}. Used GAS: {}", cur_use, amount_to_decrement, used_gas.0);
                 
-        // Default the token ID to none and return / remove the next token ID if it's an NFT drop
-        let mut token_id = None;
-        // Default the storage for longest to be none and return the actual value if it's an NFT drop
-        let mut storage_for_longest = None;
-
-        // If it's an NFT drop get the token ID and remove it from the set. Also set the storage for longest
-        match &mut drop.drop_type {
-            DropType::NFT(data) => {
-                token_id = data.token_ids.iter().next();
-                data.token_ids.remove(token_id.as_ref().unwrap());
-                storage_for_longest = Some(data.storage_for_longest);
-            },
-            _ => {}
-        };
+                emit_claim_failure(drop_id, &signer_pk, account_id, "invalid password", amount_to_decrement);
+                key_usage.allowance -= amount_to_decrement;
+                debug_log!("Allowance is now {}", key_usage.allowance);
+                drop.pks.insert(&signer_pk, &key_usage);
+                self.drop_for_id.insert(&drop_id, &drop);
+                return (None, None, vec![], 0, drop_id);
+            }
+        }
+
+        // Ensure the claiming account is allowed to claim given the drop's allowlist, denylist and new account suffix.
+        if !drop.is_account_allowed(account_id, is_new_account) {
+            used_gas = env::used_gas();
+            
+            let amount_to_decrement = failed_claim_charge(used_gas, self.yocto_per_gas);
+            debug_log!("Account {} is not allowed to claim from the drop. Decrementing allowance by {}. Used GAS: {}", account_id, amount_to_decrement, used_gas.0);
+            
+            emit_claim_failure(drop_id, &signer_pk, account_id, "account not allowed", amount_to_decrement);
+            key_usage.allowance -= amount_to_decrement;
+            debug_log!("Allowance is now {}", key_usage.allowance);
+            drop.pks.insert(&signer_pk, &key_usage);
+            self.drop_for_id.insert(&drop_id, &drop);
+            return (None, None, vec![], 0, drop_id);
+        }
+
+        // Ensure the drop and the claiming account haven't reached their claim limits if specified in the config.
+        let claims_for_account = drop.claims_per_account.get(account_id).unwrap_or(0);
+        let total_claims_reached = drop.drop_config.max_total_claims.map(|max| drop.total_claims >= max).unwrap_or(false);
+        let account_claims_reached = drop.drop_config.max_claims_per_account.map(|max| claims_for_account >= max).unwrap_or(false);
+
+        if total_claims_reached || account_claims_reached {
+            used_gas = env::used_gas();
+            
+            let amount_to_decrement = failed_claim_charge(used_gas, self.yocto_per_gas);
+            if total_claims_reached {
+                debug_log!("Drop has reached its maximum of {} claims. Decrementing allowance by {}. Used GAS: {}", drop.total_claims, amount_to_decrement, used_gas.0);
+            } else {
+                debug_log!("Account {} has already claimed {} times. Decrementing allowance by {}. Used GAS: {}", account_id, claims_for_account, amount_to_decrement, used_gas.0);
+            }
+            
+            emit_claim_failure(drop_id, &signer_pk, account_id, if total_claims_reached {"max total claims reached"} else {"max claims per account reached"}, amount_to_decrement);
+            key_usage.allowance -= amount_to_decrement;
+            debug_log!("Allowance is now {}", key_usage.allowance);
+            drop.pks.insert(&signer_pk, &key_usage);
+            self.drop_for_id.insert(&drop_id, &drop);
+            return (None, None, vec![], 0, drop_id);
+        }
+
+        // Ensure the block height and epoch allow the key to be used if specified in the config.
+        let current_block = env::block_height();
+        let desired_block = drop.drop_config.start_block.unwrap_or(current_block);
+        let blocks_since_used = current_block - key_usage.last_used_block;
+        let current_epoch = env::epoch_height();
+        let claims_in_epoch = if key_usage.last_used_epoch == current_epoch {key_usage.claims_in_epoch} else {0};
 
+        let before_start_block = current_block < desired_block;
+        let within_block_interval = drop.drop_config.usage_interval_blocks.map(|interval| blocks_since_used < interval).unwrap_or(false);
+        let epoch_limit_reached = drop.drop_config.max_claims_per_epoch.map(|max| claims_in_epoch >= max).unwrap_or(false);
+
+        if before_start_block || within_block_interval || epoch_limit_reached {
+            used_gas = env::used_gas();
+            
+            let amount_to_decrement = failed_claim_charge(used_gas, self.yocto_per_gas);
+            if before_start_block {
+                debug_log!("Drop isn't claimable until block {}. Current block is {}. Decrementing allowance by {}. Used GAS: {}", desired_block, current_block, amount_to_decrement, used_gas.0);
+            } else if within_block_interval {
+                debug_log!("Not enough blocks have passed since the key was last used: {}. Decrementing allowance by {}. Used GAS: {}", blocks_since_used, amount_to_decrement, used_gas.0);
+            } else {
+                debug_log!("Key has been used {} times in epoch {}. Decrementing allowance by {}. Used GAS: {}", claims_in_epoch, current_epoch, amount_to_decrement, used_gas.0);
+            }
+            
+            emit_claim_failure(drop_id, &signer_pk, account_id, if before_start_block {"before start block"} else if within_block_interval {"usage interval blocks not passed"} else {"max claims per epoch reached"}, amount_to_decrement);
+            key_usage.allowance -= amount_to_decrement;
+            debug_log!("Allowance is now {}", key_usage.allowance);
+            drop.pks.insert(&signer_pk, &key_usage);
+            self.drop_for_id.insert(&drop_id, &drop);
+            return (None, None, vec![], 0, drop_id);
+        }
+                
         // Default the should delete variable to true. If there's a case where it shouldn't, change the bool.
         let mut should_delete = true;
-        env::log_str(&format!("Key usage last used: {:?} Num uses: {:?} (before)", key_usage.last_used, key_usage.num_uses));
+        debug_log!("Key usage last used: {:?} Num uses: {:?} (before)", key_usage.last_used, key_usage.num_uses);
         
         // Ensure the key is within the interval if specified
         if let Some(interval) = drop.drop_config.usage_interval {
-            env::log_str(&format!("Current timestamp {} last used: {} subs: {} interval: {}", current_timestamp, key_usage.last_used, current_timestamp - key_usage.last_used, interval));
+            debug_log!("Current timestamp {} last used: {} subs: {} interval: {}", current_timestamp, key_usage.last_used, current_timestamp - key_usage.last_used, interval);
             
-            if (current_timestamp - key_usage.last_used) < interval || key_usage.allowance < prepaid_gas.0 as u128 * self.yocto_per_gas {
+            if (current_timestamp - key_usage.last_used) < interval || key_usage.allowance < gas_cost(prepaid_gas, self.yocto_per_gas) {
                 used_gas = env::used_gas();
                 
-                let amount_to_decrement = (used_gas.0 + GAS_FOR_PANIC_OFFSET.0) as u128 * self.yocto_per_gas;
+                let amount_to_decrement = failed_claim_charge(used_gas, self.yocto_per_gas);
                 if (current_timestamp - key_usage.last_used) < interval {
-                    env::log_str(&format!("Not enough time has passed since the key was last used. Decrementing allowance by {}. Used GAS: {}", amount_to_decrement, used_gas.0));
+                    debug_log!("Not enough time has passed since the key was last used. Decrementing allowance by {}. Used GAS: {}", amount_to_decrement, used_gas.0);
                 } else {
-                    env::log_str(&format!("Not enough allowance on the key {}. Decrementing allowance by {} Used GAS: {}", key_usage.allowance, amount_to_decrement, used_gas.0));
+                    debug_log!("Not enough allowance on the key {}. Decrementing allowance by {} Used GAS: {}", key_usage.allowance, amount_to_decrement, used_gas.0);
                 }
                 
+                emit_claim_failure(drop_id, &signer_pk, account_id, if (current_timestamp - key_usage.last_used) < interval {"usage interval not passed"} else {"not enough allowance"}, amount_to_decrement);
                 key_usage.allowance -= amount_to_decrement;
-                env::log_str(&format!("Allowance is now {}", key_usage.allowance));
+                debug_log!("Allowance is now {}", key_usage.allowance);
                 drop.pks.insert(&signer_pk, &key_usage);
                 self.drop_for_id.insert(&drop_id, &drop);
-                return (None, None, None, None);
+                return (None, None, vec![], 0, drop_id);
             }
             
-            env::log_str(&format!("Enough time has passed for key to be used. Setting last used to current timestamp {}", current_timestamp));
+            debug_log!("Enough time has passed for key to be used. Setting last used to current timestamp {}", current_timestamp);
             key_usage.last_used = current_timestamp;
         }
+
+        // Keep track of the block and epoch the key was used in for the block based scheduling
+        key_usage.last_used_block = current_block;
+        key_usage.last_used_epoch = current_epoch;
+        key_usage.claims_in_epoch = claims_in_epoch + 1;
+
+        // Keep track of the claim for the drop limits. The storage for recording the account was prepaid by the funder.
+        drop.total_claims += 1;
+        if drop.drop_config.max_claims_per_account.is_some() {
+            drop.claims_per_account.insert(account_id, &(claims_for_account + 1));
+        }
+
+        // The claim is valid. Get which use of the key is being claimed (starting at 0) before the uses are decremented.
+        let use_index = drop.drop_config.max_claims_per_key - key_usage.num_uses;
+        
+        // Decrement the claims left for the drop and each of its FT and NFT assets.
+        drop.num_claims_registered -= 1;
+        // Get and remove the next token ID for every NFT asset and pick the amount to send for every FT asset
+        let mut token_ids = Vec::with_capacity(drop.assets.len());
+        let mut ft_amounts = Vec::new();
+        // Storage for the token IDs is covered by the storage prepaid for the longest token ID, which the NFT callbacks refund
+        let mut token_storage_released = 0;
+        for (asset_index, asset) in drop.assets.iter_mut().enumerate() {
+            let token_id = match asset {
+                DropAsset::NFT(data) => {
+                    data.num_claims_registered -= 1;
+                    let token_id = data.token_ids.iter().next().expect("no token IDs left for NFT asset");
+                    let storage_before_removal = env::storage_usage();
+                    data.token_ids.remove(&token_id);
+                    token_storage_released += storage_before_removal - env::storage_usage();
+                    Some(token_id)
+                },
+                DropAsset::FT(data) => {
+                    data.num_claims_registered -= 1;
+                    ft_amounts.push((asset_index, data.take_claim_amount(self.internal_random_u64(drop_id, &signer_pk, use_index, asset_index as u64))));
+                    None
+                },
+                DropAsset::FC(_) => None
+            };
+            token_ids.push(token_id);
+        }
         
         // No uses left! The key should be deleted
         if key_usage.num_uses == 1 {
-            env::log_str("Key has no uses left. It will be deleted");
+            debug_log!("Key has no uses left. It will be deleted");
             self.drop_id_for_pk.remove(&signer_pk);
         } else {
             key_usage.num_uses -= 1;
-            key_usage.allowance -= drop.required_gas_attached.0 as u128 * self.yocto_per_gas;
-            env::log_str(&format!("Key has {} uses left. Decrementing allowance by {}. Allowance left: {}", key_usage.num_uses, drop.required_gas_attached.0 as u128 * self.yocto_per_gas, key_usage.allowance));
+            key_usage.allowance -= claim_charge(drop.required_gas_attached, self.yocto_per_gas);
+            debug_log!("Key has {} uses left. Decrementing allowance by {}. Allowance left: {}", key_usage.num_uses, claim_charge(drop.required_gas_attached, self.yocto_per_gas), key_usage.allowance);
 
             drop.pks.insert(&signer_pk, &key_usage);
             should_delete = false;
@@ -454,13 +639,16 @@ impl DropZone {
         }
 
         // Calculate the storage being freed. initial - final should be >= 0 since final should be smaller than initial.
+        // The storage prepaid for recording the claiming account is released as well. The token IDs aren't counted
+        // since they were never charged for on top of the storage for the longest token ID.
         let final_storage = env::storage_usage();
-        let total_storage_freed = Balance::from(initial_storage - final_storage) * env::storage_byte_cost();
+        let prepaid_storage = if drop.drop_config.max_claims_per_account.is_some() {CLAIMED_ACCOUNT_STORAGE} else {0};
+        let total_storage_freed = storage_freed(initial_storage, prepaid_storage, final_storage + token_storage_released, env::storage_byte_cost());
 
         if should_delete {
             // Amount to refund is the current allowance less the current execution's max GAS
-            let amount_to_refund = key_usage.allowance - drop.required_gas_attached.0 as u128 * self.yocto_per_gas;
-            env::log_str(&format!("Key being deleted. Allowance Currently: {}. Will refund: {}", key_usage.allowance, amount_to_refund));
+            let amount_to_refund = key_usage.allowance - claim_charge(drop.required_gas_attached, self.yocto_per_gas);
+            debug_log!("Key being deleted. Allowance Currently: {}. Will refund: {}", key_usage.allowance, amount_to_refund);
             // Get the funder's balance and increment it by the amount to refund
             let mut cur_funder_balance = self.user_balances.get(&drop.funder_id).expect("No funder balance found");
             cur_funder_balance += amount_to_refund;
@@ -470,7 +658,14 @@ impl DropZone {
             Promise::new(env::current_account_id()).delete_key(signer_pk);
         }
         
-        // Return the drop and optional token ID with how much storage was freed
-        (Some(drop), Some(total_storage_freed), token_id, storage_for_longest)
+        // The drop has been stored so the FT data that's sent can carry the amount picked for this claim
+        for (asset_index, amount) in ft_amounts {
+            if let DropAsset::FT(data) = &mut drop.assets[asset_index] {
+                data.ft_balance = amount;
+            }
+        }
+
+        // Return the drop, token IDs, use index and drop ID with how much storage was freed
+        (Some(drop), Some(total_storage_freed), token_ids, use_index, drop_id)
     }
 }
\ No newline at end of file
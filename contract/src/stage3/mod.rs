@@ -1,3 +1 @@
-pub mod claim;
-
-pub use claim::*;
\ No newline at end of file
+pub mod claim;
\ No newline at end of file
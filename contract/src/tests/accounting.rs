@@ -0,0 +1,430 @@
+use near_sdk::mock::VmAction;
+use near_sdk::serde_json::{self, json};
+use near_sdk::test_utils::get_created_receipts;
+use near_sdk::{PromiseResult, RuntimeFeesConfig, VMConfig};
+use proptest::prelude::*;
+
+use super::*;
+
+const STORAGE_BYTE_COST: Balance = 10_000_000_000_000_000_000;
+const YOCTO_PER_GAS: u128 = 100_000_000;
+// FTs sent with every claim of an FT asset
+const FT_BALANCE: Balance = 1;
+
+#[derive(Debug, Clone, Copy)]
+enum Attempt {
+    // Claim with the key at this index. The $NEAR transfer the claim's callbacks wait on either goes through or fails.
+    Claim { key: usize, transfer_succeeds: bool },
+    // Claim with the key at this index but attach the wrong GAS so process_claim rejects it
+    WrongGas { key: usize },
+}
+
+#[derive(Debug, Clone)]
+struct Scenario {
+    num_keys: usize,
+    claims_per_key: u64,
+    balance: Balance,
+    drop_fee: Balance,
+    key_fee: Balance,
+    limits_claims_per_account: bool,
+    // Storage the FT contract needs to register an account, for every FT asset
+    ft_storage: Vec<Balance>,
+    num_nfts: usize,
+    // Deposit attached to the method called by every function call asset
+    fc_deposits: Vec<Balance>,
+    // Attempts to use the keys in order. Whatever is left once these run out is refunded and deleted by the funder.
+    attempts: Vec<Attempt>,
+}
+
+/// Where the $NEAR taken out of the funder's balance went
+#[derive(Default, Debug)]
+struct Outcome {
+    // Taken out of the funder's balance from creating the drop until it was gone, as measured on the contract
+    spent: Balance,
+    fees: Balance,
+    // Allowance used up by the claims, whether they were valid or not
+    burned_gas: Balance,
+    // Sent out by claims: the balance, function call deposits and FT storage registrations
+    delivered: Balance,
+    // Storage the contract still holds once the drop is gone
+    held_storage: Balance,
+}
+
+fn attempt() -> impl Strategy<Value = Attempt> {
+    prop_oneof![
+        3 => (0..3usize, any::<bool>()).prop_map(|(key, transfer_succeeds)| Attempt::Claim { key, transfer_succeeds }),
+        1 => (0..3usize).prop_map(|key| Attempt::WrongGas { key }),
+    ]
+}
+
+prop_compose! {
+    fn scenario()(
+        num_keys in 1..=3usize,
+        claims_per_key in 1..=3u64,
+        balance in prop_oneof![Just(0u128), 1..ONE_NEAR / 10],
+        drop_fee in 0..ONE_NEAR / 10,
+        key_fee in 0..ONE_NEAR / 100,
+        limits_claims_per_account in any::<bool>(),
+        ft_storage in prop::collection::vec(0..ONE_NEAR / 100, 0..=2),
+        num_nfts in 0..=2usize,
+        mut fc_deposits in prop::collection::vec(0..ONE_NEAR / 10, 0..=2),
+        attempts in prop::collection::vec(attempt(), 0..12),
+    ) -> Scenario {
+        // A drop can have at most 4 assets
+        fc_deposits.truncate(4 - ft_storage.len() - num_nfts);
+        Scenario { num_keys, claims_per_key, balance, drop_fee, key_fee, limits_claims_per_account, ft_storage, num_nfts, fc_deposits, attempts }
+    }
+}
+
+fn ft_contract_id(index: usize) -> AccountId {
+    format!("ft-{}.testnet", index).parse().unwrap()
+}
+
+fn nft_contract_id(index: usize) -> AccountId {
+    format!("nft-{}.testnet", index).parse().unwrap()
+}
+
+fn non_empty<T>(items: Vec<T>) -> Option<Vec<T>> {
+    Some(items).filter(|items| !items.is_empty())
+}
+
+fn transfer_result(succeeded: bool) -> PromiseResult {
+    if succeeded {PromiseResult::Successful(vec![])} else {PromiseResult::Failed}
+}
+
+// Promise results seen by the callbacks of a call, by callback name
+type CallbackResults<'a> = &'a dyn Fn(&str) -> Vec<PromiseResult>;
+
+/// Drives the contract through each call of a drop and the callbacks it schedules on itself. The mocked blockchain
+/// only measures storage within a single call so the storage the contract holds onto is added up across calls.
+struct Run {
+    contract: DropZone,
+    held_bytes: i128,
+}
+
+impl Run {
+    /// Make a call and then run the callbacks it scheduled on the contract
+    fn call<R>(&mut self, call_context: VMContext, callback_results: CallbackResults, f: impl FnOnce(&mut DropZone) -> R) -> R {
+        testing_env!(call_context);
+        let result = self.measured(f);
+        self.run_callbacks(callback_results);
+        result
+    }
+
+    fn run_callbacks(&mut self, callback_results: CallbackResults) {
+        for receipt in get_created_receipts().into_iter().filter(|receipt| receipt.receiver_id == contract_id()) {
+            for action in receipt.actions {
+                if let VmAction::FunctionCall { function_name, args, .. } = action {
+                    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), callback_results(function_name.as_str()));
+                    self.measured(|contract| run_callback(contract, &function_name, &args));
+                    self.run_callbacks(callback_results);
+                }
+            }
+        }
+    }
+
+    fn measured<R>(&mut self, f: impl FnOnce(&mut DropZone) -> R) -> R {
+        let initial_storage = env::storage_usage();
+        let result = f(&mut self.contract);
+        self.held_bytes += env::storage_usage() as i128 - initial_storage as i128;
+        result
+    }
+}
+
+/// Call a callback the contract scheduled on itself with the args it was scheduled with. Other methods are left alone.
+fn run_callback(contract: &mut DropZone, method_name: &str, args: &[u8]) {
+    #[derive(Deserialize)]
+    #[serde(crate = "near_sdk::serde")]
+    struct StorageCheckArgs { public_keys: Vec<PublicKey>, drop_id: DropId, required_deposit: u128, fees: DropCreationFees }
+    #[derive(Deserialize)]
+    #[serde(crate = "near_sdk::serde")]
+    struct SimpleArgs { funder_id: AccountId, balance: U128, storage_used: U128, claim: Option<ClaimLog>, execute: bool }
+    #[derive(Deserialize)]
+    #[serde(crate = "near_sdk::serde")]
+    struct FTArgs { account_id: AccountId, funder_id: AccountId, balance: U128, storage_used: U128, ft_data: FTData, claim: Option<ClaimLog>, execute: bool }
+    #[derive(Deserialize)]
+    #[serde(crate = "near_sdk::serde")]
+    struct NFTArgs {
+        account_id: AccountId,
+        funder_id: AccountId,
+        balance: U128,
+        storage_used: U128,
+        storage_for_longest: U128,
+        nft_sender: AccountId,
+        nft_contract: AccountId,
+        token_id: String,
+        claim: Option<ClaimLog>,
+        execute: bool,
+    }
+    #[derive(Deserialize)]
+    #[serde(crate = "near_sdk::serde")]
+    struct FCArgs {
+        account_id: AccountId,
+        funder_id: AccountId,
+        balance: U128,
+        storage_used: U128,
+        fc_data: FCData,
+        drop_id: U128,
+        use_index: u64,
+        fc_args: Option<String>,
+        claim: Option<ClaimLog>,
+        execute: bool,
+    }
+    #[derive(Deserialize)]
+    #[serde(crate = "near_sdk::serde")]
+    struct FTRefundArgs { drop_id: DropId, num_to_refund: u64, asset_index: u64, ft_contract: AccountId, ft_sender: AccountId, amount: U128 }
+    #[derive(Deserialize)]
+    #[serde(crate = "near_sdk::serde")]
+    struct NFTRefundArgs { drop_id: U128, token_ids: Vec<String>, asset_index: u64, nft_contract: AccountId, nft_sender: AccountId }
+
+    fn parse<T: near_sdk::serde::de::DeserializeOwned>(args: &[u8]) -> T {
+        serde_json::from_slice(args).expect("callback args don't match the callback")
+    }
+
+    match method_name {
+        "resolve_storage_check" => {
+            let StorageCheckArgs { public_keys, drop_id, required_deposit, fees } = parse(args);
+            contract.resolve_storage_check(public_keys, drop_id, required_deposit, fees);
+        },
+        "on_claim_simple" => {
+            let SimpleArgs { funder_id, balance, storage_used, claim, execute } = parse(args);
+            contract.on_claim_simple(funder_id, balance, storage_used, claim, execute);
+        },
+        "on_claim_ft" => {
+            let FTArgs { account_id, funder_id, balance, storage_used, ft_data, claim, execute } = parse(args);
+            contract.on_claim_ft(account_id, funder_id, balance, storage_used, ft_data, claim, execute);
+        },
+        "on_claim_nft" => {
+            let NFTArgs { account_id, funder_id, balance, storage_used, storage_for_longest, nft_sender, nft_contract, token_id, claim, execute } = parse(args);
+            contract.on_claim_nft(account_id, funder_id, balance, storage_used, storage_for_longest, nft_sender, nft_contract, token_id, claim, execute);
+        },
+        "on_claim_fc" => {
+            let FCArgs { account_id, funder_id, balance, storage_used, fc_data, drop_id, use_index, fc_args, claim, execute } = parse(args);
+            contract.on_claim_fc(account_id, funder_id, balance, storage_used, fc_data, drop_id, use_index, fc_args, claim, execute);
+        },
+        "ft_resolve_refund" => {
+            let FTRefundArgs { drop_id, num_to_refund, asset_index, ft_contract, ft_sender, amount } = parse(args);
+            contract.ft_resolve_refund(drop_id, num_to_refund, asset_index, ft_contract, ft_sender, amount);
+        },
+        "nft_resolve_refund" => {
+            let NFTRefundArgs { drop_id, token_ids, asset_index, nft_contract, nft_sender } = parse(args);
+            contract.nft_resolve_refund(drop_id, token_ids, asset_index, nft_contract, nft_sender);
+        },
+        _ => {}
+    }
+}
+
+/// Run a drop through the contract from its creation until it's gone and follow where the funder's $NEAR went.
+/// The FT and NFT transfers always go through. Only the $NEAR transfer a claim's callbacks wait on can fail.
+fn run(scenario: &Scenario) -> Outcome {
+    // Every case runs on the same thread so the storage left behind by the last case has to be cleared
+    near_sdk::mock::with_mocked_blockchain(|blockchain| blockchain.take_storage());
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_fees(Some(U128(scenario.drop_fee)), Some(U128(scenario.key_fee)));
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    let mut run = Run { contract, held_bytes: 0 };
+    let mut outcome = Outcome { fees: scenario.drop_fee + scenario.key_fee * scenario.num_keys as u128, ..Default::default() };
+    let no_callbacks: CallbackResults = &|_: &str| vec![];
+    let successful_callbacks: CallbackResults = &|_: &str| vec![PromiseResult::Successful(vec![])];
+
+    /*
+        Create the drop with every kind of asset the scenario asks for and register the FTs and NFTs for every claim
+    */
+    let total_claims = scenario.claims_per_key * scenario.num_keys as u64;
+    let ft_data: Vec<FTDataConfig> = (0..scenario.ft_storage.len())
+        .map(|index| FTDataConfig { ft_contract: ft_contract_id(index), ft_sender: funder_id(), ft_balance: U128(FT_BALANCE), amount_tiers: None })
+        .collect();
+    let nft_data: Vec<NFTDataConfig> = (0..scenario.num_nfts)
+        .map(|index| NFTDataConfig { nft_sender: funder_id(), nft_contract: nft_contract_id(index), longest_token_id: "token-99".to_string() })
+        .collect();
+    let fc_data: Vec<FCData> = scenario.fc_deposits.iter()
+        .map(|deposit| serde_json::from_value(json!({
+            "methods": [{ "receiver": nft_contract_id(0), "method": "nft_mint", "args": "", "deposit": U128(*deposit) }],
+        })).unwrap())
+        .collect();
+    let config = DropConfig {
+        max_claims_per_key: scenario.claims_per_key,
+        max_claims_per_account: if scenario.limits_claims_per_account {Some(1)} else {None},
+        ..simple_config()
+    };
+    // The FT contracts respond to the storage check with the storage for each FT asset
+    let bounds: Vec<Vec<u8>> = scenario.ft_storage.iter()
+        .map(|min| json!({ "min": U128(*min), "max": null }).to_string().into_bytes())
+        .collect();
+    let storage_check_results: CallbackResults = &|_: &str| bounds.iter().cloned().map(PromiseResult::Successful).collect();
+    let drop_id = run.call(context(funder_id(), 0), storage_check_results, |contract| {
+        contract.create_drop(
+            public_keys()[..scenario.num_keys].to_vec(),
+            U128(scenario.balance),
+            non_empty(ft_data),
+            non_empty(nft_data),
+            non_empty(fc_data),
+            config,
+            None,
+            None,
+            None
+        )
+    });
+
+    for index in 0..scenario.ft_storage.len() {
+        run.call(context(ft_contract_id(index), 0), no_callbacks, |contract| {
+            contract.ft_on_transfer(funder_id(), U128(FT_BALANCE * total_claims as u128), drop_id.to_string());
+        });
+    }
+    for index in 0..scenario.num_nfts {
+        for token in 0..total_claims {
+            run.call(context(nft_contract_id(index), 0), no_callbacks, |contract| {
+                contract.nft_on_transfer(format!("token-{}", token), funder_id(), drop_id.to_string());
+            });
+        }
+    }
+
+    /*
+        Use the keys. Each claim goes to a different account so the claims per account limit is never reached.
+    */
+    let required_gas = run.contract.drop_for_id.get(&drop_id).unwrap().required_gas_attached;
+    let ft_storage: Balance = scenario.ft_storage.iter().sum();
+    let fc_deposits: Balance = scenario.fc_deposits.iter().sum();
+    let mut uses_left = vec![scenario.claims_per_key; scenario.num_keys];
+    for (index, attempt) in scenario.attempts.iter().enumerate() {
+        let (key, prepaid_gas, transfer_succeeds) = match *attempt {
+            Attempt::Claim { key, transfer_succeeds } => (key % scenario.num_keys, required_gas, transfer_succeeds),
+            Attempt::WrongGas { key } => (key % scenario.num_keys, Gas(required_gas.0 + 1), true),
+        };
+        // Keys are deleted once they have no uses left
+        if uses_left[key] == 0 {
+            continue;
+        }
+
+        let pk = public_keys()[key].clone();
+        let allowance = |contract: &DropZone| contract.drop_for_id.get(&drop_id).unwrap().pks.get(&pk).unwrap().allowance;
+        let allowance_before = allowance(&run.contract);
+        // The runtime won't let the key sign the transaction if its allowance can't cover the GAS
+        if allowance_before < prepaid_gas.0 as u128 * YOCTO_PER_GAS {
+            continue;
+        }
+
+        let claimer: AccountId = format!("claimer-{}.testnet", index).parse().unwrap();
+        run.call(claim_context(pk.clone(), prepaid_gas), &|_: &str| vec![transfer_result(transfer_succeeds)], |contract| {
+            contract.claim(claimer, None, None)
+        });
+
+        if let Attempt::WrongGas { .. } = attempt {
+            // The GAS used by a rejected claim is only known once it has run
+            outcome.burned_gas += allowance_before - allowance(&run.contract);
+            continue;
+        }
+
+        uses_left[key] -= 1;
+        outcome.burned_gas += required_gas.0 as u128 * YOCTO_PER_GAS;
+        // The FT storage is always sent. If the claim failed, it goes to the FT sender with the tokens.
+        outcome.delivered += ft_storage;
+        // Claims with no balance have no transfer to wait on
+        if transfer_succeeds || scenario.balance == 0 {
+            outcome.delivered += scenario.balance + fc_deposits;
+        }
+    }
+
+    /*
+        Refund the assets that weren't claimed and delete the keys that are left
+    */
+    if let Some(drop) = run.contract.drop_for_id.get(&drop_id) {
+        let registered: Vec<u64> = drop.assets.iter().enumerate().filter_map(|(index, asset)| match asset {
+            DropAsset::FT(data) if data.num_claims_registered > 0 => Some(index as u64),
+            DropAsset::NFT(data) if data.num_claims_registered > 0 => Some(index as u64),
+            _ => None
+        }).collect();
+        for index in registered {
+            run.call(context(funder_id(), 0), successful_callbacks, |contract| contract.refund_assets(drop_id, None, Some(index)));
+        }
+        run.call(context(funder_id(), 0), no_callbacks, |contract| contract.delete_keys(None, drop_id));
+    }
+    assert!(run.contract.drop_for_id.get(&drop_id).is_none(), "drop should be gone");
+
+    assert!(run.held_bytes >= 0, "contract released {} bytes more than it used", -run.held_bytes);
+    outcome.held_storage = run.held_bytes as Balance * STORAGE_BYTE_COST;
+    outcome.spent = balance_before - run.contract.get_user_balance(funder_id()).0;
+    outcome
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn funders_pay_for_fees_gas_what_was_sent_and_storage_held(scenario in scenario()) {
+        let outcome = run(&scenario);
+        prop_assert_eq!(outcome.spent, outcome.fees + outcome.burned_gas + outcome.delivered + outcome.held_storage, "{:?}", outcome);
+    }
+
+    #[test]
+    fn unclaimed_drops_refund_everything_but_fees(scenario in scenario()) {
+        let outcome = run(&Scenario { attempts: vec![], ..scenario });
+        prop_assert_eq!(outcome.spent, outcome.fees + outcome.held_storage, "{:?}", outcome);
+        prop_assert_eq!(outcome.burned_gas + outcome.delivered, 0);
+    }
+
+    #[test]
+    fn rejected_claims_only_burn_gas(scenario in scenario(), keys in prop::collection::vec(0..3usize, 1..6)) {
+        let attempts = keys.into_iter().map(|key| Attempt::WrongGas { key }).collect();
+        let outcome = run(&Scenario { attempts, ..scenario });
+        prop_assert_eq!(outcome.spent, outcome.fees + outcome.burned_gas + outcome.held_storage, "{:?}", outcome);
+        prop_assert_eq!(outcome.delivered, 0);
+    }
+}
+
+proptest! {
+    #[test]
+    fn create_drop_costs_one_drop_fee_more_than_adding_keys(scenario in scenario()) {
+        let inputs = KeyDepositInputs {
+            num_keys: scenario.num_keys as u128,
+            claims_per_key: scenario.claims_per_key,
+            drop_fee: scenario.drop_fee,
+            key_fees: scenario.key_fee * scenario.num_keys as u128,
+            allowance_per_key: key_allowance(ATTACHED_GAS_FROM_WALLET, scenario.claims_per_key, YOCTO_PER_GAS),
+            ..Default::default()
+        };
+        let created = required_deposit(&inputs);
+        let added = required_deposit(&KeyDepositInputs { drop_fee: 0, ..inputs });
+        prop_assert_eq!(created.total() - added.total(), scenario.drop_fee);
+        prop_assert_eq!(created.fees, scenario.drop_fee + tiered_key_fees(scenario.key_fee, &[], 0, scenario.num_keys as u64));
+    }
+
+    #[test]
+    fn tiered_key_fees_charge_each_key_its_tier(
+        key_fee in 0..ONE_NEAR / 10,
+        mut min_keys in prop::collection::vec(0..100u64, 0..5),
+        keys_before in 0..100u64,
+        num_keys in 0..100u64,
+        split in 0..100u64,
+    ) {
+        min_keys.sort_unstable();
+        let tiers: Vec<VolumeTier> = min_keys.iter().enumerate()
+            .map(|(i, min_keys)| VolumeTier { min_keys: *min_keys, key_fee: U128(key_fee / (i as u128 + 2)) })
+            .collect();
+        let fee_for_key = |key: u64| tiers.iter().rev().find(|tier| tier.min_keys <= key).map(|tier| tier.key_fee.0).unwrap_or(key_fee);
+
+        let expected: Balance = (keys_before..keys_before + num_keys).map(fee_for_key).sum();
+        prop_assert_eq!(tiered_key_fees(key_fee, &tiers, keys_before, num_keys), expected);
+
+        // Adding keys in two batches costs the same as adding them all at once
+        let split = split.min(num_keys);
+        prop_assert_eq!(
+            tiered_key_fees(key_fee, &tiers, keys_before, split) + tiered_key_fees(key_fee, &tiers, keys_before + split, num_keys - split),
+            expected
+        );
+    }
+}
+
+#[test]
+fn failed_claim_charge_includes_panic_offset() {
+    assert_eq!(failed_claim_charge(Gas(0), YOCTO_PER_GAS), gas_cost(GAS_FOR_PANIC_OFFSET, YOCTO_PER_GAS));
+}
+
+#[test]
+fn allowance_covers_every_claim() {
+    for claims_per_key in 1..10 {
+        let allowance = key_allowance(ATTACHED_GAS_FROM_WALLET, claims_per_key, YOCTO_PER_GAS);
+        assert!(allowance >= claim_charge(ATTACHED_GAS_FROM_WALLET, YOCTO_PER_GAS) * claims_per_key as u128);
+    }
+}
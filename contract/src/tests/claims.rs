@@ -0,0 +1,71 @@
+use super::*;
+use near_sdk::{PromiseResult, RuntimeFeesConfig, VMConfig};
+
+const BALANCE: Balance = ONE_NEAR / 100;
+
+/// Create a drop with two keys that can only be claimed once in total and once per account, then claim the first key
+fn setup_claimed_drop() -> (DropZone, DropId, ClaimLog) {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    let config = DropConfig { max_total_claims: Some(1), max_claims_per_account: Some(1), ..simple_config() };
+    let drop_id = contract.create_drop(public_keys()[..2].to_vec(), U128(BALANCE), None, None, None, config, None, None, None);
+
+    testing_env!(claim_context(public_keys()[0].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.claim(claimer_id(), None, None);
+    // The claim isn't logged until the transfer has been resolved
+    assert!(!get_logs().iter().any(|log| log.contains("\"event\":\"claim\"")));
+    assert_eq!(contract.get_drop_information(drop_id).total_claims, 1);
+    assert_eq!(contract.get_claims_for_account(drop_id, claimer_id()), 1);
+
+    let claim = ClaimLog {
+        drop_id: U128(drop_id),
+        public_key: public_keys()[0].clone(),
+        account_id: claimer_id(),
+        new_account: false,
+        use_index: 0,
+        balance: U128(BALANCE),
+        assets: vec![],
+    };
+    (contract, drop_id, claim)
+}
+
+fn resolve_claim(contract: &mut DropZone, claim: ClaimLog, result: PromiseResult) -> bool {
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![result]);
+    contract.on_claim_simple(funder_id(), U128(BALANCE), U128(0), Some(claim), false)
+}
+
+#[test]
+fn failed_transfers_dont_count_towards_claim_limits() {
+    let (mut contract, drop_id, claim) = setup_claimed_drop();
+    assert!(!resolve_claim(&mut contract, claim, PromiseResult::Failed));
+    assert!(get_logs().iter().any(|log| log.contains("\"event\":\"claim_failure\"") && log.contains("transfer failed")));
+    assert_eq!(contract.get_drop_information(drop_id).total_claims, 0);
+    assert_eq!(contract.get_claims_for_account(drop_id, claimer_id()), 0);
+
+    // The limits are free again so the claimer can use the drop's other key
+    testing_env!(claim_context(public_keys()[1].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.claim(claimer_id(), None, None);
+    assert!(!get_logs().iter().any(|log| log.contains("\"event\":\"claim_failure\"")));
+}
+
+#[test]
+fn successful_transfers_keep_counting_towards_claim_limits() {
+    let (mut contract, drop_id, claim) = setup_claimed_drop();
+    assert!(resolve_claim(&mut contract, claim, PromiseResult::Successful(vec![])));
+    assert!(get_logs().iter().any(|log| log.contains("\"event\":\"claim\"")));
+    assert_eq!(contract.get_drop_information(drop_id).total_claims, 1);
+
+    testing_env!(claim_context(public_keys()[1].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.claim(claimer_id(), None, None);
+    assert!(get_logs().iter().any(|log| log.contains("max total claims reached")));
+}
+
+#[test]
+fn failed_transfers_only_refund_the_balance() {
+    let (mut contract, _, claim) = setup_claimed_drop();
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    assert!(!resolve_claim(&mut contract, claim, PromiseResult::Failed));
+
+    // The storage prepaid for recording the claiming account was refunded by the claim so only the balance comes back
+    assert_eq!(contract.get_user_balance(funder_id()).0, balance_before + BALANCE);
+}
@@ -0,0 +1,76 @@
+use super::*;
+use crate::stage1::estimates::JsonCostEstimate;
+
+/// How far the estimated contract storage can be from what's measured when the call is made
+const STORAGE_TOLERANCE_PERCENT: u128 = 10;
+
+/// Assert the estimate matches what was taken from the funder's balance. Only the contract storage is estimated.
+fn assert_estimate_matches(estimate: &JsonCostEstimate, charged: Balance) {
+    let measured_storage = charged - (estimate.total.0 - estimate.contract_storage.0);
+    let difference = measured_storage.abs_diff(estimate.contract_storage.0);
+    assert!(
+        difference * 100 <= measured_storage * STORAGE_TOLERANCE_PERCENT,
+        "estimated storage {} is too far from the measured storage {}", estimate.contract_storage.0, measured_storage
+    );
+}
+
+fn funder_balance(contract: &DropZone) -> Balance {
+    contract.get_user_balance(funder_id()).0
+}
+
+#[test]
+fn create_drop_estimate_matches_deposit() {
+    let pks = public_keys();
+    let mut contract = setup();
+    let config = DropConfig { max_claims_per_key: 2, max_claims_per_account: Some(1), ..simple_config() };
+
+    testing_env!(context(funder_id(), 0));
+    let estimate = contract.estimate_create_drop_cost(funder_id(), 2, U128(ONE_NEAR / 100), None, None, None, config.clone(), None, None, None);
+    assert_eq!(estimate.drop_fee.0, DROP_CREATION_FEE);
+    assert_eq!(estimate.key_fees.0, 2 * KEY_ADDITION_FEE);
+    assert_eq!(estimate.balance.0, 4 * ONE_NEAR / 100);
+    assert_eq!(estimate.access_key_storage.0, 4 * ACCESS_KEY_STORAGE);
+    assert!(estimate.ft_storage_included);
+
+    let balance_before = funder_balance(&contract);
+    contract.create_drop(vec![pks[0].clone(), pks[1].clone()], U128(ONE_NEAR / 100), None, None, None, config, None, None, None);
+    assert_estimate_matches(&estimate, balance_before - funder_balance(&contract));
+}
+
+#[test]
+fn add_to_drop_estimate_matches_deposit() {
+    let pks = public_keys();
+    let mut contract = setup();
+
+    testing_env!(context(funder_id(), 0));
+    contract.create_drop(vec![pks[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+
+    testing_env!(context(funder_id(), 0));
+    let estimate = contract.estimate_add_to_drop_cost(0, 2, None, None);
+    assert_eq!(estimate.drop_fee.0, 0);
+    assert_eq!(estimate.key_fees.0, 2 * KEY_ADDITION_FEE);
+
+    let balance_before = funder_balance(&contract);
+    contract.add_to_drop(vec![pks[1].clone(), pks[2].clone()], 0, None, None, None);
+    assert_estimate_matches(&estimate, balance_before - funder_balance(&contract));
+}
+
+#[test]
+fn ft_storage_is_only_included_when_passed_in() {
+    let contract = setup();
+    let ft_data = vec![FTDataConfig {
+        ft_contract: "ft.testnet".parse().unwrap(),
+        ft_sender: funder_id(),
+        ft_balance: U128(100),
+        amount_tiers: None,
+    }];
+
+    let without = contract.estimate_create_drop_cost(funder_id(), 3, U128(0), Some(ft_data.clone()), None, None, simple_config(), None, None, None);
+    assert!(!without.ft_storage_included);
+    assert_eq!(without.ft_storage.0, 0);
+
+    let with = contract.estimate_create_drop_cost(funder_id(), 3, U128(0), Some(ft_data), None, None, simple_config(), None, None, Some(vec![U128(ONE_NEAR / 100)]));
+    assert!(with.ft_storage_included);
+    assert_eq!(with.ft_storage.0, 3 * ONE_NEAR / 100);
+    assert_eq!(with.total.0 - without.total.0, 3 * ONE_NEAR / 100);
+}
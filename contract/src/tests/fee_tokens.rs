@@ -0,0 +1,156 @@
+use super::*;
+use near_sdk::{PromiseResult, RuntimeFeesConfig, VMConfig};
+
+// 0.001 $NEAR per token so 1000 tokens are worth 1 $NEAR
+const RATE: Balance = ONE_NEAR / 1000;
+
+fn token_id() -> AccountId {
+    "token.testnet".parse().unwrap()
+}
+
+fn oracle_id() -> AccountId {
+    "oracle.testnet".parse().unwrap()
+}
+
+fn fee_payment_msg() -> String {
+    near_sdk::serde_json::json!({ "v1": { "pay_fees": {} } }).to_string()
+}
+
+/// Deploy the contract with the token whitelisted at a fixed rate
+fn setup_fixed_rate() -> DropZone {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_fee_token(token_id(), Some(FeeToken { rate: Some(U128(RATE)), oracle_id: None }));
+    contract
+}
+
+/// Send tokens from the funder to pay for fees
+fn pay_fees(contract: &mut DropZone, amount: Balance) -> PromiseOrValue<U128> {
+    testing_env!(context(token_id(), 0));
+    contract.ft_on_transfer(funder_id(), U128(amount), fee_payment_msg())
+}
+
+#[test]
+fn fixed_rate_payments_are_credited() {
+    let mut contract = setup_fixed_rate();
+    assert!(matches!(pay_fees(&mut contract, 1000), PromiseOrValue::Value(U128(0))));
+
+    // The storage for the credit record comes out of the first payment
+    let credit = contract.get_fee_credit(funder_id()).0;
+    assert!(credit < ONE_NEAR && credit > 0);
+    assert_eq!(contract.get_token_fees_collected(token_id()).0, 1000);
+    assert!(get_logs().iter().any(|log| log.contains("\"event\":\"token_fee_payment\"")));
+
+    // Later payments are credited in full
+    pay_fees(&mut contract, 1000);
+    assert_eq!(contract.get_fee_credit(funder_id()).0, credit + ONE_NEAR);
+    assert_eq!(contract.get_token_fees_collected(token_id()).0, 2000);
+}
+
+#[test]
+fn fee_credit_pays_for_drop_fees() {
+    let mut contract = setup_fixed_rate();
+    // 2 $NEAR of credit less the storage for the credit record is enough to cover both fees
+    pay_fees(&mut contract, 2000);
+    let credit = contract.get_fee_credit(funder_id()).0;
+
+    testing_env!(context(funder_id(), 0));
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    let estimate = contract.estimate_create_drop_cost(funder_id(), 1, U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    assert_eq!(estimate.fee_credit.0, DROP_CREATION_FEE + KEY_ADDITION_FEE);
+
+    contract.create_drop(vec![public_keys()[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    assert_eq!(contract.get_fee_credit(funder_id()).0, credit - DROP_CREATION_FEE - KEY_ADDITION_FEE);
+    assert_eq!(contract.fees_collected, 0);
+    assert!(balance_before - contract.get_user_balance(funder_id()).0 <= estimate.total.0 + ONE_NEAR / 1000);
+}
+
+#[test]
+fn fees_past_the_credit_are_paid_in_near() {
+    let mut contract = setup_fixed_rate();
+    pay_fees(&mut contract, 100);
+    let credit = contract.get_fee_credit(funder_id()).0;
+    assert!(credit < DROP_CREATION_FEE);
+
+    testing_env!(context(funder_id(), 0));
+    contract.create_drop(vec![public_keys()[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    assert_eq!(contract.get_fee_credit(funder_id()).0, 0);
+    assert_eq!(contract.fees_collected, DROP_CREATION_FEE + KEY_ADDITION_FEE - credit);
+}
+
+#[test]
+fn failed_ft_storage_checks_give_the_fee_credit_back() {
+    let mut contract = setup_fixed_rate();
+    pay_fees(&mut contract, 1000);
+    let credit = contract.get_fee_credit(funder_id()).0;
+
+    testing_env!(context(funder_id(), 0));
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    let ft_data = FTDataConfig { ft_contract: "ft.testnet".parse().unwrap(), ft_sender: funder_id(), ft_balance: U128(100), amount_tiers: None };
+    let drop_id = contract.create_drop(vec![public_keys()[0].clone()], U128(0), Some(vec![ft_data]), None, None, simple_config(), None, None, None);
+    let used = credit - contract.get_fee_credit(funder_id()).0;
+    let required_deposit = balance_before - contract.get_user_balance(funder_id()).0;
+    assert!(used > 0);
+
+    // The credit goes back to the funder and only what they paid in $NEAR is refunded to their balance
+    let fees = DropCreationFees { fees: U128(0), fee_credit: U128(used), referrer: None };
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Failed]);
+    assert!(!contract.resolve_storage_check(vec![public_keys()[0].clone()], drop_id, required_deposit, fees));
+    assert_eq!(contract.get_fee_credit(funder_id()).0, credit);
+    assert_eq!(contract.get_user_balance(funder_id()).0, balance_before);
+}
+
+#[test]
+fn oracle_rates_are_credited_once_resolved() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_fee_token(token_id(), Some(FeeToken { rate: None, oracle_id: Some(oracle_id()) }));
+    assert!(matches!(pay_fees(&mut contract, 1000), PromiseOrValue::Promise(_)));
+    assert_eq!(contract.get_fee_credit(funder_id()).0, 0);
+
+    let rate = near_sdk::serde_json::to_vec(&U128(RATE)).unwrap();
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Successful(rate)]);
+    assert_eq!(contract.resolve_fee_payment(token_id(), funder_id(), U128(1000)).0, 0);
+    assert!(contract.get_fee_credit(funder_id()).0 > 0);
+    assert_eq!(contract.get_token_fees_collected(token_id()).0, 1000);
+}
+
+#[test]
+fn failed_oracle_calls_refund_the_tokens() {
+    let mut contract = setup();
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Failed]);
+    assert_eq!(contract.resolve_fee_payment(token_id(), funder_id(), U128(1000)).0, 1000);
+    assert_eq!(contract.get_fee_credit(funder_id()).0, 0);
+    assert_eq!(contract.get_token_fees_collected(token_id()).0, 0);
+}
+
+#[test]
+fn tokens_that_arent_whitelisted_are_refunded() {
+    let mut contract = setup();
+    assert!(matches!(pay_fees(&mut contract, 1000), PromiseOrValue::Value(U128(1000))));
+    assert_eq!(contract.get_fee_credit(funder_id()).0, 0);
+    assert_eq!(contract.get_token_fees_collected(token_id()).0, 0);
+}
+
+#[test]
+#[should_panic(expected = "fee token must have either a rate or an oracle")]
+fn fee_tokens_need_a_rate_or_an_oracle() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_fee_token(token_id(), Some(FeeToken { rate: Some(U128(RATE)), oracle_id: Some(oracle_id()) }));
+}
+
+#[test]
+fn token_fees_are_withdrawn_per_token() {
+    let mut contract = setup_fixed_rate();
+    pay_fees(&mut contract, 1000);
+
+    testing_env!(context(funder_id(), 0));
+    contract.withdraw_token_fees(token_id(), funder_id());
+    assert_eq!(contract.get_token_fees_collected(token_id()).0, 0);
+
+    // A failed transfer adds the fees back to the ledger
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Failed]);
+    assert!(!contract.on_withdraw_token_fees(token_id(), U128(1000), funder_id()));
+    assert_eq!(contract.get_token_fees_collected(token_id()).0, 1000);
+}
@@ -0,0 +1,103 @@
+use super::*;
+use near_sdk::{PromiseResult, RuntimeFeesConfig, VMConfig};
+
+fn partner_id() -> AccountId {
+    "partner.testnet".parse().unwrap()
+}
+
+/// Fees collected by creating a simple drop with the given keys as the funder
+fn fees_for_drop(contract: &mut DropZone, pks: Vec<PublicKey>) -> Balance {
+    let fees_before = contract.fees_collected;
+    testing_env!(context(funder_id(), 0));
+    contract.create_drop(pks, U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    contract.fees_collected - fees_before
+}
+
+#[test]
+fn global_fees_are_charged_by_default() {
+    let mut contract = setup();
+    assert_eq!(fees_for_drop(&mut contract, vec![public_keys()[0].clone()]), DROP_CREATION_FEE + KEY_ADDITION_FEE);
+
+    testing_env!(context(funder_id(), 0));
+    contract.set_fees(Some(U128(3)), Some(U128(2)));
+    assert_eq!(fees_for_drop(&mut contract, vec![public_keys()[1].clone()]), 3 + 2);
+}
+
+#[test]
+fn funder_overrides_replace_the_global_fees() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_funder_fees(funder_id(), Some(FunderFees { drop_fee: Some(U128(5)), key_fee: None, exempt: false }));
+    assert_eq!(fees_for_drop(&mut contract, vec![public_keys()[0].clone()]), 5 + KEY_ADDITION_FEE);
+
+    testing_env!(context(funder_id(), 0));
+    contract.set_funder_fees(funder_id(), Some(FunderFees { drop_fee: None, key_fee: None, exempt: true }));
+    assert_eq!(fees_for_drop(&mut contract, vec![public_keys()[1].clone()]), 0);
+
+    testing_env!(context(funder_id(), 0));
+    contract.set_funder_fees(funder_id(), None);
+    assert_eq!(fees_for_drop(&mut contract, vec![public_keys()[2].clone()]), DROP_CREATION_FEE + KEY_ADDITION_FEE);
+}
+
+#[test]
+fn key_fee_drops_once_a_tier_is_reached() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_volume_tiers(vec![VolumeTier { min_keys: 2, key_fee: U128(1) }]);
+
+    // The first two keys are charged the base fee and the third is charged the tier's fee
+    let drop_id = contract.create_drop(public_keys()[..2].to_vec(), U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    assert_eq!(contract.get_fees_for_funder(funder_id(), None).key_fee.0, 1);
+    let fees_before = contract.fees_collected;
+    testing_env!(context(funder_id(), 0));
+    contract.add_to_drop(vec![public_keys()[2].clone()], drop_id, None, None, None);
+    assert_eq!(contract.fees_collected - fees_before, 1);
+    assert_eq!(contract.get_fees_for_funder(funder_id(), None).keys_added, 3);
+}
+
+#[test]
+fn reverted_drops_dont_count_towards_volume_tiers() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.create_drop(vec![public_keys()[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+
+    testing_env!(context(funder_id(), 0));
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    let ft_data = FTDataConfig { ft_contract: "ft.testnet".parse().unwrap(), ft_sender: funder_id(), ft_balance: U128(100), amount_tiers: None };
+    let pks = public_keys()[1..].to_vec();
+    let drop_id = contract.create_drop(pks.clone(), U128(0), Some(vec![ft_data]), None, None, simple_config(), None, None, None);
+    assert_eq!(contract.get_fees_for_funder(funder_id(), None).keys_added, 3);
+
+    let required_deposit = balance_before - contract.get_user_balance(funder_id()).0;
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Failed]);
+    assert!(!contract.resolve_storage_check(pks, drop_id, required_deposit, no_creation_fees()));
+    assert_eq!(contract.get_fees_for_funder(funder_id(), None).keys_added, 1);
+}
+
+#[test]
+fn multipliers_apply_to_each_drop_type() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_fee_multipliers(FeeMultipliers { simple: 5_000, ft: 10_000, nft: 10_000, fc: 20_000 });
+
+    assert_eq!(fees_for_drop(&mut contract, vec![public_keys()[0].clone()]), (DROP_CREATION_FEE + KEY_ADDITION_FEE) / 2);
+    let fc_fees = contract.get_fees_for_funder(funder_id(), Some(DropType::FC));
+    assert_eq!(fc_fees.drop_fee.0, DROP_CREATION_FEE * 2);
+    assert_eq!(fc_fees.key_fee.0, KEY_ADDITION_FEE * 2);
+}
+
+#[test]
+#[should_panic(expected = "volume tier fees can't increase")]
+fn volume_tier_fees_cant_increase() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_volume_tiers(vec![VolumeTier { min_keys: 2, key_fee: U128(1) }, VolumeTier { min_keys: 4, key_fee: U128(2) }]);
+}
+
+#[test]
+#[should_panic(expected = "predecessor doesn't have the operations role")]
+fn only_operations_can_set_funder_fees() {
+    let mut contract = setup();
+    testing_env!(context(partner_id(), 0));
+    contract.set_funder_fees(partner_id(), Some(FunderFees { drop_fee: None, key_fee: None, exempt: true }));
+}
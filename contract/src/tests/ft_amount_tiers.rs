@@ -0,0 +1,134 @@
+use super::*;
+use near_sdk::serde_json::json;
+use near_sdk::{PromiseResult, RuntimeFeesConfig, VMConfig};
+
+fn ft_contract_id() -> AccountId {
+    "ft.testnet".parse().unwrap()
+}
+
+fn tier(amount: Balance, num_claims: u64) -> FTAmountTier {
+    FTAmountTier { amount: U128(amount), num_claims }
+}
+
+fn ft_data(contract: &DropZone, drop_id: DropId) -> FTData {
+    match &contract.drop_for_id.get(&drop_id).unwrap().assets[0] {
+        DropAsset::FT(data) => data.clone(),
+        _ => panic!("expected an FT asset"),
+    }
+}
+
+/// Create a drop with three keys where two claims send 10 FTs and one sends 50, then resolve its storage check
+fn setup_tiered_drop() -> (DropZone, DropId) {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    let ft_data = FTDataConfig {
+        ft_contract: ft_contract_id(),
+        ft_sender: funder_id(),
+        ft_balance: U128(0),
+        amount_tiers: Some(vec![tier(10, 2), tier(50, 1)]),
+    };
+    let drop_id = contract.create_drop(public_keys(), U128(0), Some(vec![ft_data]), None, None, simple_config(), None, None, None);
+
+    let bounds = json!({ "min": U128(ONE_NEAR / 800), "max": null }).to_string().into_bytes();
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Successful(bounds)]);
+    assert!(contract.resolve_storage_check(public_keys(), drop_id, 0, no_creation_fees()));
+    (contract, drop_id)
+}
+
+fn register(contract: &mut DropZone, drop_id: DropId, amount: Balance) -> Balance {
+    testing_env!(context(ft_contract_id(), 0));
+    match contract.ft_on_transfer(funder_id(), U128(amount), drop_id.to_string()) {
+        PromiseOrValue::Value(unused) => unused.0,
+        PromiseOrValue::Promise(_) => panic!("expected a value"),
+    }
+}
+
+#[test]
+fn tiers_are_picked_by_the_claims_they_have_left() {
+    let mut data = FTData {
+        ft_contract: ft_contract_id(),
+        ft_sender: funder_id(),
+        ft_balance: U128(0),
+        ft_storage: U128(0),
+        num_claims_registered: 10,
+        amount_tiers: Some(vec![tier(10, 7), tier(50, 2), tier(500, 1)]),
+    };
+    assert_eq!(data.take_claim_amount(6).0, 10);
+    // Only 6 claims of 10 are left so the same number now lands on the tiers after it
+    assert_eq!(data.take_claim_amount(6).0, 50);
+    assert_eq!(data.take_claim_amount(6).0, 50);
+    assert_eq!(data.take_claim_amount(6).0, 500);
+    assert_eq!(data.amount_tiers_total(), Some((6, 60)));
+}
+
+#[test]
+fn draws_with_the_same_seed_get_different_numbers() {
+    let mut contract = setup();
+    let pk = public_keys()[0].clone();
+    let first = contract.internal_random_u64(0, &pk, 0, 0);
+    assert_ne!(contract.internal_random_u64(0, &pk, 0, 0), first);
+    assert_ne!(contract.internal_random_u64(1, &pk, 0, 0), first);
+    assert_eq!(contract.random_nonce, 3);
+}
+
+#[test]
+fn registration_needs_the_sum_of_the_table() {
+    let (mut contract, drop_id) = setup_tiered_drop();
+    assert_eq!(register(&mut contract, drop_id, 69), 69);
+
+    // Every claim is registered at once and anything past the sum is refunded
+    assert_eq!(register(&mut contract, drop_id, 100), 30);
+    assert_eq!(ft_data(&contract, drop_id).num_claims_registered, 3);
+    assert_eq!(register(&mut contract, drop_id, 70), 70);
+}
+
+#[test]
+fn claims_count_down_the_tiers() {
+    let (mut contract, drop_id) = setup_tiered_drop();
+    register(&mut contract, drop_id, 70);
+
+    for (claims_made, pk) in public_keys().into_iter().enumerate() {
+        testing_env!(claim_context(pk, ATTACHED_GAS_FROM_WALLET));
+        contract.claim(claimer_id(), None, None);
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"claim\"")));
+
+        if claims_made < 2 {
+            let data = ft_data(&contract, drop_id);
+            assert_eq!(data.amount_tiers_total().unwrap().0, 2 - claims_made as u64);
+            assert_eq!(data.num_claims_registered, 2 - claims_made as u64);
+        }
+    }
+    assert!(contract.drop_for_id.get(&drop_id).is_none());
+}
+
+#[test]
+#[should_panic(expected = "amount tiers must cover every claim in the drop")]
+fn tiers_must_cover_every_claim() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    let ft_data = FTDataConfig {
+        ft_contract: ft_contract_id(),
+        ft_sender: funder_id(),
+        ft_balance: U128(0),
+        amount_tiers: Some(vec![tier(10, 1), tier(50, 1)]),
+    };
+    contract.create_drop(public_keys(), U128(0), Some(vec![ft_data]), None, None, simple_config(), None, None, None);
+}
+
+#[test]
+#[should_panic(expected = "FT assets with amount tiers can only be refunded in full")]
+fn tiered_assets_are_refunded_in_full() {
+    let (mut contract, drop_id) = setup_tiered_drop();
+    register(&mut contract, drop_id, 70);
+
+    testing_env!(context(funder_id(), 0));
+    contract.refund_assets(drop_id, Some(1), None);
+}
+
+#[test]
+#[should_panic(expected = "cannot add keys to a drop with FT amount tiers")]
+fn keys_cant_be_added_to_tiered_drops() {
+    let (mut contract, drop_id) = setup_tiered_drop();
+    testing_env!(context(funder_id(), 0));
+    contract.add_to_drop(vec!["ed25519:28Fedz5vi3bjgQg4TYRUBzvQvfmcJaZkimtxcwsS6pBZ".parse().unwrap()], drop_id, None, None, None);
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use near_sdk::{serde_json, RuntimeFeesConfig, VMConfig, VMContext};
+
+use super::*;
+
+/// Where the recorded gas for each path is kept. It's committed and only rewritten when `UPDATE_GAS_BASELINE=1` is set.
+const BASELINE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/gas-baseline.json");
+/// How much more gas than the baseline a path can use before it's considered a regression
+const TOLERANCE_PERCENT: u64 = 1;
+
+/// Name the gas is recorded under for the features the contract was built with
+fn feature_set() -> &'static str {
+    if cfg!(feature = "debug-logs") {
+        "debug-logs"
+    } else {
+        "default"
+    }
+}
+
+type Baseline = BTreeMap<String, BTreeMap<String, u64>>;
+
+/// Whether the baseline should be recorded again instead of checked
+fn updating_baseline() -> bool {
+    std::env::var("UPDATE_GAS_BASELINE").map(|value| value == "1").unwrap_or(false)
+}
+
+/// Read the committed baseline. A missing or unreadable baseline is a failure, not a reason to record a new one.
+fn read_baseline() -> Baseline {
+    let contents = fs::read_to_string(BASELINE_PATH)
+        .unwrap_or_else(|_| panic!("no gas baseline at {}. Run the tests with UPDATE_GAS_BASELINE=1 to record it", BASELINE_PATH));
+    serde_json::from_str(&contents).expect("unable to parse gas baseline")
+}
+
+/// Replace the baseline in one step so tests reading it never see a partially written file
+fn write_baseline(baseline: &Baseline) {
+    let contents = serde_json::to_string_pretty(baseline).expect("unable to serialize gas baseline");
+    let tmp_path = format!("{}.{}.tmp", BASELINE_PATH, feature_set());
+    fs::write(&tmp_path, contents + "\n").expect("unable to write gas baseline");
+    fs::rename(&tmp_path, BASELINE_PATH).expect("unable to write gas baseline");
+}
+
+/// Set the context for a measured call. The mocked receipt fees push creating an account and claiming past the wallet's GAS
+/// so they're left out. Only the contract's own execution and the static GAS it attaches are measured.
+fn measured_context(context: VMContext) {
+    testing_env!(context, VMConfig::test(), RuntimeFeesConfig::free());
+}
+
+/// Run every claim path and record how much gas each one uses
+fn measure_paths() -> BTreeMap<String, u64> {
+    let mut gas_used = BTreeMap::new();
+    let mut record = |path: &str| {
+        assert_only_events_logged();
+        gas_used.insert(path.to_string(), env::used_gas().0);
+    };
+    let pks = public_keys();
+
+    let mut contract = setup();
+    record("add_to_balance");
+
+    measured_context(context(funder_id(), 0));
+    contract.create_drop(vec![pks[0].clone(), pks[1].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    record("create_drop");
+
+    measured_context(context(funder_id(), 0));
+    contract.add_to_drop(vec![pks[2].clone()], 0, None, None, None);
+    record("add_to_drop");
+
+    measured_context(claim_context(pks[0].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.claim(claimer_id(), None, None);
+    record("claim");
+
+    measured_context(claim_context(pks[1].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.create_account_and_claim("new-account.testnet".parse().unwrap(), pks[1].clone(), None, None);
+    record("create_account_and_claim");
+
+    measured_context(context(funder_id(), 0));
+    contract.delete_keys(Some(vec![pks[2].clone()]), 0);
+    record("delete_keys");
+
+    measured_context(context(funder_id(), 0));
+    contract.withdraw_from_balance();
+    record("withdraw_from_balance");
+
+    gas_used
+}
+
+#[test]
+fn gas_regression() {
+    let gas_used = measure_paths();
+
+    if updating_baseline() {
+        let mut baseline = fs::read_to_string(BASELINE_PATH)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Baseline>(&contents).ok())
+            .unwrap_or_default();
+        baseline.insert(feature_set().to_string(), gas_used);
+        write_baseline(&baseline);
+        return
+    }
+
+    let baseline = read_baseline();
+    let recorded = baseline.get(feature_set())
+        .unwrap_or_else(|| panic!("no gas baseline for the {} build. Run the tests with UPDATE_GAS_BASELINE=1 to record it", feature_set()));
+
+    let mut regressions = vec![];
+    for (path, gas) in &gas_used {
+        println!("{} ({}): {} gas", path, feature_set(), gas);
+        match recorded.get(path) {
+            Some(expected) if *gas > expected + expected * TOLERANCE_PERCENT / 100 => {
+                regressions.push(format!("{} used {} gas. Baseline is {}", path, gas, expected));
+            },
+            Some(_) => {},
+            None => regressions.push(format!("{} has no baseline", path)),
+        }
+    }
+
+    assert!(regressions.is_empty(), "gas regressions ({}):\n{}", feature_set(), regressions.join("\n"));
+}
+
+#[test]
+#[cfg(feature = "debug-logs")]
+fn release_logs_are_cheaper() {
+    // The baseline is being rewritten by `gas_regression`, so there's nothing committed to compare against
+    if updating_baseline() {
+        return
+    }
+    let gas_used = measure_paths();
+
+    // Without the debug-logs feature, only structured events are logged and claims shouldn't pay for anything else.
+    // With it, the diagnostic logs must cost extra gas on every claim path compared to the recorded default build.
+    let baseline = read_baseline();
+    let default_gas = baseline.get("default").expect("no gas baseline for the default build");
+    for path in ["claim", "create_account_and_claim"] {
+        let release = default_gas.get(path).unwrap_or_else(|| panic!("{} has no baseline for the default build", path));
+        let debug = gas_used[path];
+        assert!(debug > *release, "{} should use more gas with debug logs. Debug: {} Release: {}", path, debug, release);
+    }
+}
@@ -0,0 +1,231 @@
+use super::*;
+
+fn migration_keys() -> Vec<PublicKey> {
+    vec![
+        "ed25519:28Fedz5vi3bjgQg4TYRUBzvQvfmcJaZkimtxcwsS6pBZ".parse().unwrap(),
+        "ed25519:H9HMndtZkfL2BjUfU1eSwzL5smrfC1iTbSC2hJLNhQaL".parse().unwrap(),
+        "ed25519:Hk2vQJy8KGDMppmBwVwF89S89avhbB5EmXYdrt4iDGQK".parse().unwrap(),
+        "ed25519:54i7h1Pxpt3zSi4V3C59ohdEeJ9YEGA9THzJgnr1NYEv".parse().unwrap(),
+    ]
+}
+
+fn ft_contract_id() -> AccountId {
+    "ft.testnet".parse().unwrap()
+}
+
+fn nft_contract_id() -> AccountId {
+    "nft.testnet".parse().unwrap()
+}
+
+// Allowance given to every key of the baseline drops
+const BASELINE_ALLOWANCE: Balance = ONE_NEAR / 10;
+
+/// Key in the baseline drop map for a drop ID
+fn legacy_drop_key(drop_id: DropId) -> Vec<u8> {
+    let mut key = StorageKey::DropsForId.try_to_vec().unwrap();
+    key.extend(drop_id.try_to_vec().unwrap());
+    key
+}
+
+/// Key in the versioned drop map for a drop ID
+fn versioned_drop_key(drop_id: DropId) -> Vec<u8> {
+    let mut key = StorageKey::VersionedDropsForId.try_to_vec().unwrap();
+    key.extend(drop_id.try_to_vec().unwrap());
+    key
+}
+
+/// Store a drop with a single key that has two claims left the way the baseline `create_drop` stored it
+fn write_baseline_drop(state: &mut DropZoneV1, pk: &PublicKey, balance: Balance, drop_type: DropTypeV1, num_claims_registered: u64) -> DropId {
+    let drop_id = state.nonce;
+    let funder_id = funder_id();
+
+    let mut pks = UnorderedMap::new(StorageKey::PksForDrop {
+        account_id_hash: hash_account_id(&format!("{}{}", drop_id, funder_id)),
+    });
+    pks.insert(pk, &KeyUsageV1 { num_uses: 2, last_used: 0, allowance: BASELINE_ALLOWANCE });
+    state.drop_id_for_pk.insert(pk, &drop_id);
+
+    let mut drop_ids = state.drop_ids_for_funder.get(&funder_id).unwrap_or_else(|| {
+        UnorderedSet::new(StorageKey::DropIdsForFunderInner { account_id_hash: hash_account_id(&funder_id.to_string()) })
+    });
+    drop_ids.insert(&drop_id);
+    state.drop_ids_for_funder.insert(&funder_id, &drop_ids);
+
+    state.drop_for_id.insert(&drop_id, &DropV1 {
+        funder_id,
+        pks,
+        balance: U128(balance),
+        drop_type,
+        drop_config: DropConfigV1 {
+            max_claims_per_key: 2,
+            start_timestamp: None,
+            usage_interval: None,
+            refund_if_claim: None,
+            only_call_claim: None,
+        },
+        num_claims_registered,
+        required_gas_attached: ATTACHED_GAS_FROM_WALLET,
+    });
+    state.nonce += 1;
+
+    drop_id
+}
+
+/// Write the contract state in the baseline layout with a simple, FT, NFT and function call drop whose claims are all registered
+fn deploy_baseline() -> Vec<DropId> {
+    testing_env!(context(funder_id(), 0));
+    let pks = migration_keys();
+
+    let mut user_balances = LookupMap::new(StorageKey::UserBalances);
+    user_balances.insert(&funder_id(), &ONE_NEAR);
+    let mut state = DropZoneV1 {
+        owner_id: funder_id(),
+        linkdrop_contract: "testnet".parse().unwrap(),
+        drop_id_for_pk: UnorderedMap::new(StorageKey::DropIdForPk),
+        drop_for_id: LookupMap::new(StorageKey::DropsForId),
+        drop_ids_for_funder: LookupMap::new(StorageKey::DropIdsForFunder),
+        drop_fee: DROP_CREATION_FEE,
+        key_fee: KEY_ADDITION_FEE,
+        fees_collected: ONE_NEAR / 10,
+        user_balances,
+        nonce: 0,
+        yocto_per_gas: 100_000_000,
+    };
+
+    let simple = write_baseline_drop(&mut state, &pks[0], ONE_NEAR / 100, DropTypeV1::Simple, 2);
+
+    let ft_data = FTDataV1 { ft_contract: ft_contract_id(), ft_sender: funder_id(), ft_balance: U128(100), ft_storage: U128(ONE_NEAR / 800) };
+    let ft = write_baseline_drop(&mut state, &pks[1], 0, DropTypeV1::FT(ft_data), 2);
+
+    let mut token_ids = UnorderedSet::new(StorageKey::TokenIdsForDrop {
+        account_id_hash: hash_account_id(&format!("{}{}", state.nonce, funder_id())),
+    });
+    token_ids.insert(&"token-1".to_string());
+    token_ids.insert(&"token-2".to_string());
+    let nft_data = NFTDataV1 {
+        nft_sender: funder_id(),
+        nft_contract: nft_contract_id(),
+        longest_token_id: "token-1".to_string(),
+        storage_for_longest: ONE_NEAR / 1000,
+        token_ids,
+    };
+    let nft = write_baseline_drop(&mut state, &pks[2], 0, DropTypeV1::NFT(nft_data), 2);
+
+    let fc_data = FCDataV1 {
+        receiver: nft_contract_id(),
+        method: "nft_mint".to_string(),
+        args: "".to_string(),
+        deposit: U128(0),
+        refund_to_deposit: None,
+        claimed_account_field: Some("receiver_id".to_string()),
+        gas_if_straight_execute: None,
+    };
+    let fc = write_baseline_drop(&mut state, &pks[3], 0, DropTypeV1::FC(fc_data), 2);
+
+    env::state_write(&state);
+    vec![simple, ft, nft, fc]
+}
+
+#[test]
+fn drops_of_each_type_are_claimable_after_migrating_from_the_baseline() {
+    let drop_ids = deploy_baseline();
+
+    testing_env!(context(funder_id(), 0));
+    let mut contract = DropZone::migrate();
+    assert_eq!(contract.get_state_version(), STATE_VERSION);
+    assert_eq!(contract.fees_collected, ONE_NEAR / 10);
+    assert_eq!(contract.get_user_balance(funder_id()).0, ONE_NEAR);
+
+    // Drops that haven't been touched are read from the baseline map without being rewritten
+    for drop_id in &drop_ids {
+        assert_eq!(contract.get_drop_information(*drop_id).drop_id, *drop_id);
+        assert!(env::storage_has_key(&legacy_drop_key(*drop_id)));
+    }
+
+    for (pk, drop_id) in migration_keys().into_iter().zip(drop_ids) {
+        let key_info = contract.get_key_information(pk.clone());
+        assert_eq!(key_info.key_usage.num_uses, 2);
+        assert_eq!(key_info.key_usage.allowance, BASELINE_ALLOWANCE);
+
+        testing_env!(claim_context(pk.clone(), ATTACHED_GAS_FROM_WALLET));
+        contract.claim(claimer_id(), None, None);
+        assert!(!get_logs().iter().any(|log| log.contains("\"event\":\"claim_failure\"")), "drop {} wasn't claimed", drop_id);
+
+        // The drop and the key were upgraded when the claim wrote them back
+        assert!(!env::storage_has_key(&legacy_drop_key(drop_id)));
+        assert_eq!(env::storage_read(&versioned_drop_key(drop_id)).unwrap()[0], 1);
+        let key_usage = contract.drop_for_id.get(&drop_id).unwrap().pks.get(&pk).unwrap();
+        assert_eq!(key_usage.num_uses, 1);
+        assert!(key_usage.allowance < BASELINE_ALLOWANCE);
+    }
+}
+
+#[test]
+fn baseline_drops_keep_their_registered_claims() {
+    let drop_ids = deploy_baseline();
+
+    testing_env!(context(funder_id(), 0));
+    let contract = DropZone::migrate();
+
+    let simple = contract.drop_for_id.get(&drop_ids[0]).unwrap();
+    assert!(simple.assets.is_empty());
+    assert_eq!(simple.num_claims_registered, 2);
+    assert_eq!(simple.drop_config.max_claims_per_key, 2);
+    assert!(simple.drop_config.end_timestamp.is_none());
+
+    for drop_id in &drop_ids[1..3] {
+        let drop = contract.drop_for_id.get(drop_id).unwrap();
+        assert_eq!(drop.num_claims_registered, 2);
+        match &drop.assets[..] {
+            [DropAsset::FT(data)] => assert_eq!((data.num_claims_registered, data.ft_storage.0), (2, ONE_NEAR / 800)),
+            [DropAsset::NFT(data)] => assert_eq!((data.num_claims_registered, data.token_ids.len()), (2, 2)),
+            _ => panic!("drop {} should have a single FT or NFT asset", drop_id),
+        }
+    }
+
+    let fc = contract.drop_for_id.get(&drop_ids[3]).unwrap();
+    match &fc.assets[..] {
+        [DropAsset::FC(data)] => {
+            assert_eq!(data.methods.len(), 1);
+            assert_eq!(data.methods[0].method, "nft_mint");
+            assert!(data.methods[0].min_gas.is_none());
+            assert_eq!(data.claimed_account_field.as_deref(), Some("receiver_id"));
+        },
+        _ => panic!("function call drop should have a single FC asset"),
+    }
+}
+
+#[test]
+fn deleting_baseline_keys_refunds_their_allowance() {
+    let drop_ids = deploy_baseline();
+
+    testing_env!(context(funder_id(), 0));
+    let mut contract = DropZone::migrate();
+    contract.delete_keys(None, drop_ids[0]);
+
+    assert!(contract.drop_for_id.get(&drop_ids[0]).is_none());
+    assert!(contract.get_user_balance(funder_id()).0 > ONE_NEAR + BASELINE_ALLOWANCE);
+}
+
+#[test]
+fn migrate_is_a_no_op_for_current_state() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    let drop_id = contract.create_drop(public_keys(), U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    env::state_write(&contract);
+
+    testing_env!(context(funder_id(), 0));
+    let contract = DropZone::migrate();
+    assert_eq!(contract.get_state_version(), STATE_VERSION);
+    assert_eq!(contract.get_nonce(), drop_id + 1);
+    assert_eq!(env::storage_read(&versioned_drop_key(drop_id)).unwrap()[0], 1);
+}
+
+#[test]
+#[should_panic(expected = "predecessor != owner")]
+fn only_owner_can_migrate() {
+    deploy_baseline();
+
+    testing_env!(context(claimer_id(), 0));
+    DropZone::migrate();
+}
@@ -0,0 +1,106 @@
+use near_sdk::test_utils::{get_logs, VMContextBuilder};
+use near_sdk::{testing_env, VMContext};
+
+use crate::*;
+
+mod accounting;
+mod claims;
+mod estimates;
+mod fee_tokens;
+mod fees;
+mod ft_amount_tiers;
+mod gas;
+mod migration;
+mod pause;
+mod referrals;
+mod refunds;
+mod roles;
+mod transfer_msg;
+mod upgrade;
+
+pub(crate) const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+
+pub(crate) fn contract_id() -> AccountId {
+    "linkdrop-proxy.testnet".parse().unwrap()
+}
+
+pub(crate) fn funder_id() -> AccountId {
+    "funder.testnet".parse().unwrap()
+}
+
+pub(crate) fn claimer_id() -> AccountId {
+    "claimer.testnet".parse().unwrap()
+}
+
+pub(crate) fn public_keys() -> Vec<PublicKey> {
+    vec![
+        "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp".parse().unwrap(),
+        "ed25519:3tysLvy7KGoE8pznUgXvSHa4vYyGvrDZFcT8jgb8PEQ6".parse().unwrap(),
+        "ed25519:9SgbbTMQb2gLJNNGtGm1SpgKanWTSQQxQv8LbZDKrhHD".parse().unwrap(),
+    ]
+}
+
+/// Context for a call made by the given account with the given deposit
+pub(crate) fn context(predecessor: AccountId, deposit: Balance) -> VMContext {
+    VMContextBuilder::new()
+        .current_account_id(contract_id())
+        .signer_account_id(predecessor.clone())
+        .predecessor_account_id(predecessor)
+        .attached_deposit(deposit)
+        .build()
+}
+
+/// Context for a claim signed by the given access key on the contract
+pub(crate) fn claim_context(pk: PublicKey, prepaid_gas: Gas) -> VMContext {
+    VMContextBuilder::new()
+        .current_account_id(contract_id())
+        .signer_account_id(contract_id())
+        .predecessor_account_id(contract_id())
+        .signer_account_pk(pk)
+        .prepaid_gas(prepaid_gas)
+        .build()
+}
+
+/// Drop config with a single claim per key and no other restrictions
+pub(crate) fn simple_config() -> DropConfig {
+    DropConfig {
+        max_claims_per_key: 1,
+        start_timestamp: None,
+        end_timestamp: None,
+        start_block: None,
+        end_block: None,
+        usage_interval: None,
+        usage_interval_blocks: None,
+        max_claims_per_epoch: None,
+        max_total_claims: None,
+        max_claims_per_account: None,
+        refund_if_claim: None,
+        only_call_claim: None,
+    }
+}
+
+/// Fees passed to the FT storage check for drops that don't pay any
+pub(crate) fn no_creation_fees() -> DropCreationFees {
+    DropCreationFees { fees: U128(0), fee_credit: U128(0), referrer: None }
+}
+
+/// Deploy the contract and fund the funder's balance
+pub(crate) fn setup() -> DropZone {
+    testing_env!(context(funder_id(), 0));
+    let mut contract = DropZone::new("testnet".parse().unwrap(), funder_id());
+
+    testing_env!(context(funder_id(), 10 * ONE_NEAR));
+    contract.add_to_balance();
+    contract
+}
+
+/// Every log must be a structured event unless the contract is built with the `debug-logs` feature
+pub(crate) fn assert_only_events_logged() {
+    if cfg!(feature = "debug-logs") {
+        return;
+    }
+
+    for log in get_logs() {
+        assert!(log.starts_with("EVENT_JSON:"), "unexpected log without the debug-logs feature: {}", log);
+    }
+}
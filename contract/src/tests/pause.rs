@@ -0,0 +1,130 @@
+use super::*;
+
+fn pauser_id() -> AccountId {
+    "pauser.testnet".parse().unwrap()
+}
+
+/// Create a simple drop with a single key and grant the pauser role
+fn setup_drop() -> (DropZone, DropId) {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Pauser, pauser_id());
+    let drop_id = contract.create_drop(vec![public_keys()[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    (contract, drop_id)
+}
+
+#[test]
+#[should_panic(expected = "contract is paused for claims")]
+fn paused_claims_fail_before_the_allowance_is_charged() {
+    let (mut contract, _) = setup_drop();
+    testing_env!(context(pauser_id(), 0));
+    contract.pause(PauseCategory::Claims);
+    assert!(get_logs()[0].contains("\"event\":\"pause\""));
+    assert!(contract.get_paused().claims);
+
+    testing_env!(claim_context(public_keys()[0].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.claim(claimer_id(), None, None);
+}
+
+#[test]
+fn unpaused_claims_go_through() {
+    let (mut contract, drop_id) = setup_drop();
+    testing_env!(context(pauser_id(), 0));
+    contract.pause(PauseCategory::Claims);
+    contract.unpause(PauseCategory::Claims);
+    assert!(!contract.get_paused().claims);
+
+    testing_env!(claim_context(public_keys()[0].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.claim(claimer_id(), None, None);
+    // The drop's only key was used so the drop is gone
+    assert!(contract.drop_for_id.get(&drop_id).is_none());
+}
+
+#[test]
+#[should_panic(expected = "contract is paused for creation")]
+fn paused_creation_blocks_new_drops() {
+    let (mut contract, _) = setup_drop();
+    testing_env!(context(pauser_id(), 0));
+    contract.pause(PauseCategory::Creation);
+
+    testing_env!(context(funder_id(), 0));
+    contract.create_drop(vec![public_keys()[1].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+}
+
+#[test]
+#[should_panic(expected = "contract is paused for creation")]
+fn paused_creation_blocks_adding_keys() {
+    let (mut contract, drop_id) = setup_drop();
+    testing_env!(context(pauser_id(), 0));
+    contract.pause(PauseCategory::Creation);
+
+    testing_env!(context(funder_id(), 0));
+    contract.add_to_drop(vec![public_keys()[1].clone()], drop_id, None, None, None);
+}
+
+#[test]
+#[should_panic(expected = "contract is paused for asset_intake")]
+fn paused_asset_intake_rejects_tokens() {
+    let (mut contract, drop_id) = setup_drop();
+    testing_env!(context(pauser_id(), 0));
+    contract.pause(PauseCategory::AssetIntake);
+
+    testing_env!(context("nft.testnet".parse().unwrap(), 0));
+    contract.nft_on_transfer("token-1".to_string(), funder_id(), drop_id.to_string());
+}
+
+#[test]
+#[should_panic(expected = "contract is paused for withdrawals")]
+fn paused_withdrawals_keep_the_balance() {
+    let (mut contract, _) = setup_drop();
+    testing_env!(context(pauser_id(), 0));
+    contract.pause(PauseCategory::Withdrawals);
+
+    testing_env!(context(funder_id(), 0));
+    contract.withdraw_from_balance();
+}
+
+#[test]
+#[should_panic(expected = "predecessor doesn't have the pauser role")]
+fn only_pausers_can_pause() {
+    let (mut contract, _) = setup_drop();
+
+    testing_env!(context(claimer_id(), 0));
+    contract.pause(PauseCategory::Claims);
+}
+
+#[test]
+#[should_panic(expected = "drop is paused")]
+fn funder_can_pause_their_drop() {
+    let (mut contract, drop_id) = setup_drop();
+    testing_env!(context(funder_id(), 0));
+    contract.pause_drop(drop_id);
+    assert!(get_logs()[0].contains("\"event\":\"drop_pause\""));
+    assert!(contract.get_drop_information(drop_id).paused);
+
+    testing_env!(claim_context(public_keys()[0].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.create_account_and_claim(claimer_id(), public_keys()[1].clone(), None, None);
+}
+
+#[test]
+fn funder_can_unpause_their_drop() {
+    let (mut contract, drop_id) = setup_drop();
+    testing_env!(context(funder_id(), 0));
+    contract.pause_drop(drop_id);
+    contract.unpause_drop(drop_id);
+    assert!(!contract.get_drop_information(drop_id).paused);
+
+    testing_env!(claim_context(public_keys()[0].clone(), ATTACHED_GAS_FROM_WALLET));
+    contract.claim(claimer_id(), None, None);
+    // The drop's only key was used so the drop is gone
+    assert!(contract.drop_for_id.get(&drop_id).is_none());
+}
+
+#[test]
+#[should_panic(expected = "only funder can pause a drop")]
+fn only_funder_can_pause_their_drop() {
+    let (mut contract, drop_id) = setup_drop();
+
+    testing_env!(context(pauser_id(), 0));
+    contract.pause_drop(drop_id);
+}
@@ -0,0 +1,110 @@
+use super::*;
+use near_sdk::serde_json::json;
+use near_sdk::{PromiseResult, RuntimeFeesConfig, VMConfig};
+
+fn referrer_id() -> AccountId {
+    "referrer.testnet".parse().unwrap()
+}
+
+fn ft_data() -> Vec<FTDataConfig> {
+    vec![FTDataConfig { ft_contract: "ft.testnet".parse().unwrap(), ft_sender: funder_id(), ft_balance: U128(100), amount_tiers: None }]
+}
+
+/// Deploy the contract with half of the fees going to referrers
+fn setup_split() -> DropZone {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_referral_split(5_000);
+    contract
+}
+
+#[test]
+fn referrers_are_credited_their_share() {
+    let mut contract = setup_split();
+    let drop_id = contract.create_drop(vec![public_keys()[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, Some(referrer_id()));
+    let fees = DROP_CREATION_FEE + KEY_ADDITION_FEE;
+    let share = contract.get_referral_fees(referrer_id()).0;
+    assert!(get_logs().iter().any(|log| log.contains("\"event\":\"referral_fee\"")));
+
+    // The storage for the referrer's record comes out of their first share
+    assert!(share < fees / 2 && share > 0);
+    assert_eq!(contract.fees_collected + share, fees);
+
+    // Later shares are credited in full
+    testing_env!(context(funder_id(), 0));
+    contract.add_to_drop(vec![public_keys()[1].clone()], drop_id, None, None, Some(referrer_id()));
+    assert_eq!(contract.get_referral_fees(referrer_id()).0, share + KEY_ADDITION_FEE / 2);
+}
+
+#[test]
+fn fees_without_a_referrer_are_collected_by_the_contract() {
+    let mut contract = setup_split();
+    contract.create_drop(vec![public_keys()[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, None);
+    assert_eq!(contract.fees_collected, DROP_CREATION_FEE + KEY_ADDITION_FEE);
+}
+
+#[test]
+fn referral_fees_can_be_withdrawn() {
+    let mut contract = setup_split();
+    contract.create_drop(vec![public_keys()[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, Some(referrer_id()));
+
+    testing_env!(context(referrer_id(), 0));
+    contract.withdraw_referral_fees();
+    assert_eq!(contract.get_referral_fees(referrer_id()).0, 0);
+}
+
+#[test]
+#[should_panic(expected = "funder can't refer themselves")]
+fn funders_cant_refer_themselves() {
+    let mut contract = setup_split();
+    contract.create_drop(vec![public_keys()[0].clone()], U128(ONE_NEAR / 100), None, None, None, simple_config(), None, None, Some(funder_id()));
+}
+
+#[test]
+#[should_panic(expected = "referral split can't be more than 10000 basis points")]
+fn referral_split_is_capped() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.set_referral_split(10_001);
+}
+
+#[test]
+fn ft_drop_fees_are_collected_once_the_storage_check_succeeds() {
+    let mut contract = setup_split();
+    let drop_id = contract.create_drop(vec![public_keys()[0].clone()], U128(0), Some(ft_data()), None, None, simple_config(), None, None, Some(referrer_id()));
+    assert_eq!(contract.fees_collected, 0);
+    assert_eq!(contract.get_referral_fees(referrer_id()).0, 0);
+
+    let fees = DropCreationFees { fees: U128(DROP_CREATION_FEE + KEY_ADDITION_FEE), fee_credit: U128(0), referrer: Some(referrer_id()) };
+    let bounds = json!({ "min": U128(ONE_NEAR / 800), "max": null }).to_string().into_bytes();
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Successful(bounds)]);
+    assert!(contract.resolve_storage_check(vec![public_keys()[0].clone()], drop_id, 0, fees));
+
+    let share = contract.get_referral_fees(referrer_id()).0;
+    assert!(share > 0);
+    assert_eq!(contract.fees_collected + share, DROP_CREATION_FEE + KEY_ADDITION_FEE);
+}
+
+#[test]
+fn failed_ft_storage_checks_dont_credit_referrers() {
+    let mut contract = setup_split();
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    let drop_id = contract.create_drop(vec![public_keys()[0].clone()], U128(0), Some(ft_data()), None, None, simple_config(), None, None, Some(referrer_id()));
+    let required_deposit = balance_before - contract.get_user_balance(funder_id()).0;
+
+    // The FT contract couldn't be queried so the drop is removed and the funder refunded in full
+    let fees = DropCreationFees { fees: U128(DROP_CREATION_FEE + KEY_ADDITION_FEE), fee_credit: U128(0), referrer: Some(referrer_id()) };
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Failed]);
+    assert!(!contract.resolve_storage_check(vec![public_keys()[0].clone()], drop_id, required_deposit, fees));
+
+    assert_eq!(contract.get_user_balance(funder_id()).0, balance_before);
+    assert_eq!(contract.fees_collected, 0);
+    assert_eq!(contract.get_referral_fees(referrer_id()).0, 0);
+}
+
+#[test]
+#[should_panic(expected = "funder can't refer themselves")]
+fn funders_cant_refer_themselves_on_ft_drops() {
+    let mut contract = setup_split();
+    contract.create_drop(vec![public_keys()[0].clone()], U128(0), Some(ft_data()), None, None, simple_config(), None, None, Some(funder_id()));
+}
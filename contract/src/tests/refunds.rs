@@ -0,0 +1,73 @@
+use super::*;
+use near_sdk::{PromiseResult, RuntimeFeesConfig, VMConfig};
+
+fn nft_contract_id() -> AccountId {
+    "nft.testnet".parse().unwrap()
+}
+
+fn ft_contract_id() -> AccountId {
+    "ft.testnet".parse().unwrap()
+}
+
+/// Create an NFT drop with one key, register a token for it and refund the token
+fn setup_refunded_nft_drop() -> (DropZone, DropId) {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    let nft_data = NFTDataConfig { nft_sender: funder_id(), nft_contract: nft_contract_id(), longest_token_id: "token-1".to_string() };
+    let drop_id = contract.create_drop(vec![public_keys()[0].clone()], U128(0), None, Some(vec![nft_data]), None, simple_config(), None, None, None);
+
+    testing_env!(context(nft_contract_id(), 0));
+    contract.nft_on_transfer("token-1".to_string(), funder_id(), drop_id.to_string());
+
+    testing_env!(context(funder_id(), 0));
+    contract.refund_assets(drop_id, None, None);
+    assert_eq!(contract.drop_for_id.get(&drop_id).unwrap().pending_refunds, 1);
+    (contract, drop_id)
+}
+
+fn callback_context(result: PromiseResult) {
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![result]);
+}
+
+#[test]
+#[should_panic(expected = "asset refunds must resolve before keys are deleted")]
+fn keys_cant_be_deleted_while_refunds_are_pending() {
+    let (mut contract, drop_id) = setup_refunded_nft_drop();
+    testing_env!(context(funder_id(), 0));
+    contract.delete_keys(None, drop_id);
+}
+
+#[test]
+fn keys_can_be_deleted_once_refunds_resolve() {
+    let (mut contract, drop_id) = setup_refunded_nft_drop();
+    callback_context(PromiseResult::Successful(vec![]));
+    assert!(contract.nft_resolve_refund(U128(drop_id), vec!["token-1".to_string()], 0, nft_contract_id(), funder_id()));
+    assert_eq!(contract.drop_for_id.get(&drop_id).unwrap().pending_refunds, 0);
+
+    testing_env!(context(funder_id(), 0));
+    contract.delete_keys(None, drop_id);
+    assert!(contract.drop_for_id.get(&drop_id).is_none());
+}
+
+#[test]
+fn failed_refunds_are_registered_again() {
+    let (mut contract, drop_id) = setup_refunded_nft_drop();
+    callback_context(PromiseResult::Failed);
+    assert!(!contract.nft_resolve_refund(U128(drop_id), vec!["token-1".to_string()], 0, nft_contract_id(), funder_id()));
+
+    let drop = contract.drop_for_id.get(&drop_id).unwrap();
+    assert_eq!(drop.pending_refunds, 0);
+    assert!(matches!(&drop.assets[0], DropAsset::NFT(data) if data.num_claims_registered == 1));
+}
+
+#[test]
+fn failed_refunds_for_removed_drops_dont_panic() {
+    let (mut contract, drop_id) = setup_refunded_nft_drop();
+    // The drop can be removed by a claim of its last key while a partial refund is in flight
+    contract.drop_for_id.remove(&drop_id);
+
+    callback_context(PromiseResult::Failed);
+    assert!(!contract.nft_resolve_refund(U128(drop_id), vec!["token-1".to_string()], 0, nft_contract_id(), funder_id()));
+    callback_context(PromiseResult::Failed);
+    assert!(!contract.ft_resolve_refund(drop_id, 1, 0, ft_contract_id(), funder_id(), U128(100)));
+}
@@ -0,0 +1,119 @@
+use super::*;
+
+fn operator_id() -> AccountId {
+    "operator.testnet".parse().unwrap()
+}
+
+#[test]
+fn granted_roles_can_call_gated_methods() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Operations, operator_id());
+    assert!(get_logs()[0].contains("\"event\":\"role_grant\""));
+    assert!(contract.has_role(Role::Operations, operator_id()));
+    assert!(!contract.has_role(Role::FeeManager, operator_id()));
+
+    testing_env!(context(operator_id(), 0));
+    contract.set_gas_price(1);
+    contract.set_fees(Some(U128(2)), None);
+    assert_eq!(contract.yocto_per_gas, 1);
+    assert_eq!(contract.drop_fee, 2);
+}
+
+#[test]
+#[should_panic(expected = "predecessor doesn't have the operations role")]
+fn revoked_roles_cant_call_gated_methods() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Operations, operator_id());
+    contract.revoke_role(Role::Operations, operator_id());
+    assert!(get_logs()[1].contains("\"event\":\"role_revoke\""));
+
+    testing_env!(context(operator_id(), 0));
+    contract.set_gas_price(1);
+}
+
+#[test]
+#[should_panic(expected = "predecessor doesn't have the fee_manager role")]
+fn only_fee_managers_can_withdraw_fees() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Operations, operator_id());
+
+    testing_env!(context(operator_id(), 0));
+    contract.withdraw_fees(operator_id());
+}
+
+#[test]
+#[should_panic(expected = "predecessor != owner")]
+fn only_owner_can_set_the_linkdrop_contract() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Operations, operator_id());
+
+    testing_env!(context(operator_id(), 0));
+    contract.set_contract(operator_id());
+}
+
+#[test]
+#[should_panic(expected = "predecessor != owner")]
+fn only_owner_can_grant_roles() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Operations, operator_id());
+
+    testing_env!(context(operator_id(), 0));
+    contract.grant_role(Role::Operations, claimer_id());
+}
+
+#[test]
+fn role_members_are_paginated() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Pauser, operator_id());
+    contract.grant_role(Role::Pauser, claimer_id());
+
+    assert_eq!(contract.get_role_members(Role::Pauser, None, None), vec![operator_id(), claimer_id()]);
+    assert_eq!(contract.get_role_members(Role::Pauser, Some(U128(1)), Some(1)), vec![claimer_id()]);
+    assert!(contract.get_role_members(Role::Upgrader, None, None).is_empty());
+}
+
+#[test]
+fn ownership_is_transferred_once_accepted() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.propose_owner(operator_id());
+    assert_eq!(contract.get_owner(), funder_id());
+    assert_eq!(contract.get_pending_owner(), Some(operator_id()));
+
+    testing_env!(context(operator_id(), 0));
+    contract.accept_ownership();
+    assert!(get_logs()[0].contains("\"event\":\"ownership_transfer\""));
+    assert_eq!(contract.get_owner(), operator_id());
+    assert_eq!(contract.get_pending_owner(), None);
+    // The old owner loses every role
+    assert!(!contract.has_role(Role::FeeManager, funder_id()));
+}
+
+#[test]
+#[should_panic(expected = "predecessor isn't the proposed owner")]
+fn only_the_proposed_owner_can_accept() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.propose_owner(operator_id());
+
+    testing_env!(context(claimer_id(), 0));
+    contract.accept_ownership();
+}
+
+#[test]
+#[should_panic(expected = "predecessor isn't the proposed owner")]
+fn cancelled_proposals_cant_be_accepted() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.propose_owner(operator_id());
+    contract.cancel_ownership_proposal();
+
+    testing_env!(context(operator_id(), 0));
+    contract.accept_ownership();
+}
@@ -0,0 +1,188 @@
+use super::*;
+use near_sdk::serde_json::json;
+use near_sdk::{PromiseResult, RuntimeFeesConfig, VMConfig};
+
+fn ft_contract_id() -> AccountId {
+    "ft.testnet".parse().unwrap()
+}
+
+fn nft_contract_id() -> AccountId {
+    "nft.testnet".parse().unwrap()
+}
+
+fn unused(result: PromiseOrValue<U128>) -> Balance {
+    match result {
+        PromiseOrValue::Value(unused) => unused.0,
+        PromiseOrValue::Promise(_) => panic!("expected a value"),
+    }
+}
+
+/// Create a drop with two keys that each send 100 FTs and resolve its storage check
+fn setup_ft_drop() -> (DropZone, DropId) {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    let ft_data = FTDataConfig { ft_contract: ft_contract_id(), ft_sender: funder_id(), ft_balance: U128(100), amount_tiers: None };
+    let drop_id = contract.create_drop(public_keys()[..2].to_vec(), U128(0), Some(vec![ft_data]), None, None, simple_config(), None, None, None);
+
+    let bounds = json!({ "min": U128(ONE_NEAR / 800), "max": null }).to_string().into_bytes();
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Successful(bounds)]);
+    assert!(contract.resolve_storage_check(public_keys()[..2].to_vec(), drop_id, 0, no_creation_fees()));
+    (contract, drop_id)
+}
+
+fn ft_claims_registered(contract: &DropZone, drop_id: DropId) -> u64 {
+    match &contract.drop_for_id.get(&drop_id).unwrap().assets[0] {
+        DropAsset::FT(data) => data.num_claims_registered,
+        _ => panic!("expected an FT asset"),
+    }
+}
+
+#[test]
+fn numeric_and_v1_messages_register_claims() {
+    let (mut contract, drop_id) = setup_ft_drop();
+    testing_env!(context(ft_contract_id(), 0));
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(100), drop_id.to_string())), 0);
+
+    let msg = json!({ "v1": { "register_claims": { "drop_id": drop_id.to_string() } } }).to_string();
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(100), msg)), 0);
+    assert_eq!(ft_claims_registered(&contract, drop_id), 2);
+}
+
+#[test]
+fn tokens_past_the_claims_left_are_refunded() {
+    let (mut contract, drop_id) = setup_ft_drop();
+    testing_env!(context(ft_contract_id(), 0));
+    // Only whole claims are registered and the drop only has two
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(150), drop_id.to_string())), 50);
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(300), drop_id.to_string())), 200);
+    assert_eq!(ft_claims_registered(&contract, drop_id), 2);
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(100), drop_id.to_string())), 100);
+}
+
+#[test]
+fn mismatched_transfers_are_refunded() {
+    let (mut contract, drop_id) = setup_ft_drop();
+    testing_env!(context(ft_contract_id(), 0));
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(100), "not a message".to_string())), 100);
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(100), (drop_id + 1).to_string())), 100);
+    assert_eq!(unused(contract.ft_on_transfer(claimer_id(), U128(100), drop_id.to_string())), 100);
+
+    testing_env!(context(nft_contract_id(), 0));
+    assert!(matches!(contract.nft_on_transfer("token-1".to_string(), funder_id(), drop_id.to_string()), PromiseOrValue::Value(true)));
+    assert_eq!(ft_claims_registered(&contract, drop_id), 0);
+}
+
+#[test]
+fn nfts_can_only_register_claims() {
+    let (mut contract, _) = setup_ft_drop();
+    testing_env!(context(nft_contract_id(), 0));
+    let msg = json!({ "v1": { "top_up_balance": {} } }).to_string();
+    assert!(matches!(contract.nft_on_transfer("token-1".to_string(), funder_id(), msg), PromiseOrValue::Value(true)));
+}
+
+#[test]
+fn tokens_top_up_the_token_balance() {
+    let mut contract = setup();
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    let msg = json!({ "v1": { "top_up_balance": {} } }).to_string();
+
+    testing_env!(context(ft_contract_id(), 0));
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(500), msg.clone())), 0);
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(500), msg)), 0);
+    assert_eq!(contract.get_token_balance(funder_id(), ft_contract_id()).0, 1000);
+
+    // The storage for the record is paid once from the $NEAR balance
+    let record_cost = balance_before - contract.get_user_balance(funder_id()).0;
+    assert!(record_cost > 0 && record_cost < ONE_NEAR / 100);
+
+    // Withdrawing everything refunds the storage
+    testing_env!(context(funder_id(), 0));
+    contract.withdraw_token_balance(ft_contract_id(), None);
+    assert_eq!(contract.get_token_balance(funder_id(), ft_contract_id()).0, 0);
+    assert_eq!(contract.get_user_balance(funder_id()).0, balance_before);
+}
+
+#[test]
+fn top_ups_without_a_near_balance_are_refunded() {
+    let mut contract = setup();
+    let msg = json!({ "v1": { "top_up_balance": { "account_id": claimer_id() } } }).to_string();
+
+    testing_env!(context(ft_contract_id(), 0));
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(500), msg)), 500);
+    assert_eq!(contract.get_token_balance(claimer_id(), ft_contract_id()).0, 0);
+}
+
+#[test]
+fn failed_token_balance_withdrawals_are_added_back() {
+    let mut contract = setup();
+    testing_env!(context(ft_contract_id(), 0));
+    contract.ft_on_transfer(funder_id(), U128(1000), json!({ "v1": { "top_up_balance": {} } }).to_string());
+
+    testing_env!(context(funder_id(), 0));
+    contract.withdraw_token_balance(ft_contract_id(), Some(U128(400)));
+    assert_eq!(contract.get_token_balance(funder_id(), ft_contract_id()).0, 600);
+
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Failed]);
+    assert!(!contract.on_withdraw_token_balance(ft_contract_id(), U128(400), funder_id()));
+    assert_eq!(contract.get_token_balance(funder_id(), ft_contract_id()).0, 1000);
+}
+
+/// Message that creates a drop with two keys that each send 100 FTs
+fn create_drop_msg() -> String {
+    near_sdk::serde_json::to_string(&TransferMsg::V1(TransferIntent::CreateDrop(CreateDropMsg {
+        public_keys: public_keys()[..2].to_vec(),
+        balance: U128(0),
+        ft_balance: U128(100),
+        drop_config: simple_config(),
+        passwords_per_key: None,
+        passwords_per_use: None,
+        referrer: None,
+    }))).unwrap()
+}
+
+#[test]
+fn drops_are_created_and_registered_from_a_transfer() {
+    let mut contract = setup();
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    let drop_id = contract.get_nonce();
+
+    testing_env!(context(ft_contract_id(), 0));
+    assert!(matches!(contract.ft_on_transfer(funder_id(), U128(250), create_drop_msg()), PromiseOrValue::Promise(_)));
+    assert!(contract.get_user_balance(funder_id()).0 < balance_before);
+
+    // Only whole claims are registered and the rest is refunded
+    let bounds = json!({ "min": U128(ONE_NEAR / 800), "max": null }).to_string().into_bytes();
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Successful(bounds)]);
+    let required_deposit = balance_before - contract.get_user_balance(funder_id()).0;
+    assert_eq!(contract.resolve_transfer_drop_creation(public_keys()[..2].to_vec(), drop_id, required_deposit, no_creation_fees(), U128(250)).0, 50);
+    assert_eq!(ft_claims_registered(&contract, drop_id), 2);
+    assert_eq!(contract.get_drop_information(drop_id).funder_id, funder_id());
+}
+
+#[test]
+fn failed_storage_queries_refund_the_transfer() {
+    let mut contract = setup();
+    let balance_before = contract.get_user_balance(funder_id()).0;
+    let drop_id = contract.get_nonce();
+
+    testing_env!(context(ft_contract_id(), 0));
+    contract.ft_on_transfer(funder_id(), U128(200), create_drop_msg());
+    let required_deposit = balance_before - contract.get_user_balance(funder_id()).0;
+
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![PromiseResult::Failed]);
+    assert_eq!(contract.resolve_transfer_drop_creation(public_keys()[..2].to_vec(), drop_id, required_deposit, no_creation_fees(), U128(200)).0, 200);
+    assert!(contract.drop_for_id.get(&drop_id).is_none());
+    assert_eq!(contract.get_user_balance(funder_id()).0, balance_before);
+}
+
+#[test]
+fn transfers_that_cant_create_a_drop_are_refunded() {
+    let mut contract = setup();
+    let nonce = contract.get_nonce();
+    testing_env!(context(ft_contract_id(), 0));
+    // Not enough tokens for a single claim
+    assert_eq!(unused(contract.ft_on_transfer(funder_id(), U128(99), create_drop_msg())), 99);
+    // No $NEAR balance to pay for the drop
+    assert_eq!(unused(contract.ft_on_transfer(claimer_id(), U128(200), create_drop_msg())), 200);
+    assert_eq!(contract.get_nonce(), nonce);
+}
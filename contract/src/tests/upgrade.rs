@@ -0,0 +1,141 @@
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::{RuntimeFeesConfig, VMConfig};
+
+use super::*;
+
+const CODE: &[u8] = b"\0asm new code";
+const DELAY: u64 = 1_000_000_000;
+
+/// Context for the owner staging code at a given block timestamp
+fn stage_context(predecessor: AccountId, timestamp: u64) -> VMContext {
+    let mut context = timestamp_context(predecessor, timestamp);
+    context.input = CODE.to_vec();
+    context
+}
+
+/// Context for a call at a given block timestamp
+fn timestamp_context(predecessor: AccountId, timestamp: u64) -> VMContext {
+    VMContextBuilder::new()
+        .current_account_id(contract_id())
+        .predecessor_account_id(predecessor)
+        .block_timestamp(timestamp)
+        .build()
+}
+
+fn code_hash() -> Base58CryptoHash {
+    env::sha256_array(CODE).into()
+}
+
+/// Resolve the deploy of the staged code as if the deploy and migrate batch had the given result
+fn resolve_deploy(contract: &mut DropZone, result: PromiseResult) -> bool {
+    testing_env!(context(contract_id(), 0), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), vec![result]);
+    contract.on_deploy_staged_code(code_hash())
+}
+
+/// Deploy the contract with an upgrade delay and stage the code
+fn setup_staged() -> DropZone {
+    let mut contract = setup();
+
+    testing_env!(context(funder_id(), 0));
+    contract.set_upgrade_delay(DELAY);
+    testing_env!(stage_context(funder_id(), 100));
+    contract.stage_code();
+    contract
+}
+
+#[test]
+fn staged_code_deploys_once_the_delay_has_passed() {
+    let mut contract = setup_staged();
+    let staged = contract.get_staged_code().unwrap();
+    assert_eq!(staged.code_hash, code_hash());
+    assert_eq!(staged.length, CODE.len() as u64);
+    assert_eq!(staged.deployable_at, 100 + DELAY);
+    assert!(get_logs()[0].contains("\"event\":\"code_staged\""));
+
+    testing_env!(timestamp_context(funder_id(), 100 + DELAY));
+    contract.deploy_staged_code(code_hash());
+    // Nothing changes until the deploy has succeeded
+    assert!(contract.get_staged_code().is_some());
+    assert!(contract.get_code_hash().is_none());
+
+    assert!(resolve_deploy(&mut contract, PromiseResult::Successful(vec![])));
+    assert!(get_logs()[0].contains("\"event\":\"code_deployment\""));
+    assert!(contract.get_staged_code().is_none());
+    assert_eq!(contract.get_code_hash(), Some(code_hash()));
+    assert!(!env::storage_has_key(&StorageKey::StagedCode.try_to_vec().unwrap()));
+}
+
+#[test]
+fn failed_deploys_keep_the_code_staged() {
+    let mut contract = setup_staged();
+
+    testing_env!(timestamp_context(funder_id(), 100 + DELAY));
+    contract.deploy_staged_code(code_hash());
+    assert!(!resolve_deploy(&mut contract, PromiseResult::Failed));
+
+    assert_eq!(contract.get_staged_code().unwrap().code_hash, code_hash());
+    assert!(contract.get_code_hash().is_none());
+    assert!(env::storage_has_key(&StorageKey::StagedCode.try_to_vec().unwrap()));
+}
+
+#[test]
+#[should_panic(expected = "staged code can't be deployed until")]
+fn staged_code_waits_for_the_delay() {
+    let mut contract = setup_staged();
+
+    testing_env!(timestamp_context(funder_id(), 100 + DELAY - 1));
+    contract.deploy_staged_code(code_hash());
+}
+
+#[test]
+#[should_panic(expected = "code hash doesn't match the staged code")]
+fn deploy_requires_the_staged_code_hash() {
+    let mut contract = setup_staged();
+
+    testing_env!(timestamp_context(funder_id(), 100 + DELAY));
+    contract.deploy_staged_code(env::sha256_array(b"other code").into());
+}
+
+#[test]
+fn cancelled_code_is_removed() {
+    let mut contract = setup_staged();
+
+    testing_env!(context(funder_id(), 0));
+    contract.cancel_staged_code();
+    assert!(contract.get_staged_code().is_none());
+    assert!(!env::storage_has_key(&StorageKey::StagedCode.try_to_vec().unwrap()));
+}
+
+#[test]
+#[should_panic(expected = "predecessor doesn't have the upgrader role")]
+fn only_upgraders_can_stage_code() {
+    let mut contract = setup();
+
+    testing_env!(stage_context(claimer_id(), 0));
+    contract.stage_code();
+}
+
+#[test]
+fn upgraders_can_stage_and_deploy_code() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Upgrader, claimer_id());
+
+    testing_env!(stage_context(claimer_id(), 0));
+    contract.stage_code();
+    testing_env!(timestamp_context(claimer_id(), 0));
+    contract.deploy_staged_code(code_hash());
+    resolve_deploy(&mut contract, PromiseResult::Successful(vec![]));
+    assert_eq!(contract.get_code_hash(), Some(code_hash()));
+}
+
+#[test]
+#[should_panic(expected = "predecessor != owner")]
+fn upgraders_cant_change_the_delay() {
+    let mut contract = setup();
+    testing_env!(context(funder_id(), 0));
+    contract.grant_role(Role::Upgrader, claimer_id());
+
+    testing_env!(context(claimer_id(), 0));
+    contract.set_upgrade_delay(0);
+}
@@ -2,8 +2,7 @@ use crate::*;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
-pub enum JsonDropType {
-    Simple,
+pub enum JsonDropAsset {
     NFT(JsonNFTData),
     FT(FTData),
     FC(FCData),
@@ -21,17 +20,27 @@ pub struct JsonDrop {
     // Balance for all keys of this drop. Can be 0 if specified.
     pub balance: U128,
 
-    // Every drop must have a type
-    pub drop_type: JsonDropType,
+    // Assets sent alongside the balance with every claim
+    pub assets: Vec<JsonDropAsset>,
 
     // The drop as a whole can have a config as well
     pub drop_config: DropConfig,
 
-    // How many claims
+    // How many claims are left across all keys
     pub num_claims_registered: u64,
 
     // Ensure this drop can only be used when the function has the required gas to attach
     pub required_gas_attached: Gas,
+
+    // How many claims have passed every check across all keys
+    pub total_claims: u64,
+
+    // How many accounts are in the drop's allowlist and the suffix new accounts are allowed to have
+    pub allowlist_len: u64,
+    pub new_account_suffix: Option<String>,
+
+    // Has the funder paused claims on the drop
+    pub paused: bool,
 }
 
 /// Keep track of nft data 
@@ -41,7 +50,8 @@ pub struct JsonNFTData {
     pub nft_sender: AccountId,
     pub nft_contract: AccountId,
     pub longest_token_id: String,
-    pub storage_for_longest: U128
+    pub storage_for_longest: U128,
+    pub num_claims_registered: u64
 }
 
 /// Struct to return in views to query for specific data related to an access key.
@@ -52,18 +62,49 @@ pub struct JsonKeyInfo {
     pub drop_id: DropId,
     pub pk: PublicKey,
     pub key_usage: KeyUsage,
+    // Which use of the key will be claimed next (starting at 0)
+    pub next_use_index: u64,
+    // Methods that each function call asset will call on the next claim. None if the function call is skipped for that use.
+    pub next_fc_methods: Vec<Option<Vec<MethodData>>>,
+    // First block at which the start block and block interval allow the key to be used. None if the drop's end block has passed.
+    pub next_eligible_block: Option<u64>,
+    // Can the key still be used in the current epoch
+    pub claimable_this_epoch: bool,
     // Funder of this specific drop
     pub funder_id: AccountId,
     // Balance for all linkdrops of this drop
     pub balance: U128,
 
-    // Every drop must have a type
-    pub drop_type: JsonDropType,
+    // Assets sent alongside the balance with every claim
+    pub assets: Vec<JsonDropAsset>,
 
     // The drop as a whole can have a config as well
     pub drop_config: DropConfig,
 }
 
+/// Convert the assets stored in a drop into their JSON representation
+fn json_assets(assets: Vec<DropAsset>) -> Vec<JsonDropAsset> {
+    assets.into_iter().map(|asset| match asset {
+        DropAsset::FC(data) => {
+            JsonDropAsset::FC(data)
+        },
+        DropAsset::NFT(data) => {
+            JsonDropAsset::NFT(
+                JsonNFTData{
+                    nft_contract: data.nft_contract,
+                    nft_sender: data.nft_sender,
+                    longest_token_id: data.longest_token_id,
+                    storage_for_longest: U128(data.storage_for_longest),
+                    num_claims_registered: data.num_claims_registered
+                }
+            )
+        },
+        DropAsset::FT(data) => {
+            JsonDropAsset::FT(data)
+        }
+    }).collect()
+}
+
 #[near_bindgen]
 impl DropZone {
     /// Returns the balance associated with given key. This is used by the NEAR wallet to display the amount of the linkdrop
@@ -112,31 +153,33 @@ impl DropZone {
     ) -> JsonKeyInfo {
         let drop_id = self.drop_id_for_pk.get(&key).expect("no drop ID found for key");
         let drop = self.drop_for_id.get(&drop_id).expect("no drop found for drop ID");
-        let key_usage = drop.pks.get(&key).unwrap();
-
-        let drop_type: JsonDropType = match drop.drop_type {
-            DropType::FC(data) => {
-                JsonDropType::FC(data)
-            },
-            DropType::NFT(data) => {
-                 JsonDropType::NFT(
-                    JsonNFTData{
-                        nft_contract: data.nft_contract,
-                        nft_sender: data.nft_sender,
-                        longest_token_id: data.longest_token_id,
-                        storage_for_longest: U128(data.storage_for_longest)
-                    }
-                )
-            },
-            DropType::FT(data) => {
-                JsonDropType::FT(data)
-            },
-            _simple => {JsonDropType::Simple}
-        };
+        let key_usage = drop.get_key_usage(&key).unwrap();
+
+        // Get the methods each function call asset will call on the next use of the key
+        let next_use_index = drop.drop_config.max_claims_per_key - key_usage.num_uses;
+        let next_fc_methods = drop.assets.iter().filter_map(|asset| match asset {
+            DropAsset::FC(data) => Some(data.methods_for_use(next_use_index)),
+            _ => None
+        }).collect();
+
+        // Get the next block the key can be used at given the start block and block interval
+        let current_block = env::block_height();
+        let next_eligible_block = Some(current_block)
+            .max(drop.drop_config.start_block)
+            .max(drop.drop_config.usage_interval_blocks.map(|interval| key_usage.last_used_block + interval))
+            .filter(|block| drop.drop_config.end_block.map(|end| *block <= end).unwrap_or(true));
+        let claims_in_epoch = if key_usage.last_used_epoch == env::epoch_height() {key_usage.claims_in_epoch} else {0};
+        let claimable_this_epoch = drop.drop_config.max_claims_per_epoch.map(|max| claims_in_epoch < max).unwrap_or(true);
+
+        let assets = json_assets(drop.assets);
 
         JsonKeyInfo { 
             key_usage,
-            drop_type,
+            next_use_index,
+            next_fc_methods,
+            next_eligible_block,
+            claimable_this_epoch,
+            assets,
             drop_config: drop.drop_config,
             drop_id,
             pk: key,
@@ -152,34 +195,20 @@ impl DropZone {
     ) -> JsonDrop {
         let drop = self.drop_for_id.get(&drop_id).expect("no drop found for drop ID");
         
-        let drop_type: JsonDropType = match drop.drop_type {
-            DropType::FC(data) => {
-                JsonDropType::FC(data)
-            },
-            DropType::NFT(data) => {
-                 JsonDropType::NFT(
-                    JsonNFTData{
-                        nft_contract: data.nft_contract,
-                        nft_sender: data.nft_sender,
-                        longest_token_id: data.longest_token_id,
-                        storage_for_longest: U128(data.storage_for_longest)
-                    }
-                )
-            },
-            DropType::FT(data) => {
-                JsonDropType::FT(data)
-            },
-            _simple => {JsonDropType::Simple}
-        };
+        let assets = json_assets(drop.assets);
 
         JsonDrop { 
             drop_id,
             funder_id: drop.funder_id,
             balance: drop.balance,
-            drop_type,
+            assets,
             drop_config: drop.drop_config,
             num_claims_registered: drop.num_claims_registered,
             required_gas_attached: drop.required_gas_attached,
+            total_claims: drop.total_claims,
+            allowlist_len: drop.allowlist_len,
+            new_account_suffix: drop.new_account_suffix,
+            paused: drop.paused,
         }
     }
 
@@ -221,13 +250,13 @@ impl DropZone {
     ) -> u64 {
         //get the set of drops for the passed in funder
         let drops_for_owner = self.drop_ids_for_funder.get(&account_id);
-        env::log_str(&format!("Drops: {:?}", drops_for_owner));
+        debug_log!("Drops: {:?}", drops_for_owner);
 
         //if there is some set of drops, we'll iterate through and collect all the keys
         if let Some(drops_for_owner) = drops_for_owner {
             let mut supply = 0;
             for id in drops_for_owner.iter() {
-                env::log_str(&format!("ID: {:?}", id));
+                debug_log!("ID: {:?}", id);
                 supply += self.drop_for_id.get(&id).unwrap().pks.len();
             }
 
@@ -280,33 +309,38 @@ impl DropZone {
                 // Collect all JsonDrops into a vector and return it
                 .collect()
         } else {
-            return vec![];
+            vec![]
         }
     }
 
-    /// Returns if the current token ID lives in the NFT drop
+    /// Returns if the current token ID lives in any NFT asset of the drop
     pub fn drop_contains_token_id(
         &self, 
         drop_id: DropId,
         token_id: String
     ) -> bool {
         let drop = self.drop_for_id.get(&drop_id).expect("no drop found");
-        if let DropType::NFT(nft_data) = drop.drop_type {
-            nft_data.token_ids.contains(&token_id)   
-        } else {
-            false
-        }
+        drop.assets.iter().any(|asset| match asset {
+            DropAsset::NFT(nft_data) => nft_data.token_ids.contains(&token_id),
+            _ => false
+        })
     }
 
-    /// Paginate through token IDs in a drop
+    /// Paginate through token IDs in an NFT asset of a drop. Defaults to the first NFT asset if no index is passed in.
     pub fn get_token_ids_for_drop(
         &self, 
         drop_id: DropId,
         from_index: Option<U128>, 
-        limit: Option<u64>
+        limit: Option<u64>,
+        asset_index: Option<u64>
     ) -> Vec<String> {
         let drop = self.drop_for_id.get(&drop_id).expect("no drop found");
-        if let DropType::NFT(nft_data) = drop.drop_type {
+        let nft_asset = match asset_index {
+            Some(index) => drop.assets.into_iter().nth(index as usize),
+            None => drop.assets.into_iter().find(|asset| matches!(asset, DropAsset::NFT(_)))
+        };
+
+        if let Some(DropAsset::NFT(nft_data)) = nft_asset {
             let token_ids = nft_data.token_ids;
 
             // Where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
@@ -321,11 +355,21 @@ impl DropZone {
                 //since we turned the keys into an iterator, we need to turn it back into a vector to return
                 .collect()
         } else {
-            return vec![];
+            vec![]
         }
     }
 
 
+    /// Returns how many times an account has claimed from a drop. Only tracked if the drop limits claims per account.
+    pub fn get_claims_for_account(
+        &self,
+        drop_id: DropId,
+        account_id: AccountId
+    ) -> u64 {
+        let drop = self.drop_for_id.get(&drop_id).expect("no drop found");
+        drop.claims_per_account.get(&account_id).unwrap_or(0)
+    }
+
     /// Returns the current nonce on the contract
     pub fn get_nonce(&self) -> u128 {
         self.nonce
@@ -125,7 +125,7 @@ async function start() {
 			{
 				public_keys: pubKeys,
 				balance: parseNearAmount(LINKDROP_NEAR_AMOUNT),
-				ft_data,
+				ft_data: [ft_data],
 				drop_config
 			}, 
 			"300000000000000"
@@ -134,13 +134,17 @@ async function start() {
 
 	try {
 		let fc_data = {
-			receiver: "nft.examples.testnet",
-			method: "nft_mint",
-			args: JSON.stringify({
-				token_id: pubKeys[0],
-				metadata: METADATA,
-			}),
-			deposit: parseNearAmount("1"),
+			methods: [
+				{
+					receiver: "nft.examples.testnet",
+					method: "nft_mint",
+					args: JSON.stringify({
+						token_id: pubKeys[0],
+						metadata: METADATA,
+					}),
+					deposit: parseNearAmount("1"),
+				}
+			],
 			refund_to_deposit: true,
 			claimed_account_field: "receiver_id",
 			// How much GAS should be attached to the function call. Cannot be greater than ATTACHED_GAS_FROM_WALLET - GAS_OFFSET_IF_FC_EXECUTE (90 TGas).
@@ -153,7 +157,7 @@ async function start() {
 			{
 				public_keys: pubKeys,
 				balance: parseNearAmount(LINKDROP_NEAR_AMOUNT),
-				fc_data,
+				fc_data: [fc_data],
 				drop_config
 			}, 
 			"300000000000000"
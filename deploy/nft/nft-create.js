@@ -145,7 +145,7 @@ async function start() {
 			{
 				public_keys: pubKeys,
 				balance: parseNearAmount(LINKDROP_NEAR_AMOUNT),
-				nft_data,
+				nft_data: [nft_data],
 				drop_config
 			}, 
 			"300000000000000"
@@ -0,0 +1,204 @@
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use workspaces::network::Sandbox;
+use workspaces::result::ExecutionFinalResult;
+use workspaces::types::{KeyType, SecretKey};
+use workspaces::{Account, AccountId, Contract, Worker};
+
+pub const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+/// GAS that claims must be signed with when the drop has at most one asset
+pub const CLAIM_GAS: u64 = 100_000_000_000_000;
+/// Storage balance that the mock FT contract requires to register an account
+pub const FT_STORAGE: u128 = 1_250_000_000_000_000_000_000;
+/// What the funder deposits into their balance on the contract when the environment is set up
+pub const FUNDER_DEPOSIT: u128 = 20 * ONE_NEAR;
+
+/// Contract, mocks and accounts deployed to a fresh sandbox
+pub struct TestEnv {
+    pub worker: Worker<Sandbox>,
+    // The linkdrop proxy contract being tested
+    pub dropzone: Contract,
+    // Mock of the `near` / `testnet` linkdrop that new accounts are created with
+    pub linkdrop: Contract,
+    // Mock NEP-141 contract
+    pub ft: Contract,
+    // Mock NEP-171 contract
+    pub nft: Contract,
+    // Mock price oracle for fee tokens
+    pub oracle: Contract,
+    pub funder: Account,
+    pub claimer: Account,
+}
+
+async fn deploy(worker: &Worker<Sandbox>, project: &str) -> anyhow::Result<Contract> {
+    let wasm = workspaces::compile_project(&format!("{}/../{}", env!("CARGO_MANIFEST_DIR"), project)).await?;
+    Ok(worker.dev_deploy(&wasm).await?)
+}
+
+impl TestEnv {
+    /// Deploy and initialize every contract and fund the funder's balance on the linkdrop proxy
+    pub async fn init() -> anyhow::Result<Self> {
+        let worker = workspaces::sandbox().await?;
+        let dropzone = deploy(&worker, "contract").await?;
+        let linkdrop = deploy(&worker, "mocks/linkdrop").await?;
+        let ft = deploy(&worker, "mocks/fungible-token").await?;
+        let nft = deploy(&worker, "mocks/non-fungible-token").await?;
+        let oracle = deploy(&worker, "mocks/fee-oracle").await?;
+
+        dropzone
+            .call("new")
+            .args_json(json!({ "linkdrop_contract": linkdrop.id(), "owner_id": dropzone.id() }))
+            .transact()
+            .await?
+            .into_result()?;
+        ft.call("new").transact().await?.into_result()?;
+        nft.call("new").transact().await?.into_result()?;
+
+        let root = worker.root_account()?;
+        let funder = root
+            .create_subaccount("funder")
+            .initial_balance(50 * ONE_NEAR)
+            .transact()
+            .await?
+            .into_result()?;
+        let claimer = root
+            .create_subaccount("claimer")
+            .initial_balance(10 * ONE_NEAR)
+            .transact()
+            .await?
+            .into_result()?;
+
+        funder
+            .call(dropzone.id(), "add_to_balance")
+            .deposit(FUNDER_DEPOSIT)
+            .transact()
+            .await?
+            .into_result()?;
+
+        Ok(Self { worker, dropzone, linkdrop, ft, nft, oracle, funder, claimer })
+    }
+
+    /// Create a drop with `num_keys` new keys as the funder. `args` are the rest of the arguments to `create_drop`.
+    pub async fn create_drop(&self, num_keys: usize, args: Value) -> anyhow::Result<(u128, Vec<SecretKey>)> {
+        let keys = new_keys(num_keys);
+        let drop_id = self.create_drop_with_keys(&keys, args).await?;
+        Ok((drop_id, keys))
+    }
+
+    /// Create a drop for keys that were generated up front (e.g. to hash their passwords)
+    pub async fn create_drop_with_keys(&self, keys: &[SecretKey], mut args: Value) -> anyhow::Result<u128> {
+        args["public_keys"] = json!(keys.iter().map(public_key).collect::<Vec<_>>());
+
+        let drop_id = self
+            .funder
+            .call(self.dropzone.id(), "create_drop")
+            .args_json(args)
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?
+            .json::<u128>()?;
+        Ok(drop_id)
+    }
+
+    /// Sign a call to the contract with a drop's access key
+    pub async fn call_with_key(&self, key: &SecretKey, method: &str, args: Value, gas: u64) -> anyhow::Result<ExecutionFinalResult> {
+        let key_account = Account::from_secret_key(self.dropzone.id().clone(), key.clone(), &self.worker);
+        Ok(key_account.call(self.dropzone.id(), method).args_json(args).gas(gas).transact().await?)
+    }
+
+    /// Claim a key to an existing account with the GAS from the wallet
+    pub async fn claim(&self, key: &SecretKey, account_id: &AccountId) -> anyhow::Result<ExecutionFinalResult> {
+        self.call_with_key(key, "claim", json!({ "account_id": account_id }), CLAIM_GAS).await
+    }
+
+    /// Claim a key by creating a new account with the GAS from the wallet
+    pub async fn create_account_and_claim(&self, key: &SecretKey, new_account_id: &AccountId) -> anyhow::Result<ExecutionFinalResult> {
+        let new_public_key = public_key(&SecretKey::from_random(KeyType::ED25519));
+        self.call_with_key(
+            key,
+            "create_account_and_claim",
+            json!({ "new_account_id": new_account_id, "new_public_key": new_public_key }),
+            CLAIM_GAS,
+        )
+        .await
+    }
+
+    /// ID for a new account that the mock linkdrop is able to create
+    pub fn new_account_id(&self, name: &str) -> AccountId {
+        format!("{}.{}", name, self.linkdrop.id()).parse().unwrap()
+    }
+
+    /// Call a view method on the linkdrop proxy
+    pub async fn view(&self, method: &str, args: Value) -> anyhow::Result<Value> {
+        Ok(self.dropzone.view(method).args_json(args).await?.json()?)
+    }
+
+    /// Balance of an account on the linkdrop proxy
+    pub async fn user_balance(&self, account_id: &AccountId) -> anyhow::Result<u128> {
+        let balance = self.view("get_user_balance", json!({ "account_id": account_id })).await?;
+        Ok(balance.as_str().unwrap().parse()?)
+    }
+
+    pub async fn key_supply_for_drop(&self, drop_id: u128) -> anyhow::Result<u64> {
+        Ok(self.view("key_supply_for_drop", json!({ "drop_id": drop_id })).await?.as_u64().unwrap())
+    }
+
+    /// $NEAR held by an account on chain
+    pub async fn near_balance(&self, account_id: &AccountId) -> anyhow::Result<u128> {
+        Ok(self.worker.view_account(account_id).await?.balance)
+    }
+
+    /// Register an account on the mock FT contract
+    pub async fn register_ft(&self, account_id: &AccountId) -> anyhow::Result<()> {
+        self.funder
+            .call(self.ft.id(), "storage_deposit")
+            .args_json(json!({ "account_id": account_id }))
+            .deposit(FT_STORAGE)
+            .transact()
+            .await?
+            .into_result()?;
+        Ok(())
+    }
+
+    pub async fn ft_balance_of(&self, account_id: &AccountId) -> anyhow::Result<u128> {
+        let balance: Value = self.ft.view("ft_balance_of").args_json(json!({ "account_id": account_id })).await?.json()?;
+        Ok(balance.as_str().unwrap().parse()?)
+    }
+
+    /// Current owner of a token on the mock NFT contract
+    pub async fn nft_owner(&self, token_id: &str) -> anyhow::Result<Option<String>> {
+        let token: Value = self.nft.view("nft_token").args_json(json!({ "token_id": token_id })).await?.json()?;
+        Ok(token["owner_id"].as_str().map(|owner_id| owner_id.to_string()))
+    }
+}
+
+/// Drop config with a single claim per key and no other restrictions
+pub fn simple_config() -> Value {
+    json!({ "max_claims_per_key": 1 })
+}
+
+pub fn new_keys(num_keys: usize) -> Vec<SecretKey> {
+    (0..num_keys).map(|_| SecretKey::from_random(KeyType::ED25519)).collect()
+}
+
+pub fn public_key(key: &SecretKey) -> String {
+    key.public_key().to_string()
+}
+
+/// Base64 hash that the contract stores for a per-key password
+pub fn password_hash(password: &str, key: &SecretKey) -> String {
+    base64::encode(Sha256::digest(format!("{}{}", password, public_key(key)).as_bytes()))
+}
+
+/// Data of every NEP-297 event with the given name logged anywhere in the transaction
+pub fn events(result: &ExecutionFinalResult, name: &str) -> Vec<Value> {
+    result
+        .logs()
+        .into_iter()
+        .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+        .filter_map(|log| serde_json::from_str::<Value>(log).ok())
+        .filter(|event| event["event"] == name)
+        .map(|event| event["data"].clone())
+        .collect()
+}
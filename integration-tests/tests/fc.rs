@@ -0,0 +1,90 @@
+use integration_tests::*;
+use serde_json::{json, Value};
+
+/// Drop that mints an NFT to whoever claims it
+fn fc_drop_args(env: &TestEnv, fc_extra: Value) -> Value {
+    let mut fc_data = json!({
+        "methods": [{
+            "receiver": env.nft.id(),
+            "method": "nft_mint",
+            "args": json!({ "token_id": "fc-token" }).to_string(),
+            "deposit": "0",
+        }],
+        "claimed_account_field": "receiver_id",
+    });
+    for (field, value) in fc_extra.as_object().unwrap() {
+        fc_data[field] = value.clone();
+    }
+
+    json!({
+        "balance": (ONE_NEAR / 10).to_string(),
+        "fc_data": [fc_data],
+        "drop_config": simple_config(),
+    })
+}
+
+#[tokio::test]
+async fn claim_calls_method_with_claimed_account() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env.create_drop(1, fc_drop_args(&env, json!({}))).await?;
+
+    let result = env.claim(&keys[0], env.claimer.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    assert_eq!(env.nft_owner("fc-token").await?.as_deref(), Some(env.claimer.id().as_str()));
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_account_and_claim_calls_method_with_new_account() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (_, keys) = env.create_drop(1, fc_drop_args(&env, json!({}))).await?;
+    let new_account_id = env.new_account_id("alice");
+
+    let result = env.create_account_and_claim(&keys[0], &new_account_id).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    assert_eq!(env.nft_owner("fc-token").await?.as_deref(), Some(new_account_id.as_str()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_account_creation_skips_function_call() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (_, keys) = env.create_drop(1, fc_drop_args(&env, json!({}))).await?;
+    let taken = env.linkdrop.as_account().create_subaccount("taken").transact().await?.into_result()?;
+    let funder_balance_before = env.user_balance(env.funder.id()).await?;
+
+    let result = env.create_account_and_claim(&keys[0], taken.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    assert_eq!(env.nft_owner("fc-token").await?, None);
+    assert!(env.user_balance(env.funder.id()).await? - funder_balance_before >= ONE_NEAR / 10);
+    Ok(())
+}
+
+#[tokio::test]
+async fn claimer_can_set_allowed_fields() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let user_args = json!({ "user_args": [{ "field": "token_id", "arg_type": "String", "max_len": 16 }] });
+    let (_, keys) = env.create_drop(2, fc_drop_args(&env, user_args)).await?;
+
+    // The claimer picks the token ID but can't override the injected receiver
+    let fc_args = json!({ "token_id": "picked", "receiver_id": env.funder.id() }).to_string();
+    let result = env
+        .call_with_key(&keys[0], "claim", json!({ "account_id": env.claimer.id(), "fc_args": fc_args }), CLAIM_GAS)
+        .await?;
+    assert!(result.is_success(), "{:?}", result);
+    assert_eq!(env.nft_owner("picked").await?.as_deref(), Some(env.claimer.id().as_str()));
+
+    // Values outside the funder's limits reject the function call entirely
+    let fc_args = json!({ "token_id": "a-token-id-that-is-too-long" }).to_string();
+    let result = env
+        .call_with_key(&keys[1], "claim", json!({ "account_id": env.claimer.id(), "fc_args": fc_args }), CLAIM_GAS)
+        .await?;
+    assert!(result.is_success(), "{:?}", result);
+    assert_eq!(env.nft_owner("a-token-id-that-is-too-long").await?, None);
+    assert_eq!(env.nft_owner("fc-token").await?, None);
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use integration_tests::*;
+use serde_json::{json, Value};
+
+// 0.001 $NEAR per token so 1000 tokens are worth 1 $NEAR
+const RATE: u128 = ONE_NEAR / 1000;
+const FEE_TOKENS: u128 = 1000;
+
+/// Whitelist the mock FT contract as a fee token and register the contract on it
+async fn set_fee_token(env: &TestEnv, fee_token: Value) -> anyhow::Result<()> {
+    env.dropzone
+        .call("set_fee_token")
+        .args_json(json!({ "token_id": env.ft.id(), "fee_token": fee_token }))
+        .transact()
+        .await?
+        .into_result()?;
+    env.register_ft(env.dropzone.id()).await?;
+    Ok(())
+}
+
+/// Mint FTs to the funder and send them to the contract to pay for fees
+async fn pay_fees(env: &TestEnv, amount: u128) -> anyhow::Result<()> {
+    env.funder
+        .call(env.ft.id(), "ft_mint")
+        .args_json(json!({ "account_id": env.funder.id(), "amount": amount.to_string() }))
+        .transact()
+        .await?
+        .into_result()?;
+    env.funder
+        .call(env.ft.id(), "ft_transfer_call")
+        .args_json(json!({ "receiver_id": env.dropzone.id(), "amount": amount.to_string(), "msg": json!({ "v1": { "pay_fees": {} } }).to_string() }))
+        .deposit(1)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+async fn fee_credit(env: &TestEnv) -> anyhow::Result<u128> {
+    let credit = env.view("get_fee_credit", json!({ "account_id": env.funder.id() })).await?;
+    Ok(credit.as_str().unwrap().parse()?)
+}
+
+#[tokio::test]
+async fn fixed_rate_payment_covers_drop_fees() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    set_fee_token(&env, json!({ "rate": RATE.to_string() })).await?;
+    pay_fees(&env, FEE_TOKENS).await?;
+
+    // The storage for the credit record is taken out of the credit
+    let credit = fee_credit(&env).await?;
+    assert!(credit > 0 && credit < ONE_NEAR);
+    assert_eq!(env.ft_balance_of(env.dropzone.id()).await?, FEE_TOKENS);
+    assert_eq!(env.view("get_token_fees_collected", json!({ "token_id": env.ft.id() })).await?, json!(FEE_TOKENS.to_string()));
+
+    let fees_before = env.view("get_fees_collected", json!({})).await?;
+    env.create_drop(1, json!({ "balance": (ONE_NEAR / 10).to_string(), "drop_config": simple_config() })).await?;
+
+    // The drop and key fees came out of the credit rather than the $NEAR balance
+    assert!(fee_credit(&env).await? < credit);
+    assert_eq!(env.view("get_fees_collected", json!({})).await?, fees_before);
+    Ok(())
+}
+
+#[tokio::test]
+async fn oracle_rate_is_used_for_payment() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    env.oracle
+        .call("set_rate")
+        .args_json(json!({ "token_id": env.ft.id(), "rate": RATE.to_string() }))
+        .transact()
+        .await?
+        .into_result()?;
+    set_fee_token(&env, json!({ "oracle_id": env.oracle.id() })).await?;
+    pay_fees(&env, FEE_TOKENS).await?;
+
+    assert!(fee_credit(&env).await? > 0);
+    assert_eq!(env.ft_balance_of(env.dropzone.id()).await?, FEE_TOKENS);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_oracle_call_refunds_tokens() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    // The oracle has no rate for the token so the query fails
+    set_fee_token(&env, json!({ "oracle_id": env.oracle.id() })).await?;
+    pay_fees(&env, FEE_TOKENS).await?;
+
+    assert_eq!(fee_credit(&env).await?, 0);
+    assert_eq!(env.ft_balance_of(env.funder.id()).await?, FEE_TOKENS);
+    assert_eq!(env.ft_balance_of(env.dropzone.id()).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn owner_withdraws_token_fees() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    set_fee_token(&env, json!({ "rate": RATE.to_string() })).await?;
+    pay_fees(&env, FEE_TOKENS).await?;
+    env.register_ft(env.claimer.id()).await?;
+
+    let result = env
+        .dropzone
+        .call("withdraw_token_fees")
+        .args_json(json!({ "token_id": env.ft.id(), "withdraw_to": env.claimer.id() }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    assert_eq!(env.ft_balance_of(env.claimer.id()).await?, FEE_TOKENS);
+    assert_eq!(env.view("get_token_fees_collected", json!({ "token_id": env.ft.id() })).await?, json!("0"));
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use integration_tests::*;
+use serde_json::{json, Value};
+use workspaces::AccountId;
+
+const FT_PER_CLAIM: u128 = 10;
+
+fn ft_drop_args(ft_contract: &AccountId, ft_sender: &AccountId) -> Value {
+    json!({
+        "balance": (ONE_NEAR / 10).to_string(),
+        "ft_data": [{ "ft_contract": ft_contract, "ft_sender": ft_sender, "ft_balance": FT_PER_CLAIM.to_string() }],
+        "drop_config": simple_config(),
+    })
+}
+
+/// Mint FTs to the funder and send them to the contract to register claims for the drop
+async fn fund_drop(env: &TestEnv, drop_id: u128, amount: u128) -> anyhow::Result<()> {
+    env.register_ft(env.dropzone.id()).await?;
+    env.funder
+        .call(env.ft.id(), "ft_mint")
+        .args_json(json!({ "account_id": env.funder.id(), "amount": amount.to_string() }))
+        .transact()
+        .await?
+        .into_result()?;
+    env.funder
+        .call(env.ft.id(), "ft_transfer_call")
+        .args_json(json!({ "receiver_id": env.dropzone.id(), "amount": amount.to_string(), "msg": drop_id.to_string() }))
+        .deposit(1)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_sends_fts_and_registers_storage() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env.create_drop(2, ft_drop_args(env.ft.id(), env.funder.id())).await?;
+    fund_drop(&env, drop_id, 2 * FT_PER_CLAIM).await?;
+    assert_eq!(env.ft_balance_of(env.dropzone.id()).await?, 2 * FT_PER_CLAIM);
+
+    let result = env.claim(&keys[0], env.claimer.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+    assert_eq!(env.ft_balance_of(env.claimer.id()).await?, FT_PER_CLAIM);
+
+    let new_account_id = env.new_account_id("alice");
+    let result = env.create_account_and_claim(&keys[1], &new_account_id).await?;
+    assert!(result.is_success(), "{:?}", result);
+    assert_eq!(env.ft_balance_of(&new_account_id).await?, FT_PER_CLAIM);
+    assert_eq!(env.ft_balance_of(env.dropzone.id()).await?, 0);
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_without_registered_fts_is_rejected() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env.create_drop(1, ft_drop_args(env.ft.id(), env.funder.id())).await?;
+
+    let result = env.claim(&keys[0], env.claimer.id()).await?;
+    let failures = events(&result, "claim_failure");
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0]["reason"], "no claims registered");
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_account_creation_returns_fts_to_sender() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env.create_drop(1, ft_drop_args(env.ft.id(), env.funder.id())).await?;
+    fund_drop(&env, drop_id, FT_PER_CLAIM).await?;
+    let taken = env.linkdrop.as_account().create_subaccount("taken").transact().await?.into_result()?;
+    let funder_balance_before = env.user_balance(env.funder.id()).await?;
+
+    let result = env.create_account_and_claim(&keys[0], taken.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    // The FTs go back to the sender and the $NEAR balance back to the funder's balance
+    assert_eq!(env.ft_balance_of(env.funder.id()).await?, FT_PER_CLAIM);
+    assert_eq!(env.ft_balance_of(taken.id()).await?, 0);
+    assert!(env.user_balance(env.funder.id()).await? - funder_balance_before >= ONE_NEAR / 10);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_storage_check_reverts_drop_creation() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let funder_balance_before = env.user_balance(env.funder.id()).await?;
+
+    // The linkdrop mock doesn't implement storage_balance_bounds so resolve_storage_check can't get the storage
+    let (drop_id, _) = env.create_drop(1, ft_drop_args(env.linkdrop.id(), env.funder.id())).await?;
+
+    assert_eq!(env.user_balance(env.funder.id()).await?, funder_balance_before);
+    assert_eq!(env.view("drop_supply_for_funder", json!({ "account_id": env.funder.id() })).await?, json!(0));
+    assert!(env.dropzone.view("get_drop_information").args_json(json!({ "drop_id": drop_id })).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_check_reverts_when_balance_cannot_cover_ft_storage() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+
+    // Find out what an identical drop costs up front. The FT storage for each claim is charged on top in resolve_storage_check.
+    let result = env
+        .funder
+        .call(env.dropzone.id(), "create_drop")
+        .args_json({
+            let mut args = ft_drop_args(env.ft.id(), env.funder.id());
+            args["public_keys"] = json!([public_key(&new_keys(1)[0])]);
+            args
+        })
+        .max_gas()
+        .transact()
+        .await?;
+    let creations = events(&result, "drop_creation");
+    let required_deposit: u128 = creations[0]["required_deposit"].as_str().unwrap().parse()?;
+
+    // Leave enough to create the drop but not enough for the FT storage
+    env.funder.call(env.dropzone.id(), "withdraw_from_balance").transact().await?.into_result()?;
+    let funder_balance = required_deposit + FT_STORAGE / 2;
+    env.funder
+        .call(env.dropzone.id(), "add_to_balance")
+        .deposit(funder_balance)
+        .transact()
+        .await?
+        .into_result()?;
+
+    let (drop_id, _) = env.create_drop(1, ft_drop_args(env.ft.id(), env.funder.id())).await?;
+
+    assert_eq!(env.user_balance(env.funder.id()).await?, funder_balance);
+    assert!(env.dropzone.view("get_drop_information").args_json(json!({ "drop_id": drop_id })).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_is_created_from_a_transfer() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    env.register_ft(env.dropzone.id()).await?;
+    let keys = new_keys(2);
+    let amount = 2 * FT_PER_CLAIM + FT_PER_CLAIM / 2;
+    env.funder
+        .call(env.ft.id(), "ft_mint")
+        .args_json(json!({ "account_id": env.funder.id(), "amount": amount.to_string() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let msg = json!({ "v1": { "create_drop": {
+        "public_keys": keys.iter().map(public_key).collect::<Vec<_>>(),
+        "balance": (ONE_NEAR / 10).to_string(),
+        "ft_balance": FT_PER_CLAIM.to_string(),
+        "drop_config": simple_config(),
+    } } });
+    let result = env
+        .funder
+        .call(env.ft.id(), "ft_transfer_call")
+        .args_json(json!({ "receiver_id": env.dropzone.id(), "amount": amount.to_string(), "msg": msg.to_string() }))
+        .deposit(1)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_success(), "{:?}", result);
+    let drop_id: u128 = events(&result, "drop_creation")[0]["drop_id"].as_str().unwrap().parse()?;
+
+    // Only whole claims are kept and the rest goes back to the funder
+    assert_eq!(env.ft_balance_of(env.dropzone.id()).await?, 2 * FT_PER_CLAIM);
+    assert_eq!(env.ft_balance_of(env.funder.id()).await?, FT_PER_CLAIM / 2);
+
+    let result = env.claim(&keys[0], env.claimer.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+    assert_eq!(env.ft_balance_of(env.claimer.id()).await?, FT_PER_CLAIM);
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 1);
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use integration_tests::*;
+use serde_json::{json, Value};
+
+const TOKEN_ID: &str = "token-1";
+
+fn nft_drop_args(env: &TestEnv) -> Value {
+    json!({
+        "balance": (ONE_NEAR / 10).to_string(),
+        "nft_data": [{ "nft_sender": env.funder.id(), "nft_contract": env.nft.id(), "longest_token_id": TOKEN_ID }],
+        "drop_config": simple_config(),
+    })
+}
+
+/// Mint an NFT to the funder and send it to the contract to register a claim for the drop
+async fn fund_drop(env: &TestEnv, drop_id: u128) -> anyhow::Result<()> {
+    env.funder
+        .call(env.nft.id(), "nft_mint")
+        .args_json(json!({ "token_id": TOKEN_ID, "receiver_id": env.funder.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+    env.funder
+        .call(env.nft.id(), "nft_transfer_call")
+        .args_json(json!({ "receiver_id": env.dropzone.id(), "token_id": TOKEN_ID, "msg": drop_id.to_string() }))
+        .deposit(1)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_sends_nft() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env.create_drop(1, nft_drop_args(&env)).await?;
+    fund_drop(&env, drop_id).await?;
+    assert_eq!(env.nft_owner(TOKEN_ID).await?.as_deref(), Some(env.dropzone.id().as_str()));
+
+    let result = env.claim(&keys[0], env.claimer.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    assert_eq!(env.nft_owner(TOKEN_ID).await?.as_deref(), Some(env.claimer.id().as_str()));
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_account_and_claim_sends_nft_to_new_account() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env.create_drop(1, nft_drop_args(&env)).await?;
+    fund_drop(&env, drop_id).await?;
+    let new_account_id = env.new_account_id("alice");
+
+    let result = env.create_account_and_claim(&keys[0], &new_account_id).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    assert_eq!(env.nft_owner(TOKEN_ID).await?.as_deref(), Some(new_account_id.as_str()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejected_transfer_returns_nft_to_sender() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env.create_drop(1, nft_drop_args(&env)).await?;
+    fund_drop(&env, drop_id).await?;
+
+    // The NFT contract refuses to send the token to the claimer so nft_resolve_transfer sends it back to the sender
+    env.funder
+        .call(env.nft.id(), "reject_receiver")
+        .args_json(json!({ "account_id": env.claimer.id() }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let result = env.claim(&keys[0], env.claimer.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    assert_eq!(env.nft_owner(TOKEN_ID).await?.as_deref(), Some(env.funder.id().as_str()));
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_account_creation_returns_nft_to_sender() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env.create_drop(1, nft_drop_args(&env)).await?;
+    fund_drop(&env, drop_id).await?;
+    let taken = env.linkdrop.as_account().create_subaccount("taken").transact().await?.into_result()?;
+    let funder_balance_before = env.user_balance(env.funder.id()).await?;
+
+    let result = env.create_account_and_claim(&keys[0], taken.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    assert_eq!(env.nft_owner(TOKEN_ID).await?.as_deref(), Some(env.funder.id().as_str()));
+    assert!(env.user_balance(env.funder.id()).await? - funder_balance_before >= ONE_NEAR / 10);
+    Ok(())
+}
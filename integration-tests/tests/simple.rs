@@ -0,0 +1,116 @@
+use integration_tests::*;
+use serde_json::json;
+
+#[tokio::test]
+async fn claim_sends_balance_to_existing_account() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env
+        .create_drop(1, json!({ "balance": ONE_NEAR.to_string(), "drop_config": simple_config() }))
+        .await?;
+    let balance_before = env.near_balance(env.claimer.id()).await?;
+    let funder_balance_before = env.user_balance(env.funder.id()).await?;
+
+    let result = env.claim(&keys[0], env.claimer.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+    assert_eq!(events(&result, "claim").len(), 1);
+
+    // The claimer receives the balance, the key is removed and the funder is refunded the storage that was freed
+    assert_eq!(env.near_balance(env.claimer.id()).await? - balance_before, ONE_NEAR);
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 0);
+    assert!(env.user_balance(env.funder.id()).await? > funder_balance_before);
+
+    // The key was deleted so it can't be used again
+    assert!(env.claim(&keys[0], env.claimer.id()).await.map(|result| result.is_failure()).unwrap_or(true));
+    Ok(())
+}
+
+#[tokio::test]
+async fn create_account_and_claim_creates_funded_account() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env
+        .create_drop(1, json!({ "balance": ONE_NEAR.to_string(), "drop_config": simple_config() }))
+        .await?;
+    let new_account_id = env.new_account_id("alice");
+
+    let result = env.create_account_and_claim(&keys[0], &new_account_id).await?;
+    assert!(result.is_success(), "{:?}", result);
+    assert!(events(&result, "funder_refund").iter().all(|refund| refund["amount"].as_str().unwrap().parse::<u128>().unwrap() < ONE_NEAR));
+
+    assert_eq!(env.near_balance(&new_account_id).await?, ONE_NEAR);
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_account_creation_refunds_funder_balance() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env
+        .create_drop(1, json!({ "balance": ONE_NEAR.to_string(), "drop_config": simple_config() }))
+        .await?;
+
+    // The account already exists so the linkdrop can't create it
+    let taken = env.linkdrop.as_account().create_subaccount("taken").transact().await?.into_result()?;
+    let funder_balance_before = env.user_balance(env.funder.id()).await?;
+
+    let result = env.create_account_and_claim(&keys[0], taken.id()).await?;
+    assert!(result.is_success(), "{:?}", result);
+
+    // The key is still used up but the balance goes back to the funder instead of being lost
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 0);
+    let refunds = events(&result, "funder_refund");
+    assert_eq!(refunds.len(), 1);
+    assert!(env.user_balance(env.funder.id()).await? - funder_balance_before >= ONE_NEAR);
+    Ok(())
+}
+
+#[tokio::test]
+async fn wrong_password_decrements_allowance_and_keeps_key() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let keys = new_keys(1);
+    let drop_id = env
+        .create_drop_with_keys(
+            &keys,
+            json!({
+                "balance": ONE_NEAR.to_string(),
+                "drop_config": simple_config(),
+                "passwords_per_key": [password_hash("hunter2", &keys[0])],
+            }),
+        )
+        .await?;
+    let balance_before = env.near_balance(env.claimer.id()).await?;
+
+    let result = env
+        .call_with_key(&keys[0], "claim", json!({ "account_id": env.claimer.id(), "password": "wrong" }), CLAIM_GAS)
+        .await?;
+    let failures = events(&result, "claim_failure");
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0]["reason"], "invalid password");
+    assert!(failures[0]["allowance_decremented"].as_str().unwrap().parse::<u128>()? > 0);
+
+    // Nothing was sent and the key can still be claimed with the right password
+    assert_eq!(env.near_balance(env.claimer.id()).await?, balance_before);
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 1);
+
+    let result = env
+        .call_with_key(&keys[0], "claim", json!({ "account_id": env.claimer.id(), "password": "hunter2" }), CLAIM_GAS)
+        .await?;
+    assert_eq!(events(&result, "claim").len(), 1);
+    assert_eq!(env.near_balance(env.claimer.id()).await? - balance_before, ONE_NEAR);
+    Ok(())
+}
+
+#[tokio::test]
+async fn claim_with_wrong_gas_is_rejected() -> anyhow::Result<()> {
+    let env = TestEnv::init().await?;
+    let (drop_id, keys) = env
+        .create_drop(1, json!({ "balance": ONE_NEAR.to_string(), "drop_config": simple_config() }))
+        .await?;
+
+    let result = env
+        .call_with_key(&keys[0], "claim", json!({ "account_id": env.claimer.id() }), CLAIM_GAS / 2)
+        .await?;
+    assert_eq!(events(&result, "claim_failure").len(), 1);
+    assert!(events(&result, "claim").is_empty());
+    assert_eq!(env.key_supply_for_drop(drop_id).await?, 1);
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{near_bindgen, AccountId, Balance};
+use std::collections::HashMap;
+
+/// Price oracle for fee tokens used by the integration tests. Anyone can set a rate.
+#[near_bindgen]
+#[derive(Default, BorshDeserialize, BorshSerialize)]
+pub struct MockFeeOracle {
+    rates: HashMap<AccountId, Balance>,
+}
+
+#[near_bindgen]
+impl MockFeeOracle {
+    /// Set the rate (in yoctoNEAR per smallest unit) quoted for a token
+    pub fn set_rate(&mut self, token_id: AccountId, rate: U128) {
+        self.rates.insert(token_id, rate.0);
+    }
+
+    /// Rate for a token. Panics if none has been set so the linkdrop proxy's failure path can be tested.
+    pub fn get_rate(&self, token_id: AccountId) -> U128 {
+        U128(*self.rates.get(&token_id).expect("no rate for token"))
+    }
+}
@@ -0,0 +1,135 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
+use near_sdk::{
+    assert_one_yocto, env, ext_contract, near_bindgen, require, AccountId, Balance, PanicOnDefault, Promise, PromiseOrValue,
+    PromiseResult,
+};
+
+/// Storage every account has to pay for before it can receive tokens
+const STORAGE_BALANCE: Balance = 1_250_000_000_000_000_000_000; // 0.00125 N
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,
+}
+
+// Only the generated module is called
+#[allow(dead_code)]
+#[ext_contract(ext_ft_receiver)]
+trait FungibleTokenReceiver {
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128>;
+}
+
+/// Minimal NEP-141 / NEP-145 token used by the integration tests. Anyone can mint.
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct MockFungibleToken {
+    balances: LookupMap<AccountId, Balance>,
+}
+
+#[near_bindgen]
+impl MockFungibleToken {
+    #[init]
+    pub fn new() -> Self {
+        Self { balances: LookupMap::new(b"b") }
+    }
+
+    /// Register the account if needed and give it `amount` new tokens
+    pub fn ft_mint(&mut self, account_id: AccountId, amount: U128) {
+        let balance = self.balances.get(&account_id).unwrap_or(0);
+        self.balances.insert(&account_id, &(balance + amount.0));
+    }
+
+    /// Register an account. Anything attached past the storage balance (or everything if already registered) is refunded.
+    #[payable]
+    pub fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
+        let _ = registration_only;
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let deposit = env::attached_deposit();
+
+        let refund = if self.balances.contains_key(&account_id) {
+            deposit
+        } else {
+            require!(deposit >= STORAGE_BALANCE, "attached deposit is less than the minimum storage balance");
+            self.balances.insert(&account_id, &0);
+            deposit - STORAGE_BALANCE
+        };
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+
+        StorageBalance { total: U128(STORAGE_BALANCE), available: U128(0) }
+    }
+
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds { min: U128(STORAGE_BALANCE), max: Some(U128(STORAGE_BALANCE)) }
+    }
+
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.balances
+            .contains_key(&account_id)
+            .then_some(StorageBalance { total: U128(STORAGE_BALANCE), available: U128(0) })
+    }
+
+    #[payable]
+    pub fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        let _ = memo;
+        self.internal_transfer(&env::predecessor_account_id(), &receiver_id, amount.0);
+    }
+
+    #[payable]
+    pub fn ft_transfer_call(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String) -> Promise {
+        assert_one_yocto();
+        let _ = memo;
+        let sender_id = env::predecessor_account_id();
+        self.internal_transfer(&sender_id, &receiver_id, amount.0);
+
+        ext_ft_receiver::ext(receiver_id.clone())
+            .ft_on_transfer(sender_id.clone(), amount, msg)
+            .then(Self::ext(env::current_account_id()).ft_resolve_transfer(sender_id, receiver_id, amount))
+    }
+
+    /// Return the tokens the receiver didn't use (or all of them if `ft_on_transfer` failed) to the sender
+    #[private]
+    pub fn ft_resolve_transfer(&mut self, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> U128 {
+        let unused = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<U128>(&value)
+                .map(|unused| unused.0.min(amount.0))
+                .unwrap_or(amount.0),
+            _ => amount.0,
+        };
+
+        let refund = unused.min(self.balances.get(&receiver_id).unwrap_or(0));
+        if refund > 0 {
+            self.internal_transfer(&receiver_id, &sender_id, refund);
+        }
+
+        U128(amount.0 - refund)
+    }
+
+    pub fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        U128(self.balances.get(&account_id).unwrap_or(0))
+    }
+
+    fn internal_transfer(&mut self, sender_id: &AccountId, receiver_id: &AccountId, amount: Balance) {
+        let sender_balance = self.balances.get(sender_id).expect("sender is not registered");
+        let receiver_balance = self.balances.get(receiver_id).expect("receiver is not registered");
+        require!(sender_balance >= amount, "not enough balance");
+
+        self.balances.insert(sender_id, &(sender_balance - amount));
+        self.balances.insert(receiver_id, &(receiver_balance + amount));
+    }
+}
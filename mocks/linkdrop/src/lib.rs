@@ -0,0 +1,21 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, AccountId, Promise, PublicKey};
+
+/// Stand-in for the `near` / `testnet` linkdrop contract used by the integration tests.
+/// Accounts are created as sub-accounts of this contract so creating one that already exists fails.
+#[near_bindgen]
+#[derive(Default, BorshDeserialize, BorshSerialize)]
+pub struct MockLinkdrop {}
+
+#[near_bindgen]
+impl MockLinkdrop {
+    /// Create the account with a full access key and send it the attached deposit.
+    /// The returned promise fails if the account can't be created.
+    #[payable]
+    pub fn create_account(&mut self, new_account_id: AccountId, new_public_key: PublicKey) -> Promise {
+        Promise::new(new_account_id)
+            .create_account()
+            .add_full_access_key(new_public_key)
+            .transfer(env::attached_deposit())
+    }
+}
@@ -0,0 +1,112 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, LookupSet};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
+use near_sdk::{
+    assert_one_yocto, env, ext_contract, near_bindgen, require, AccountId, PanicOnDefault, Promise, PromiseOrValue,
+    PromiseResult,
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonToken {
+    pub token_id: String,
+    pub owner_id: AccountId,
+}
+
+// Only the generated module is called
+#[allow(dead_code)]
+#[ext_contract(ext_nft_receiver)]
+trait NonFungibleTokenReceiver {
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: String,
+        msg: String,
+    ) -> PromiseOrValue<bool>;
+}
+
+/// Minimal NEP-171 token used by the integration tests. Anyone can mint and transfers to
+/// rejected receivers fail so refunds can be exercised.
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct MockNonFungibleToken {
+    owners: LookupMap<String, AccountId>,
+    rejected_receivers: LookupSet<AccountId>,
+}
+
+#[near_bindgen]
+impl MockNonFungibleToken {
+    #[init]
+    pub fn new() -> Self {
+        Self {
+            owners: LookupMap::new(b"o"),
+            rejected_receivers: LookupSet::new(b"r"),
+        }
+    }
+
+    pub fn nft_mint(&mut self, token_id: String, receiver_id: AccountId) {
+        require!(self.owners.insert(&token_id, &receiver_id).is_none(), "token already exists");
+    }
+
+    /// Make every transfer to the account fail from now on
+    pub fn reject_receiver(&mut self, account_id: AccountId) {
+        self.rejected_receivers.insert(&account_id);
+    }
+
+    #[payable]
+    pub fn nft_transfer(&mut self, receiver_id: AccountId, token_id: String, approval_id: Option<u64>, memo: Option<String>) {
+        assert_one_yocto();
+        let _ = (approval_id, memo);
+        self.internal_transfer(&env::predecessor_account_id(), &receiver_id, &token_id);
+    }
+
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> Promise {
+        assert_one_yocto();
+        let _ = (approval_id, memo);
+        let sender_id = env::predecessor_account_id();
+        self.internal_transfer(&sender_id, &receiver_id, &token_id);
+
+        ext_nft_receiver::ext(receiver_id.clone())
+            .nft_on_transfer(sender_id.clone(), sender_id.clone(), token_id.clone(), msg)
+            .then(Self::ext(env::current_account_id()).nft_resolve_transfer(sender_id, receiver_id, token_id))
+    }
+
+    /// Return the token to its previous owner if the receiver asked for it (or `nft_on_transfer` failed)
+    #[private]
+    pub fn nft_resolve_transfer(&mut self, previous_owner_id: AccountId, receiver_id: AccountId, token_id: String) -> bool {
+        let must_revert = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<bool>(&value).unwrap_or(true),
+            _ => true,
+        };
+        if !must_revert {
+            return true;
+        }
+
+        if self.owners.get(&token_id).as_ref() == Some(&receiver_id) {
+            self.owners.insert(&token_id, &previous_owner_id);
+        }
+        false
+    }
+
+    pub fn nft_token(&self, token_id: String) -> Option<JsonToken> {
+        self.owners.get(&token_id).map(|owner_id| JsonToken { token_id, owner_id })
+    }
+
+    fn internal_transfer(&mut self, sender_id: &AccountId, receiver_id: &AccountId, token_id: &String) {
+        let owner_id = self.owners.get(token_id).expect("token not found");
+        require!(&owner_id == sender_id, "sender does not own the token");
+        require!(!self.rejected_receivers.contains(receiver_id), "receiver rejects tokens");
+
+        self.owners.insert(token_id, receiver_id);
+    }
+}